@@ -0,0 +1,341 @@
+use crate::common::*;
+use crate::error::GameError;
+use crate::game::PlayerState;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/**
+*   a computer player. it places its fleet at random and picks targets from its view of
+*   the opponent's board (0 unknown, 1 miss, 2 hit) and the ships it has been proven to
+*   sink, so it plays through the same PlayerState calls as a person at the console.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    /// fires at random
+    Easy,
+    /// hunts on a checkerboard and fires around its hits until the ship is sunk
+    Medium,
+    /// fires at the tile most of the remaining ships could still cover
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Difficulty::Easy => 0,
+            Difficulty::Medium => 1,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<Difficulty> {
+        match id {
+            0 => Some(Difficulty::Easy),
+            1 => Some(Difficulty::Medium),
+            2 => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+/// placements covering hits that no sunk ship accounts for count this much more per hit
+const HIT_WEIGHT: u32 = 20;
+
+/// random placement starts over this many times before giving up on the fleet
+const PLACEMENT_ATTEMPTS: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bot {
+    pub difficulty: Difficulty,
+}
+
+impl Bot {
+    pub fn new(difficulty: Difficulty) -> Bot {
+        Bot { difficulty }
+    }
+
+    /**
+    *   places the rest of the player's fleet at random
+    */
+    pub fn place_fleet<R: Rng + ?Sized>(&self, player: &mut PlayerState, rng: &mut R) -> Result<(), GameError> {
+        let game = player.game();
+        let remaining = &game.fleet()[player.ships().len()..];
+        let ships = random_fleet(game.geometry(), game.rule(), remaining, player.board(), rng)
+            .ok_or_else(|| GameError::BadInput("the fleet couldn't be fitted onto the board".to_string()))?;
+        player.place_fleet(&ships)
+    }
+
+    /**
+    *   picks the next tile to attack from the player's view of the opponent's board
+    */
+    pub fn pick_target<R: Rng + ?Sized>(&self, player: &PlayerState, rng: &mut R) -> Result<u16, GameError> {
+        let game = player.game();
+        choose_target(self.difficulty, game.geometry(), game.fleet(), player.view(), player.sunk_ships(), rng)
+            .ok_or_else(|| GameError::BadInput("every tile has already been attacked".to_string()))
+    }
+
+    /**
+    *   picks every tile of the player's next salvo, each one as if the ones before it missed
+    */
+    pub fn pick_salvo<R: Rng + ?Sized>(&self, player: &PlayerState, rng: &mut R) -> Result<Vec<u16>, GameError> {
+        let game = player.game();
+        choose_salvo(self.difficulty, game.geometry(), game.fleet(), player.view(), player.sunk_ships(), player.salvo_size(), rng)
+            .ok_or_else(|| GameError::BadInput("every tile has already been attacked".to_string()))
+    }
+}
+
+/**
+*   picks a random legal placement for each ship in turn around the tiles already taken,
+*   keeping clear of them as far as the rule says, and starts over when a ship no longer
+*   fits. None if no attempt fits the whole fleet
+*/
+pub fn random_fleet<R: Rng + ?Sized>(geometry: BoardGeometry, rule: AdjacencyRule, fleet: &[u8], taken: &[u8], rng: &mut R) -> Option<Vec<Ship>> {
+    // the tiles next to each tile another ship may not be on
+    let mut near = vec![vec![]; geometry.tiles()];
+    for (t, u, diagonal) in neighbours(geometry) {
+        if rule.forbids(diagonal) {
+            near[t].push(u);
+            near[u].push(t);
+        }
+    }
+
+    'attempt: for _ in 0..PLACEMENT_ATTEMPTS {
+        let mut board: Vec<bool> = taken.iter().map(|tile| *tile != 0).collect();
+        let mut ships = Vec::with_capacity(fleet.len());
+        for length in fleet {
+            let free: Vec<(Ship, Vec<usize>)> = legal_placements(*length, geometry).into_iter()
+                .filter_map(|ship| ship_tiles(&ship, *length, geometry).map(|tiles| (ship, tiles)))
+                .filter(|(_, tiles)| tiles.iter().all(|t| !board[*t] && near[*t].iter().all(|n| !board[*n])))
+                .collect();
+            let (ship, tiles) = match free.choose(rng) {
+                Some(placement) => placement,
+                None => continue 'attempt,
+            };
+            for t in tiles {
+                board[*t] = true;
+            }
+            ships.push(*ship);
+        }
+        return Some(ships);
+    }
+    None
+}
+
+/**
+*   for each tile, how many placements of the ships still afloat could cover it given the
+*   misses so far. while there are hits no sunk ship accounts for, placements through them
+*   weigh HIT_WEIGHT more per hit, so the bot finishes off a ship it has found
+*/
+pub fn heatmap(geometry: BoardGeometry, fleet: &[u8], view: &[u8], sunk: &[u8]) -> Vec<u32> {
+    let sunk_tiles: usize = sunk.iter().map(|ship| fleet[*ship as usize] as usize).sum();
+    let unresolved = view.iter().filter(|seen| **seen == 2).count() > sunk_tiles;
+
+    let mut density = vec![0u32; geometry.tiles()];
+    let afloat = fleet.iter().enumerate().filter(|(i, _)| !sunk.contains(&(*i as u8)));
+    for (_, length) in afloat {
+        for ship in legal_placements(*length, geometry) {
+            let tiles = match ship_tiles(&ship, *length, geometry) {
+                Some(tiles) => tiles,
+                None => continue,
+            };
+            if tiles.iter().any(|t| view[*t] == 1) {
+                continue;
+            }
+            let covered = tiles.iter().filter(|t| view[**t] == 2).count() as u32;
+            // with every hit accounted for, a ship afloat can't lie across one
+            if !unresolved && covered > 0 {
+                continue;
+            }
+            let weight = 1 + HIT_WEIGHT * covered;
+            for t in tiles.iter().filter(|t| view[**t] == 0) {
+                density[*t] += weight;
+            }
+        }
+    }
+    density
+}
+
+/**
+*   picks `shots` different tiles the view hasn't attacked yet, each one as if the ones
+*   before it missed. None if there aren't that many left
+*/
+pub fn choose_salvo<R: Rng + ?Sized>(difficulty: Difficulty, geometry: BoardGeometry, fleet: &[u8], view: &[u8], sunk: &[u8], shots: usize, rng: &mut R) -> Option<Vec<u16>> {
+    let mut view = view.to_vec();
+    let mut targets = vec![];
+    for _ in 0..shots {
+        let target = choose_target(difficulty, geometry, fleet, &view, sunk, rng)?;
+        view[target as usize] = 1;
+        targets.push(target);
+    }
+    Some(targets)
+}
+
+/**
+*   picks a tile the view hasn't attacked yet, None once every tile has been attacked
+*/
+pub fn choose_target<R: Rng + ?Sized>(difficulty: Difficulty, geometry: BoardGeometry, fleet: &[u8], view: &[u8], sunk: &[u8], rng: &mut R) -> Option<u16> {
+    let unknown: Vec<u16> = (0..view.len() as u16).filter(|t| view[*t as usize] == 0).collect();
+    let candidates = match difficulty {
+        Difficulty::Easy => unknown.clone(),
+        Difficulty::Medium => hunt_or_target(geometry, fleet, view, sunk, &unknown),
+        Difficulty::Hard => {
+            let density = heatmap(geometry, fleet, view, sunk);
+            let best = unknown.iter().map(|t| density[*t as usize]).max().unwrap_or(0);
+            unknown.iter().copied().filter(|t| best > 0 && density[*t as usize] == best).collect()
+        }
+    };
+
+    match candidates.choose(rng) {
+        Some(target) => Some(*target),
+        None => unknown.choose(rng).copied(),
+    }
+}
+
+/**
+*   the tiles next to a hit no sunk ship accounts for, or else the unknown tiles on a
+*   checkerboard spaced by the shortest ship afloat, which every ship afloat must cross
+*/
+fn hunt_or_target(geometry: BoardGeometry, fleet: &[u8], view: &[u8], sunk: &[u8], unknown: &[u16]) -> Vec<u16> {
+    let width = geometry.width as usize;
+    let sunk_tiles: usize = sunk.iter().map(|ship| fleet[*ship as usize] as usize).sum();
+    if view.iter().filter(|seen| **seen == 2).count() > sunk_tiles {
+        return unknown.iter().copied().filter(|t| {
+            let (row, col) = (*t as usize / width, *t as usize % width);
+            let mut neighbours = vec![];
+            if col > 0 { neighbours.push(*t as usize - 1); }
+            if col + 1 < width { neighbours.push(*t as usize + 1); }
+            if row > 0 { neighbours.push(*t as usize - width); }
+            if row + 1 < geometry.height as usize { neighbours.push(*t as usize + width); }
+            neighbours.iter().any(|n| view[*n] == 2)
+        }).collect();
+    }
+
+    let shortest = fleet.iter().enumerate()
+        .filter(|(i, _)| !sunk.contains(&(*i as u8)))
+        .map(|(_, length)| *length as usize)
+        .min()
+        .unwrap_or(1);
+    unknown.iter().copied().filter(|t| (*t as usize / width + *t as usize % width).is_multiple_of(shortest)).collect()
+}
+
+/**
+*   plays a bot against a random board with no proofs, returning how many shots it took
+*   to sink the fleet
+*/
+#[cfg(test)]
+fn shots_to_win(difficulty: Difficulty, seed: u64) -> usize {
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let geometry = BoardGeometry::square(10);
+    let fleet = vec![5, 4, 3, 3, 2];
+    let ships = random_fleet(geometry, AdjacencyRule::AnythingGoes, &fleet, &[0; 100], &mut rng).unwrap();
+    let board = fleet_board(&ships, &fleet, geometry).unwrap();
+
+    let mut view = vec![0u8; geometry.tiles()];
+    let mut sunk = vec![];
+    for shots in 1..=geometry.tiles() {
+        let target = choose_target(difficulty, geometry, &fleet, &view, &sunk, &mut rng).unwrap() as usize;
+        assert_eq!(view[target], 0, "fired at tile {} twice", target);
+        view[target] = if board[target] != 0 { 2 } else { 1 };
+
+        let number = board[target];
+        if number != 0 && board.iter().zip(&view).all(|(tile, seen)| *tile != number || *seen == 2) {
+            sunk.push(number - 1);
+        }
+        if sunk.len() == fleet.len() {
+            return shots;
+        }
+    }
+    panic!("the fleet was never sunk");
+}
+
+#[test]
+fn test_random_fleet() {
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let geometry = BoardGeometry::new(7, 5);
+    let fleet = [5, 4, 3, 3, 2, 2];
+    for _ in 0..50 {
+        let ships = random_fleet(geometry, AdjacencyRule::AnythingGoes, &fleet, &[0; 35], &mut rng).unwrap();
+        assert!(fleet_board(&ships, &fleet, geometry).is_some());
+    }
+
+    // placement works around ships already on the board, and gives up when nothing fits
+    let mut taken = vec![0u8; 9];
+    taken[4] = 1;
+    let ships = random_fleet(BoardGeometry::square(3), AdjacencyRule::AnythingGoes, &[2, 2], &taken, &mut rng).unwrap();
+    for (ship, length) in ships.iter().zip([2, 2]) {
+        assert!(!ship_tiles(ship, length, BoardGeometry::square(3)).unwrap().contains(&4));
+    }
+    assert!(random_fleet(BoardGeometry::square(3), AdjacencyRule::AnythingGoes, &[3, 3, 3], &taken, &mut rng).is_none());
+
+    // and keeps the ships apart as far as the rule says
+    let geometry = BoardGeometry::square(10);
+    for rule in [AdjacencyRule::NoTouch, AdjacencyRule::NoDiagonalTouch] {
+        for _ in 0..20 {
+            let ships = random_fleet(geometry, rule, &fleet, &[0; 100], &mut rng).unwrap();
+            assert_eq!(rule_broken(&fleet_board(&ships, &fleet, geometry).unwrap(), geometry, rule), None);
+        }
+    }
+    assert!(random_fleet(BoardGeometry::square(3), AdjacencyRule::NoDiagonalTouch, &[3, 3], &[0; 9], &mut rng).is_some());
+    assert!(random_fleet(BoardGeometry::square(3), AdjacencyRule::NoTouch, &[3, 3, 3], &[0; 9], &mut rng).is_none());
+}
+
+#[test]
+fn test_heatmap() {
+    let geometry = BoardGeometry::square(3);
+
+    // a ship of length 3 fits twice through the centre and once through a corner
+    let density = heatmap(geometry, &[3], &[0; 9], &[]);
+    assert_eq!(density[4], 2);
+    assert_eq!(density[0], 2);
+    assert_eq!(density[1], 2);
+
+    // a miss rules out every placement through it
+    let mut view = vec![0u8; 9];
+    view[4] = 1;
+    let density = heatmap(geometry, &[3], &view, &[]);
+    assert_eq!(density[1], 1);
+    assert_eq!(density[0], 2);
+
+    // an unaccounted hit pulls the fire next to it, a sunk one doesn't
+    let mut view = vec![0u8; 9];
+    view[0] = 2;
+    let density = heatmap(geometry, &[2, 2], &view, &[]);
+    assert!(density[1] > density[4]);
+    let density = heatmap(geometry, &[2, 2], &view, &[0]);
+    assert_eq!(density[1], 2);
+    assert_eq!(choose_target(Difficulty::Hard, geometry, &[2, 2], &view, &[], &mut rand::thread_rng()).map(|t| t == 1 || t == 3), Some(true));
+}
+
+#[test]
+fn test_bots_sink_the_fleet() {
+    let games = 20;
+    let mut totals = vec![];
+    for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+        totals.push((0..games).map(|seed| shots_to_win(difficulty, seed)).sum::<usize>());
+    }
+
+    // each level needs fewer shots on average than the one below it
+    assert!(totals[0] > totals[1], "{:?}", totals);
+    assert!(totals[1] > totals[2], "{:?}", totals);
+}
@@ -0,0 +1,970 @@
+use crate::common::*;
+use crate::commitment::{CircuitField, CommitmentBackend};
+use crate::error::GameError;
+use crate::game::Game;
+use crate::keys::{board_circuit, remaining_circuit, salvo_circuit, shot_circuit, sunk_circuit, GameKeys};
+use crate::proof_system::Groth16;
+use ark_bls12_381::Bls12_381;
+use ark_ec::msm::VariableBaseMSM;
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain, Radix2EvaluationDomain};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use blake2::{Blake2s, Digest};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/**
+*   a multi-party ceremony for a game's groth16 keys, the board, shot, sunk, remaining and
+*   any salvo circuit's, so that nobody can forge a proof unless every contributor colluded.
+*
+*   `setup` draws all of the keys' secrets from one machine's randomness, and whoever ran
+*   it could prove anything. here the secrets are built up by contributors in turn, each
+*   multiplying in randomness of their own and throwing it away, in two phases:
+*
+*   - phase one is a powers of tau: the powers of τ and of α·τ and β·τ in both groups,
+*     with τ, α and β the products of every contribution. it only depends on the size of
+*     the largest circuit's evaluation domain, and a smaller circuit uses the first powers.
+*   - once phase one is sealed every circuit's keys are computed from it, with δ = 1, and
+*     phase two contributions each multiply every circuit's δ by a secret of their own and
+*     divide the queries that hold 1/δ by it.
+*
+*   every contribution comes with a proof of knowledge of its secret, bound to the
+*   contributions before it, and the file keeps all of them, so anyone can replay the
+*   ceremony with `verify` and check the keys at the end are the product of every
+*   contribution. each contributor is given the digest of the ceremony after their
+*   contribution as a receipt, and finds it in the verified ceremony to know theirs is in.
+*   γ is left at 1, as only δ has to be secret.
+*/
+pub const CEREMONY_MAGIC: &[u8; 4] = b"ZKBC";
+const CEREMONY_VERSION: u8 = 1;
+
+/**
+*   the phase one accumulator for a domain of n elements. the powers of τ go up to 2n - 2
+*   in g1 for the h query, the rest up to n - 1
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Powers<E: PairingEngine> {
+    pub tau_g1: Vec<E::G1Affine>,
+    pub tau_g2: Vec<E::G2Affine>,
+    pub alpha_tau_g1: Vec<E::G1Affine>,
+    pub beta_tau_g1: Vec<E::G1Affine>,
+    pub beta_g2: E::G2Affine,
+}
+
+impl<E: PairingEngine> Powers<E> {
+    /// the accumulator before any contribution, with τ, α and β all 1
+    pub fn new(n: usize) -> Powers<E> {
+        let g1 = E::G1Affine::prime_subgroup_generator();
+        let g2 = E::G2Affine::prime_subgroup_generator();
+        Powers {
+            tau_g1: vec![g1; 2 * n - 1],
+            tau_g2: vec![g2; n],
+            alpha_tau_g1: vec![g1; n],
+            beta_tau_g1: vec![g1; n],
+            beta_g2: g2,
+        }
+    }
+
+    /// the size of the domain the powers are for
+    pub fn domain_size(&self) -> usize {
+        self.tau_g2.len()
+    }
+
+    fn apply(&mut self, tau: E::Fr, alpha: E::Fr, beta: E::Fr) {
+        let mut powers = Vec::with_capacity(self.tau_g1.len());
+        let mut power = E::Fr::one();
+        for _ in 0..self.tau_g1.len() {
+            powers.push(power);
+            power *= tau;
+        }
+        let n = self.domain_size();
+        let alpha_powers: Vec<E::Fr> = powers[..n].iter().map(|power| *power * alpha).collect();
+        let beta_powers: Vec<E::Fr> = powers[..n].iter().map(|power| *power * beta).collect();
+
+        self.tau_g1 = scale(&self.tau_g1, &powers);
+        self.tau_g2 = scale(&self.tau_g2, &powers[..n]);
+        self.alpha_tau_g1 = scale(&self.alpha_tau_g1, &alpha_powers);
+        self.beta_tau_g1 = scale(&self.beta_tau_g1, &beta_powers);
+        self.beta_g2 = self.beta_g2.mul(beta.into_repr()).into_affine();
+    }
+
+    /// τ, α and β in g1, the elements each phase one contribution's proofs are about
+    fn secrets(&self) -> Vec<E::G1Affine> {
+        vec![self.tau_g1[1], self.alpha_tau_g1[0], self.beta_tau_g1[0]]
+    }
+
+    /**
+    *   whether every list holds successive powers of the same τ, starting from the
+    *   generators, with α and β the same in both groups
+    */
+    fn is_well_formed<R: RngCore + CryptoRng>(&self, rng: &mut R) -> bool {
+        let n = self.domain_size();
+        let g1 = E::G1Affine::prime_subgroup_generator();
+        let g2 = E::G2Affine::prime_subgroup_generator();
+        if n < 2 || self.tau_g1.len() != 2 * n - 1 || self.alpha_tau_g1.len() != n || self.beta_tau_g1.len() != n {
+            return false;
+        }
+        if self.tau_g1[0] != g1 || self.tau_g2[0] != g2 {
+            return false;
+        }
+        let tau_g2 = self.tau_g2[1];
+        same_ratio::<E>((g1, self.tau_g1[1]), (g2, tau_g2))
+            && same_ratio::<E>((self.beta_tau_g1[0], g1), (self.beta_g2, g2))
+            && successive_g1::<E, _>(&self.tau_g1, tau_g2, rng)
+            && successive_g1::<E, _>(&self.alpha_tau_g1, tau_g2, rng)
+            && successive_g1::<E, _>(&self.beta_tau_g1, tau_g2, rng)
+            && successive_g2::<E, _>(&self.tau_g2, self.tau_g1[1], rng)
+    }
+}
+
+/// each point times its scalar
+fn scale<G: AffineCurve>(points: &[G], scalars: &[G::ScalarField]) -> Vec<G> {
+    let scaled: Vec<G::Projective> = points.iter().zip(scalars).map(|(point, scalar)| point.mul(scalar.into_repr())).collect();
+    G::Projective::batch_normalization_into_affine(&scaled)
+}
+
+/// whether b = x·a and d = x·c for the same x
+fn same_ratio<E: PairingEngine>(g1: (E::G1Affine, E::G1Affine), g2: (E::G2Affine, E::G2Affine)) -> bool {
+    E::pairing(g1.0, g2.1) == E::pairing(g1.1, g2.0)
+}
+
+/// the same random combination of every point but the last, and of every point but the first
+fn shifted_combinations<G: AffineCurve, R: RngCore + CryptoRng>(points: &[G], rng: &mut R) -> (G, G) {
+    let weights: Vec<_> = (1..points.len()).map(|_| G::ScalarField::rand(rng).into_repr()).collect();
+    let below = VariableBaseMSM::multi_scalar_mul(&points[..points.len() - 1], &weights);
+    let above = VariableBaseMSM::multi_scalar_mul(&points[1..], &weights);
+    (below.into_affine(), above.into_affine())
+}
+
+/// whether each point in g1 is τ times the one before, checked all at once
+fn successive_g1<E: PairingEngine, R: RngCore + CryptoRng>(points: &[E::G1Affine], tau_g2: E::G2Affine, rng: &mut R) -> bool {
+    let (below, above) = shifted_combinations(points, rng);
+    same_ratio::<E>((below, above), (E::G2Affine::prime_subgroup_generator(), tau_g2))
+}
+
+/// whether each point in g2 is τ times the one before, checked all at once
+fn successive_g2<E: PairingEngine, R: RngCore + CryptoRng>(points: &[E::G2Affine], tau_g1: E::G1Affine, rng: &mut R) -> bool {
+    let (below, above) = shifted_combinations(points, rng);
+    same_ratio::<E>((E::G1Affine::prime_subgroup_generator(), tau_g1), (below, above))
+}
+
+/**
+*   a contributor's proof that they know the x they multiplied a secret by: a random s
+*   with x·s, and x·r for a point r in g2 hashed from them and the ceremony so far, whose
+*   discrete log nobody knows. the proof can't be copied from an earlier contribution or
+*   made for a secret someone else chose
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Knowledge<E: PairingEngine> {
+    pub s: E::G1Affine,
+    pub s_x: E::G1Affine,
+    pub r_x: E::G2Affine,
+}
+
+impl<E: PairingEngine> Knowledge<E> {
+    fn prove<R: RngCore + CryptoRng>(x: E::Fr, digest: &[u8], rng: &mut R) -> Result<Knowledge<E>, GameError> {
+        let s = E::G1Projective::rand(rng).into_affine();
+        let s_x = s.mul(x.into_repr()).into_affine();
+        let r_x = hash_to_g2::<E>(digest, &s, &s_x)?.mul(x.into_repr()).into_affine();
+        Ok(Knowledge { s, s_x, r_x })
+    }
+
+    /// whether the contributor knew the x that takes `before` to `after`
+    fn check(&self, digest: &[u8], before: E::G1Affine, after: E::G1Affine) -> Result<bool, GameError> {
+        if self.s.is_zero() || after.is_zero() {
+            return Ok(false);
+        }
+        let r = hash_to_g2::<E>(digest, &self.s, &self.s_x)?;
+        Ok(same_ratio::<E>((self.s, self.s_x), (r, self.r_x)) && same_ratio::<E>((before, after), (r, self.r_x)))
+    }
+}
+
+/// a point in g2 drawn from a rng seeded with the blake2s hash of the inputs
+fn hash_to_g2<E: PairingEngine>(digest: &[u8], s: &E::G1Affine, s_x: &E::G1Affine) -> Result<E::G2Affine, GameError> {
+    let mut bytes = digest.to_vec();
+    s.serialize(&mut bytes)?;
+    s_x.serialize(&mut bytes)?;
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&Blake2s::digest(&bytes));
+    Ok(E::G2Projective::rand(&mut ChaCha20Rng::from_seed(seed)).into_affine())
+}
+
+/**
+*   one contribution: the secrets in g1 after it (τ, α and β in phase one, δ in phase
+*   two) and a proof of knowledge of what each was multiplied by
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contribution<E: PairingEngine> {
+    pub after: Vec<E::G1Affine>,
+    pub knowledge: Vec<Knowledge<E>>,
+}
+
+impl<E: PairingEngine> CanonicalSerialize for Contribution<E> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.after.serialize(&mut writer)?;
+        (self.knowledge.len() as u8).serialize(&mut writer)?;
+        for knowledge in &self.knowledge {
+            knowledge.s.serialize(&mut writer)?;
+            knowledge.s_x.serialize(&mut writer)?;
+            knowledge.r_x.serialize(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.after.serialized_size() + 1 + self.knowledge.iter()
+            .map(|knowledge| knowledge.s.serialized_size() + knowledge.s_x.serialized_size() + knowledge.r_x.serialized_size()).sum::<usize>()
+    }
+}
+
+impl<E: PairingEngine> CanonicalDeserialize for Contribution<E> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Contribution<E>, SerializationError> {
+        let after = Vec::<E::G1Affine>::deserialize(&mut reader)?;
+        let mut knowledge = vec![];
+        for _ in 0..u8::deserialize(&mut reader)? {
+            knowledge.push(Knowledge {
+                s: E::G1Affine::deserialize(&mut reader)?,
+                s_x: E::G1Affine::deserialize(&mut reader)?,
+                r_x: E::G2Affine::deserialize(&mut reader)?,
+            });
+        }
+        Ok(Contribution { after, knowledge })
+    }
+}
+
+/// the digest of the ceremony after this contribution
+fn chain<E: PairingEngine>(digest: &[u8], contribution: &Contribution<E>) -> Result<Vec<u8>, GameError> {
+    let mut bytes = digest.to_vec();
+    contribution.serialize(&mut bytes)?;
+    Ok(Blake2s::digest(&bytes).to_vec())
+}
+
+/// a random secret, which must not be zero
+fn secret<F: PrimeField, R: RngCore + CryptoRng>(rng: &mut R) -> F {
+    loop {
+        let x = F::rand(rng);
+        if !x.is_zero() {
+            return x;
+        }
+    }
+}
+
+/**
+*   the circuit's r1cs matrices, built the way ark-groth16 builds them for setup and
+*   proving so the keys line up with its prover
+*/
+pub fn matrices<F: PrimeField, C: ConstraintSynthesizer<F>>(circuit: C) -> Result<ConstraintMatrices<F>, GameError> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    cs.to_matrices().ok_or_else(|| GameError::Proving("the circuit has no matrices".to_string()))
+}
+
+/// the evaluation domain ark-groth16 uses for the circuit, which has to be a power of two
+fn domain<F: PrimeField>(matrices: &ConstraintMatrices<F>) -> Result<Radix2EvaluationDomain<F>, GameError> {
+    match GeneralEvaluationDomain::<F>::new(matrices.num_constraints + matrices.num_instance_variables) {
+        Some(GeneralEvaluationDomain::Radix2(domain)) => Ok(domain),
+        _ => Err(GameError::BadInput("the circuit is too large for a ceremony".to_string())),
+    }
+}
+
+/**
+*   the lagrange basis of the domain at τ, from the powers of τ: an inverse fft carried out
+*   on the points
+*/
+fn lagrange<G: AffineCurve>(domain: &Radix2EvaluationDomain<G::ScalarField>, powers: &[G]) -> Vec<G::Projective> {
+    let n = domain.size();
+    let mut points: Vec<G::Projective> = powers[..n].iter().map(|point| point.into_projective()).collect();
+    let bits = domain.log_size_of_group;
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            points.swap(i, j);
+        }
+    }
+
+    let mut half = 1;
+    while half < n {
+        let root = domain.group_gen_inv.pow([(n / (2 * half)) as u64]);
+        for start in (0..n).step_by(2 * half) {
+            let mut twiddle = G::ScalarField::one();
+            for i in start..start + half {
+                let odd = if i == start { points[i + half] } else { points[i + half].mul(twiddle.into_repr()) };
+                let even = points[i];
+                points[i] = even + odd;
+                points[i + half] = even - odd;
+                twiddle *= root;
+            }
+        }
+        half *= 2;
+    }
+    points.iter().map(|point| point.mul(domain.size_inv.into_repr())).collect()
+}
+
+/// the point times the coefficient, skipping the multiplication for the common 1
+fn term<G: ProjectiveCurve>(point: &G, coefficient: &G::ScalarField) -> G {
+    if coefficient.is_one() {
+        *point
+    } else {
+        point.mul(coefficient.into_repr())
+    }
+}
+
+/**
+*   the groth16 keys of the circuit with these matrices at the τ, α and β of the powers,
+*   with γ and δ 1. these are the keys ark-groth16's generator would make with those
+*   secrets, computed from the points alone
+*/
+pub fn circuit_keys<E: PairingEngine>(powers: &Powers<E>, matrices: &ConstraintMatrices<E::Fr>) -> Result<ProvingKey<E>, GameError> {
+    let domain = domain(matrices)?;
+    let n = domain.size();
+    if n > powers.domain_size() {
+        return Err(GameError::BadInput(format!("the powers are for a domain of {} elements, the circuit needs {}", powers.domain_size(), n)));
+    }
+    let lagrange_g1 = lagrange(&domain, &powers.tau_g1);
+    let lagrange_g2 = lagrange(&domain, &powers.tau_g2);
+    let alpha_lagrange = lagrange(&domain, &powers.alpha_tau_g1);
+    let beta_lagrange = lagrange(&domain, &powers.beta_tau_g1);
+
+    // a, b and β·a + α·b + c of every variable at τ
+    let (constraints, instances) = (matrices.num_constraints, matrices.num_instance_variables);
+    let variables = instances + matrices.num_witness_variables;
+    let mut a = vec![E::G1Projective::zero(); variables];
+    let mut b_g1 = vec![E::G1Projective::zero(); variables];
+    let mut b_g2 = vec![E::G2Projective::zero(); variables];
+    let mut abc = vec![E::G1Projective::zero(); variables];
+    for row in 0..constraints {
+        for (coefficient, variable) in &matrices.a[row] {
+            a[*variable] += term(&lagrange_g1[row], coefficient);
+            abc[*variable] += term(&beta_lagrange[row], coefficient);
+        }
+        for (coefficient, variable) in &matrices.b[row] {
+            b_g1[*variable] += term(&lagrange_g1[row], coefficient);
+            b_g2[*variable] += term(&lagrange_g2[row], coefficient);
+            abc[*variable] += term(&alpha_lagrange[row], coefficient);
+        }
+        for (coefficient, variable) in &matrices.c[row] {
+            abc[*variable] += term(&lagrange_g1[row], coefficient);
+        }
+    }
+    // ark-groth16 gives every public input a row of its own after the constraints
+    for input in 0..instances {
+        a[input] += lagrange_g1[constraints + input];
+        abc[input] += beta_lagrange[constraints + input];
+    }
+
+    // τ^i·t(τ) for the vanishing polynomial t(x) = x^n - 1
+    let h: Vec<E::G1Projective> = (0..n - 1).map(|i| powers.tau_g1[i + n].into_projective() - powers.tau_g1[i].into_projective()).collect();
+
+    let g1 = E::G1Affine::prime_subgroup_generator();
+    let g2 = E::G2Affine::prime_subgroup_generator();
+    let mut abc = E::G1Projective::batch_normalization_into_affine(&abc);
+    let l_query = abc.split_off(instances);
+    Ok(ProvingKey {
+        vk: VerifyingKey {
+            alpha_g1: powers.alpha_tau_g1[0],
+            beta_g2: powers.beta_g2,
+            gamma_g2: g2,
+            delta_g2: g2,
+            gamma_abc_g1: abc,
+        },
+        beta_g1: powers.beta_tau_g1[0],
+        delta_g1: g1,
+        a_query: E::G1Projective::batch_normalization_into_affine(&a),
+        b_g1_query: E::G1Projective::batch_normalization_into_affine(&b_g1),
+        b_g2_query: E::G2Projective::batch_normalization_into_affine(&b_g2),
+        h_query: E::G1Projective::batch_normalization_into_affine(&h),
+        l_query,
+    })
+}
+
+
+/// multiplies δ by the secret, dividing the queries that hold 1/δ by it
+fn apply_delta<E: PairingEngine>(keys: &mut ProvingKey<E>, delta: E::Fr) -> Result<(), GameError> {
+    let inverse = delta.inverse().ok_or_else(|| GameError::BadInput("δ can't be zero".to_string()))?;
+    keys.delta_g1 = keys.delta_g1.mul(delta.into_repr()).into_affine();
+    keys.vk.delta_g2 = keys.vk.delta_g2.mul(delta.into_repr()).into_affine();
+    keys.h_query = scale(&keys.h_query, &vec![inverse; keys.h_query.len()]);
+    keys.l_query = scale(&keys.l_query, &vec![inverse; keys.l_query.len()]);
+    Ok(())
+}
+
+/// the points weighted by the scalars and added up
+fn combination<G: AffineCurve>(points: &[G], weights: &[G::ScalarField]) -> G::Projective {
+    let weights: Vec<_> = weights.iter().map(|weight| weight.into_repr()).collect();
+    VariableBaseMSM::multi_scalar_mul(points, &weights)
+}
+
+/**
+*   whether the keys are `circuit_keys` of the powers with δ changed to the one in
+*   delta_g1. rather than computing the keys again, which takes the inverse ffts on points
+*   that make sealing slow, each query is checked with one random combination: a random
+*   weighting of the variables is the same weighting of the rows of each matrix, which an
+*   fft in the field turns into coefficients for the powers of τ
+*/
+fn is_circuit_keys<E: PairingEngine, R: RngCore + CryptoRng>(powers: &Powers<E>, matrices: &ConstraintMatrices<E::Fr>, keys: &ProvingKey<E>, rng: &mut R)
+    -> Result<bool, GameError> {
+    let domain = domain(matrices)?;
+    let n = domain.size();
+    let (constraints, instances) = (matrices.num_constraints, matrices.num_instance_variables);
+    let variables = instances + matrices.num_witness_variables;
+    let g1 = E::G1Affine::prime_subgroup_generator();
+    let g2 = E::G2Affine::prime_subgroup_generator();
+    let shaped = n <= powers.domain_size() && keys.a_query.len() == variables && keys.b_g1_query.len() == variables
+        && keys.b_g2_query.len() == variables && keys.vk.gamma_abc_g1.len() == instances && keys.l_query.len() == variables - instances
+        && keys.h_query.len() == n - 1;
+    let unchanged = keys.vk.alpha_g1 == powers.alpha_tau_g1[0] && keys.beta_g1 == powers.beta_tau_g1[0] && keys.vk.beta_g2 == powers.beta_g2
+        && keys.vk.gamma_g2 == g2;
+    if !shaped || !unchanged || keys.delta_g1.is_zero() || !same_ratio::<E>((g1, keys.delta_g1), (g2, keys.vk.delta_g2)) {
+        return Ok(false);
+    }
+
+    let weights: Vec<E::Fr> = (0..variables).map(|_| E::Fr::rand(rng)).collect();
+    let rows = |matrix: &[Vec<(E::Fr, usize)>]| {
+        let mut rows = vec![E::Fr::zero(); n];
+        for (row, terms) in matrix.iter().enumerate() {
+            for (coefficient, variable) in terms {
+                rows[row] += *coefficient * weights[*variable];
+            }
+        }
+        rows
+    };
+    let mut a = rows(&matrices.a);
+    for input in 0..instances {
+        a[constraints + input] += weights[input];
+    }
+    let a = domain.ifft(&a);
+    let b = domain.ifft(&rows(&matrices.b));
+    let c = domain.ifft(&rows(&matrices.c));
+
+    // β·a + α·b + c is split between the public inputs, over γ = 1, and the witness over δ
+    let tau_g1 = &powers.tau_g1[..n];
+    let abc = combination(&powers.beta_tau_g1[..n], &a) + combination(&powers.alpha_tau_g1[..n], &b) + combination(tau_g1, &c)
+        - combination(&keys.vk.gamma_abc_g1, &weights[..instances]);
+    let l = combination(&keys.l_query, &weights[instances..]);
+
+    // and τ^i·t(τ) over δ
+    let h_weights: Vec<E::Fr> = (1..n).map(|_| E::Fr::rand(rng)).collect();
+    let t = combination(&powers.tau_g1[n..2 * n - 1], &h_weights) - combination(&powers.tau_g1[..n - 1], &h_weights);
+    let h = combination(&keys.h_query, &h_weights);
+
+    Ok(combination(&keys.a_query, &weights) == combination(tau_g1, &a)
+        && combination(&keys.b_g1_query, &weights) == combination(tau_g1, &b)
+        && combination(&keys.b_g2_query, &weights) == combination(&powers.tau_g2[..n], &b)
+        && same_ratio::<E>((l.into_affine(), abc.into_affine()), (g2, keys.vk.delta_g2))
+        && same_ratio::<E>((h.into_affine(), t.into_affine()), (g2, keys.vk.delta_g2)))
+}
+
+/// the digest of the ceremony after each contribution, in order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Receipts {
+    pub phase_one: Vec<Vec<u8>>,
+    pub phase_two: Vec<Vec<u8>>,
+}
+
+/**
+*   both phases of a ceremony for the keys of a list of circuits, given by their matrices,
+*   which any circuits can go through. the contributions' proofs are bound to a start
+*   digest naming what the keys are for, so they can't be carried over to a ceremony for
+*   something else
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Phases<E: PairingEngine> {
+    pub powers: Powers<E>,
+    pub phase_one: Vec<Contribution<E>>,
+    /// None until phase one is sealed, then each circuit's keys with every phase two
+    /// contribution applied
+    pub keys: Option<Vec<ProvingKey<E>>>,
+    pub phase_two: Vec<Contribution<E>>,
+}
+
+impl<E: PairingEngine> Phases<E> {
+    /// the phases for the circuits before any contribution, with powers for the largest
+    pub fn new(circuits: &[ConstraintMatrices<E::Fr>]) -> Result<Phases<E>, GameError> {
+        let mut n = 2;
+        for matrices in circuits {
+            n = n.max(domain(matrices)?.size());
+        }
+        Ok(Phases {
+            powers: Powers::new(n),
+            phase_one: vec![],
+            keys: None,
+            phase_two: vec![],
+        })
+    }
+
+    /// the digest of the ceremony so far, which the next contribution is bound to
+    fn digest(&self, start: &[u8]) -> Result<Vec<u8>, GameError> {
+        let mut digest = start.to_vec();
+        for contribution in self.phase_one.iter().chain(&self.phase_two) {
+            digest = chain(&digest, contribution)?;
+        }
+        Ok(digest)
+    }
+
+    /**
+    *   adds a contribution drawn from the rng to phase one, or to phase two once phase one
+    *   is sealed, returning its receipt. the secrets are dropped as soon as they are used,
+    *   but the rng could recreate them, so it must be one nobody else can know
+    */
+    pub fn contribute<R: RngCore + CryptoRng>(&mut self, start: &[u8], rng: &mut R) -> Result<Vec<u8>, GameError> {
+        let digest = self.digest(start)?;
+        let contribution = match &mut self.keys {
+            None => {
+                let (tau, alpha, beta) = (secret(rng), secret(rng), secret(rng));
+                self.powers.apply(tau, alpha, beta);
+                Contribution {
+                    after: self.powers.secrets(),
+                    knowledge: vec![Knowledge::prove(tau, &digest, rng)?, Knowledge::prove(alpha, &digest, rng)?, Knowledge::prove(beta, &digest, rng)?],
+                }
+            }
+            Some(keys) => {
+                // a δ of its own for each circuit
+                let mut contribution = Contribution { after: vec![], knowledge: vec![] };
+                for keys in keys.iter_mut() {
+                    let delta = secret(rng);
+                    apply_delta(keys, delta)?;
+                    contribution.after.push(keys.delta_g1);
+                    contribution.knowledge.push(Knowledge::prove(delta, &digest, rng)?);
+                }
+                contribution
+            }
+        };
+        let receipt = chain(&digest, &contribution)?;
+        match self.keys {
+            None => self.phase_one.push(contribution),
+            Some(_) => self.phase_two.push(contribution),
+        }
+        Ok(receipt)
+    }
+
+    /**
+    *   ends phase one and computes each circuit's keys from its powers. anyone can do it,
+    *   as the keys only depend on the powers and `verify` checks them against the powers,
+    *   but it is by far the slowest step
+    */
+    pub fn seal(&mut self, circuits: &[ConstraintMatrices<E::Fr>]) -> Result<(), GameError> {
+        if self.keys.is_some() {
+            return Err(GameError::BadInput("phase one is already sealed".to_string()));
+        }
+        if self.phase_one.is_empty() {
+            return Err(GameError::BadInput("phase one needs a contribution before it is sealed".to_string()));
+        }
+        self.keys = Some(circuits.iter().map(|matrices| circuit_keys(&self.powers, matrices)).collect::<Result<_, _>>()?);
+        Ok(())
+    }
+
+    /**
+    *   replays the ceremony, checking every contribution's proofs and that the powers and
+    *   keys are what the contributions made of them. returns every contribution's receipt,
+    *   or a ProofFailure naming the first contribution that doesn't verify
+    */
+    pub fn verify<R: RngCore + CryptoRng>(&self, start: &[u8], circuits: &[ConstraintMatrices<E::Fr>], rng: &mut R) -> Result<Receipts, GameError> {
+        let mut digest = start.to_vec();
+        let g1 = E::G1Affine::prime_subgroup_generator();
+
+        let mut secrets = vec![g1; 3];
+        let mut phase_one = vec![];
+        for (i, contribution) in self.phase_one.iter().enumerate() {
+            if contribution.after.len() != 3 || contribution.knowledge.len() != 3 {
+                return Err(GameError::ProofFailure(format!("contribution {} to phase one is malformed", i + 1)));
+            }
+            for (knowledge, (before, after)) in contribution.knowledge.iter().zip(secrets.iter().zip(&contribution.after)) {
+                if !knowledge.check(&digest, *before, *after)? {
+                    return Err(GameError::ProofFailure(format!("contribution {} to phase one doesn't verify", i + 1)));
+                }
+            }
+            secrets = contribution.after.clone();
+            digest = chain(&digest, contribution)?;
+            phase_one.push(digest.clone());
+        }
+        if self.powers.secrets() != secrets || !self.powers.is_well_formed(rng) {
+            return Err(GameError::ProofFailure("the powers aren't the ones phase one made".to_string()));
+        }
+
+        let keys = match &self.keys {
+            Some(keys) => keys,
+            None if self.phase_two.is_empty() => return Ok(Receipts { phase_one, phase_two: vec![] }),
+            None => return Err(GameError::ProofFailure("phase two has contributions but phase one was never sealed".to_string())),
+        };
+        if keys.len() != circuits.len() {
+            return Err(GameError::ProofFailure(format!("phase one was sealed with keys for {} circuits, not {}", keys.len(), circuits.len())));
+        }
+        let mut deltas = vec![g1; keys.len()];
+        let mut phase_two = vec![];
+        for (i, contribution) in self.phase_two.iter().enumerate() {
+            let mut valid = contribution.after.len() == keys.len() && contribution.knowledge.len() == keys.len();
+            for (knowledge, (before, after)) in contribution.knowledge.iter().zip(deltas.iter().zip(&contribution.after)) {
+                valid = valid && knowledge.check(&digest, *before, *after)?;
+            }
+            if !valid {
+                return Err(GameError::ProofFailure(format!("contribution {} to phase two doesn't verify", i + 1)));
+            }
+            deltas = contribution.after.clone();
+            digest = chain(&digest, contribution)?;
+            phase_two.push(digest.clone());
+        }
+        for ((keys, matrices), delta) in keys.iter().zip(circuits).zip(deltas) {
+            if keys.delta_g1 != delta || !is_circuit_keys(&self.powers, matrices, keys, rng)? {
+                return Err(GameError::ProofFailure("the keys aren't the ones the ceremony made".to_string()));
+            }
+        }
+        Ok(Receipts { phase_one, phase_two })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), GameError> {
+        self.powers.tau_g1.serialize(&mut writer)?;
+        self.powers.tau_g2.serialize(&mut writer)?;
+        self.powers.alpha_tau_g1.serialize(&mut writer)?;
+        self.powers.beta_tau_g1.serialize(&mut writer)?;
+        self.powers.beta_g2.serialize(&mut writer)?;
+        self.phase_one.serialize(&mut writer)?;
+        self.keys.serialize(&mut writer)?;
+        self.phase_two.serialize(&mut writer)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Phases<E>, GameError> {
+        let powers = Powers {
+            tau_g1: Vec::deserialize(&mut reader)?,
+            tau_g2: Vec::deserialize(&mut reader)?,
+            alpha_tau_g1: Vec::deserialize(&mut reader)?,
+            beta_tau_g1: Vec::deserialize(&mut reader)?,
+            beta_g2: E::G2Affine::deserialize(&mut reader)?,
+        };
+        Ok(Phases {
+            powers,
+            phase_one: Vec::deserialize(&mut reader)?,
+            keys: Option::deserialize(&mut reader)?,
+            phase_two: Vec::deserialize(&mut reader)?,
+        })
+    }
+}
+
+/// the matrices of every circuit a game shape is proven with, in the order `Ceremony` keeps their keys
+fn game_circuits<F: CircuitField>(geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, salvo: Salvo) -> Result<Vec<ConstraintMatrices<F>>, GameError> {
+    let mut circuits = vec![
+        matrices(board_circuit(geometry, fleet, backend, F::CURVE)?)?,
+        matrices(shot_circuit(geometry, backend, F::CURVE)?)?,
+        matrices(sunk_circuit(geometry, backend, F::CURVE)?)?,
+        matrices(remaining_circuit(geometry, fleet, backend, F::CURVE)?)?,
+    ];
+    if salvo != Salvo::Off {
+        circuits.push(matrices(salvo_circuit(geometry, salvo.max_shots(fleet.len()), backend, F::CURVE)?)?);
+    }
+    Ok(circuits)
+}
+
+/**
+*   a ceremony for every key of one game shape: the board, shot, sunk and remaining
+*   circuits, in that order, and the salvo circuit if the game is played in salvos. the
+*   adjacency rule is a public input rather than part of a circuit, so the keys serve every
+*   rule, as the ones from `setup` do
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ceremony<E: PairingEngine = Bls12_381> {
+    pub geometry: BoardGeometry,
+    pub fleet: Vec<u8>,
+    pub backend: CommitmentBackend,
+    pub salvo: Salvo,
+    pub phases: Phases<E>,
+}
+
+impl<E: GameCurve> Ceremony<E> {
+    /// a ceremony for the keys of the game, which must be on this curve
+    pub fn new(game: &Game) -> Result<Ceremony<E>, GameError> {
+        if game.curve() != E::Fr::CURVE {
+            return Err(GameError::BadInput(format!("the game is on {}, not {}", game.curve().name(), E::Fr::CURVE.name())));
+        }
+        if game.system() != System::Groth16 {
+            return Err(GameError::BadInput(format!("a ceremony makes groth16 keys, {} keys are indexed from the srs", game.system().name())));
+        }
+        Ok(Ceremony {
+            geometry: game.geometry(),
+            fleet: game.fleet().to_vec(),
+            backend: game.backend(),
+            salvo: game.salvo(),
+            phases: Phases::new(&game_circuits(game.geometry(), game.fleet(), game.backend(), game.salvo())?)?,
+        })
+    }
+
+    /// the game shape the keys are for, with the default rule
+    pub fn game(&self) -> Result<Game, GameError> {
+        Game::new(self.geometry, self.fleet.clone(), self.backend)?.with_salvo(self.salvo)?.with_curve(E::Fr::CURVE)
+    }
+
+    fn circuits(&self) -> Result<Vec<ConstraintMatrices<E::Fr>>, GameError> {
+        game_circuits(self.geometry, &self.fleet, self.backend, self.salvo)
+    }
+
+    /// what the first contribution's proofs are bound to: the game shape and domain size
+    fn start(&self) -> Result<Vec<u8>, GameError> {
+        let mut bytes = CEREMONY_MAGIC.to_vec();
+        E::Fr::CURVE.to_u8().serialize(&mut bytes)?;
+        self.geometry.width.serialize(&mut bytes)?;
+        self.geometry.height.serialize(&mut bytes)?;
+        self.fleet.serialize(&mut bytes)?;
+        self.backend.to_u8().serialize(&mut bytes)?;
+        self.salvo.to_u8().serialize(&mut bytes)?;
+        (self.phases.powers.domain_size() as u64).serialize(&mut bytes)?;
+        Ok(Blake2s::digest(&bytes).to_vec())
+    }
+
+    /// see `Phases::contribute`
+    pub fn contribute<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> Result<Vec<u8>, GameError> {
+        let start = self.start()?;
+        self.phases.contribute(&start, rng)
+    }
+
+    /// see `Phases::seal`, which takes minutes for even the smallest board
+    pub fn seal(&mut self) -> Result<(), GameError> {
+        let circuits = self.circuits()?;
+        self.phases.seal(&circuits)
+    }
+
+    /// see `Phases::verify`
+    pub fn verify<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Result<Receipts, GameError> {
+        self.phases.verify(&self.start()?, &self.circuits()?, rng)
+    }
+
+    /**
+    *   the game's keys once phase two has had a contribution. phase two is what keeps
+    *   whoever sealed phase one from knowing δ, so the keys aren't handed out before then
+    */
+    pub fn game_keys(&self) -> Result<GameKeys<Groth16<E>>, GameError> {
+        let keys = match &self.phases.keys {
+            Some(keys) if !self.phases.phase_two.is_empty() => keys.clone(),
+            _ => return Err(GameError::BadInput("the ceremony needs a contribution to phase two before its keys are used".to_string())),
+        };
+        let mut keys = keys.into_iter();
+        match (keys.next(), keys.next(), keys.next(), keys.next(), keys.next()) {
+            (Some(board), Some(shot), Some(sunk), Some(remaining), salvo) if salvo.is_some() == (self.salvo != Salvo::Off) => {
+                Ok(GameKeys::from_proving_keys(board, shot, sunk, remaining, salvo))
+            }
+            _ => Err(GameError::BadInput("the ceremony doesn't hold a key for every circuit".to_string())),
+        }
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), GameError> {
+        writer.write_all(CEREMONY_MAGIC)?;
+        CEREMONY_VERSION.serialize(&mut writer)?;
+        E::Fr::CURVE.to_u8().serialize(&mut writer)?;
+        self.geometry.width.serialize(&mut writer)?;
+        self.geometry.height.serialize(&mut writer)?;
+        self.fleet.serialize(&mut writer)?;
+        self.backend.to_u8().serialize(&mut writer)?;
+        self.salvo.to_u8().serialize(&mut writer)?;
+        self.phases.write(&mut writer)?;
+        Ok(writer.flush()?)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Ceremony<E>, GameError> {
+        let curve = read_curve(&mut reader)?;
+        if curve != E::Fr::CURVE {
+            return Err(GameError::BadInput(format!("the ceremony is on {}, not {}", curve.name(), E::Fr::CURVE.name())));
+        }
+        Ok(Ceremony {
+            geometry: BoardGeometry::new(u8::deserialize(&mut reader)?, u8::deserialize(&mut reader)?),
+            fleet: Vec::<u8>::deserialize(&mut reader)?,
+            backend: CommitmentBackend::from_u8(u8::deserialize(&mut reader)?).ok_or(SerializationError::InvalidData)?,
+            salvo: Salvo::from_u8(u8::deserialize(&mut reader)?).ok_or(SerializationError::InvalidData)?,
+            phases: Phases::read(&mut reader)?,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GameError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: &Path) -> Result<Ceremony<E>, GameError> {
+        Ceremony::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// reads a ceremony's header up to the curve its points are on
+pub fn read_curve<R: Read>(mut reader: R) -> Result<Curve, GameError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != CEREMONY_MAGIC {
+        return Err(GameError::BadInput("not a zk-battleships ceremony".to_string()));
+    }
+    let version = u8::deserialize(&mut reader)?;
+    if version != CEREMONY_VERSION {
+        return Err(GameError::BadInput(format!("unsupported ceremony version {}", version)));
+    }
+    Ok(Curve::from_u8(u8::deserialize(&mut reader)?).ok_or(SerializationError::InvalidData)?)
+}
+
+/// a shot circuit on a 3x3 board, whose domain is small enough to go through a ceremony quickly
+#[cfg(test)]
+fn test_shot_circuit(target: u16) -> (crate::constraints::ShotVerifier, Vec<ark_bls12_381::Fr>) {
+    use crate::game::generate_commitment;
+    use ark_bls12_381::Fr;
+
+    let geometry = BoardGeometry::square(3);
+    let board = vec![0, 1, 0, 0, 1, 0, 2, 0, 0];
+    let randomness = vec![7u8; 32];
+    let backend = CommitmentBackend::Poseidon;
+    let commitment = generate_commitment(Curve::Bls12_381, backend, &board, &randomness).unwrap();
+    let hit = (board[target as usize] != 0) as u8;
+    let mut inputs = vec![Fr::from(3u8), Fr::from(3u8), Fr::from(target), Fr::from(hit)];
+    inputs.append(&mut backend.to_inputs(&commitment).unwrap());
+    let circuit = crate::constraints::ShotVerifier {
+        geometry,
+        target,
+        hit,
+        commitment,
+        backend,
+
+        rng_in: Some(randomness),
+        board: Some(board),
+    };
+    (circuit, inputs)
+}
+
+#[test]
+fn test_keys_from_powers() {
+    use ark_bls12_381::Fr;
+    use ark_groth16::{create_random_proof, prepare_verifying_key, verify_proof};
+    use rand::rngs::OsRng;
+
+    let matrices = matrices::<Fr, _>(test_shot_circuit(0).0).unwrap();
+
+    // two contributions to each phase, made directly, to powers for twice the circuit's
+    // domain as a smaller circuit gets from a ceremony for a larger one
+    let mut powers = Powers::<Bls12_381>::new(2 * domain(&matrices).unwrap().size());
+    for _ in 0..2 {
+        powers.apply(secret(&mut OsRng), secret(&mut OsRng), secret(&mut OsRng));
+    }
+    assert!(powers.is_well_formed(&mut OsRng));
+    let mut keys = circuit_keys(&powers, &matrices).unwrap();
+    assert!(is_circuit_keys(&powers, &matrices, &keys, &mut OsRng).unwrap());
+    for _ in 0..2 {
+        apply_delta(&mut keys, secret(&mut OsRng)).unwrap();
+    }
+    assert!(is_circuit_keys(&powers, &matrices, &keys, &mut OsRng).unwrap());
+
+    // ark-groth16's prover and verifier take the keys as if its own generator had made them
+    let pvk = prepare_verifying_key(&keys.vk);
+    let (circuit, mut inputs) = test_shot_circuit(1);
+    let proof = create_random_proof(circuit, &keys, &mut OsRng).unwrap();
+    assert!(verify_proof(&pvk, &proof, &inputs).unwrap());
+    inputs[3] = Fr::from(0u8);
+    assert!(!verify_proof(&pvk, &proof, &inputs).unwrap());
+
+    // powers that skip a step aren't powers, and keys that don't follow from them are caught
+    // whichever query is changed
+    let mut broken = powers.clone();
+    broken.tau_g1[3] = broken.tau_g1[2];
+    assert!(!broken.is_well_formed(&mut OsRng));
+    let mut swapped = keys.clone();
+    swapped.a_query.swap(1, 2);
+    assert!(!is_circuit_keys(&powers, &matrices, &swapped, &mut OsRng).unwrap());
+    let mut shifted = keys.clone();
+    shifted.h_query[0] = shifted.h_query[1];
+    assert!(!is_circuit_keys(&powers, &matrices, &shifted, &mut OsRng).unwrap());
+    let mut public = keys.clone();
+    public.vk.gamma_abc_g1[1] = public.vk.gamma_abc_g1[2];
+    assert!(!is_circuit_keys(&powers, &matrices, &public, &mut OsRng).unwrap());
+    let mut undivided = keys;
+    undivided.l_query = circuit_keys(&powers, &matrices).unwrap().l_query;
+    assert!(!is_circuit_keys(&powers, &matrices, &undivided, &mut OsRng).unwrap());
+}
+
+#[test]
+fn test_phases() {
+    use ark_groth16::{create_random_proof, prepare_verifying_key, verify_proof};
+    use rand::rngs::OsRng;
+
+    // the shot and sunk circuits of a 3x3 board, small enough to go through quickly
+    let geometry = BoardGeometry::square(3);
+    let circuits = vec![
+        matrices(shot_circuit(geometry, CommitmentBackend::Poseidon, Curve::Bls12_381).unwrap()).unwrap(),
+        matrices(sunk_circuit(geometry, CommitmentBackend::Poseidon, Curve::Bls12_381).unwrap()).unwrap(),
+    ];
+    let start = b"a 3x3 board".to_vec();
+    let mut phases = Phases::<Bls12_381>::new(&circuits).unwrap();
+    assert!(matches!(phases.seal(&circuits), Err(GameError::BadInput(_))));
+    let first = phases.contribute(&start, &mut OsRng).unwrap();
+    let second = phases.contribute(&start, &mut OsRng).unwrap();
+    phases.seal(&circuits).unwrap();
+    assert!(matches!(phases.seal(&circuits), Err(GameError::BadInput(_))));
+    let third = phases.contribute(&start, &mut OsRng).unwrap();
+    let fourth = phases.contribute(&start, &mut OsRng).unwrap();
+    assert_eq!(phases.verify(&start, &circuits, &mut OsRng).unwrap(), Receipts { phase_one: vec![first, second], phase_two: vec![third, fourth] });
+
+    // survives a round trip through its file format
+    let mut bytes = vec![];
+    phases.write(&mut bytes).unwrap();
+    assert_eq!(Phases::<Bls12_381>::read(bytes.as_slice()).unwrap(), phases);
+
+    // and its keys prove shots, each circuit with a δ of its own
+    let keys = phases.keys.as_ref().unwrap();
+    assert_ne!(keys[0].delta_g1, keys[1].delta_g1);
+    let (circuit, inputs) = test_shot_circuit(6);
+    let proof = create_random_proof(circuit, &keys[0], &mut OsRng).unwrap();
+    assert!(verify_proof(&prepare_verifying_key(&keys[0].vk), &proof, &inputs).unwrap());
+
+    // the proofs are bound to the start, so they can't be moved to another ceremony
+    let elsewhere = b"another board".to_vec();
+    assert!(matches!(phases.verify(&elsewhere, &circuits, &mut OsRng), Err(GameError::ProofFailure(_))));
+
+    // nor can the keys be checked against other circuits
+    assert!(matches!(phases.verify(&start, &circuits[..1], &mut OsRng), Err(GameError::ProofFailure(_))));
+    assert!(matches!(phases.verify(&start, &[circuits[1].clone(), circuits[0].clone()], &mut OsRng), Err(GameError::ProofFailure(_))));
+
+    // a contribution with someone else's proof of knowledge is caught
+    let mut copied = phases.clone();
+    copied.phase_two[1].knowledge = copied.phase_two[0].knowledge.clone();
+    assert!(matches!(copied.verify(&start, &circuits, &mut OsRng), Err(GameError::ProofFailure(_))));
+
+    // as are keys that weren't made by the contributions, such as ones from setup
+    let mut replaced = phases.clone();
+    replaced.keys.as_mut().unwrap()[0] = ark_groth16::generate_random_parameters(test_shot_circuit(0).0, &mut OsRng).unwrap();
+    assert!(matches!(replaced.verify(&start, &circuits, &mut OsRng), Err(GameError::ProofFailure(_))));
+
+    // and a contribution dropped from phase one
+    let mut dropped = phases;
+    dropped.phase_one.remove(0);
+    assert!(matches!(dropped.verify(&start, &circuits, &mut OsRng), Err(GameError::ProofFailure(_))));
+}
+
+#[test]
+fn test_game_ceremony() {
+    use rand::rngs::OsRng;
+
+    // a contribution to a board's keys takes minutes, so only the start is checked here and
+    // the phases themselves are tested on small circuits
+    let game = Game::new(BoardGeometry::new(2, 1), vec![1], CommitmentBackend::Poseidon).unwrap();
+    let ceremony = Ceremony::<Bls12_381>::new(&game).unwrap();
+    assert_eq!(ceremony.game().unwrap(), game);
+    assert_eq!(ceremony.circuits().unwrap().len(), 4);
+    assert!(matches!(ceremony.clone().seal(), Err(GameError::BadInput(_))));
+    assert!(matches!(ceremony.game_keys(), Err(GameError::BadInput(_))));
+    assert_eq!(ceremony.verify(&mut OsRng).unwrap(), Receipts { phase_one: vec![], phase_two: vec![] });
+
+    // a salvo game has its salvo keys made too
+    let salvos = game.clone().with_salvo(Salvo::Fixed(2)).unwrap();
+    let salvo_ceremony = Ceremony::<Bls12_381>::new(&salvos).unwrap();
+    assert_eq!(salvo_ceremony.circuits().unwrap().len(), 5);
+    assert_eq!(salvo_ceremony.game().unwrap(), salvos);
+
+    // the keys of a different game shape are a different ceremony
+    let other = Game::new(BoardGeometry::new(2, 1), vec![2], CommitmentBackend::Poseidon).unwrap();
+    assert_ne!(Ceremony::<Bls12_381>::new(&other).unwrap().start().unwrap(), ceremony.start().unwrap());
+    assert_ne!(salvo_ceremony.start().unwrap(), ceremony.start().unwrap());
+    assert!(matches!(Ceremony::<ark_bls12_377::Bls12_377>::new(&game), Err(GameError::BadInput(_))));
+
+    let mut bytes = vec![];
+    salvo_ceremony.write(&mut bytes).unwrap();
+    assert_eq!(read_curve(bytes.as_slice()).unwrap(), Curve::Bls12_381);
+    assert!(matches!(Ceremony::<ark_bls12_377::Bls12_377>::read(bytes.as_slice()), Err(GameError::BadInput(_))));
+}
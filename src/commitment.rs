@@ -0,0 +1,776 @@
+use crate::common::*;
+use crate::error::GameError;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_r1cs_std::prelude::*;
+use ark_r1cs_std::ToConstraintFieldGadget;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_crypto_primitives::{CommitmentGadget, CommitmentScheme};
+use ark_crypto_primitives::commitment::blake2s;
+use ark_crypto_primitives::commitment::pedersen::{self, Window};
+use ark_crypto_primitives::prf::{Blake2s as Blake2sPrf, PRFGadget, PRF};
+use ark_crypto_primitives::prf::blake2s::constraints::{Blake2sGadget, OutputVar};
+use ark_ed_on_bls12_381::{EdwardsAffine, EdwardsProjective, Fr as JubJubFr};
+use ark_ed_on_bls12_381::constraints::EdwardsVar;
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::sync::OnceLock;
+
+/// bytes of randomness behind each board commitment
+pub const RANDOMNESS_LEN: usize = 32;
+
+/// bytes of the player secret the commitment randomness is derived from
+pub const SECRET_LEN: usize = 32;
+
+/// the blake2s prf input the commitment randomness is derived at
+const SALT_INPUT: &[u8; 32] = b"zk-battleships commitment salt!!";
+
+/**
+*   derives a board's commitment randomness from the player's secret with the blake2s prf,
+*   so the secret is all a player has to keep to reopen their commitment
+*/
+pub fn derive_randomness(secret: &[u8]) -> Result<[u8; RANDOMNESS_LEN], GameError> {
+    let secret: &[u8; SECRET_LEN] = secret.try_into()
+        .map_err(|_| GameError::BadInput(format!("the player secret must be {} bytes, not {}", SECRET_LEN, secret.len())))?;
+    Blake2sPrf::evaluate(secret, SALT_INPUT).map_err(|e| GameError::BadInput(e.to_string()))
+}
+
+/// derive_randomness as a gadget over the secret's bytes
+pub fn derive_randomness_gadget<F: PrimeField>(secret: &[UInt8<F>]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    let input = UInt8::constant_vec(SALT_INPUT);
+    Ok(<Blake2sGadget as PRFGadget<Blake2sPrf, F>>::evaluate(secret, &input)?.0)
+}
+
+/**
+*   the scalar field of a curve the game proves on, which the board circuits and the
+*   commitment gadgets are built over. the schemes differ between fields only where they
+*   work in the field itself: poseidon has its own constants in each, and pedersen commits
+*   on jubjub, whose base field is the bls12-381 scalar field, so it can only be opened there
+*/
+pub trait CircuitField: PrimeField {
+    const CURVE: Curve;
+
+    /// the poseidon instance over this field
+    fn poseidon_parameters() -> &'static PoseidonParameters<Self>;
+
+    /// a pedersen commitment as public inputs, CommitmentMismatch if it isn't a jubjub
+    /// point or the field has no jubjub gadget
+    fn pedersen_inputs(commitment: &[u8]) -> Result<Vec<Self>, GameError>;
+
+    /// PedersenBoard::enforce_commitment over this field
+    fn enforce_pedersen(cs: ConstraintSystemRef<Self>, board: &[UInt8<Self>], randomness: &[UInt8<Self>], commitment: &[u8])
+        -> Result<(), SynthesisError>;
+}
+
+impl CircuitField for ConstraintF {
+    const CURVE: Curve = Curve::Bls12_381;
+
+    fn poseidon_parameters() -> &'static PoseidonParameters<Self> {
+        static PARAMETERS: OnceLock<PoseidonParameters<ConstraintF>> = OnceLock::new();
+        PARAMETERS.get_or_init(|| grain_parameters(5, POSEIDON_FULL_ROUNDS, POSEIDON_PARTIAL_ROUNDS))
+    }
+
+    fn pedersen_inputs(commitment: &[u8]) -> Result<Vec<Self>, GameError> {
+        PedersenBoard::jubjub_inputs(commitment)
+    }
+
+    fn enforce_pedersen(cs: ConstraintSystemRef<Self>, board: &[UInt8<Self>], randomness: &[UInt8<Self>], commitment: &[u8])
+        -> Result<(), SynthesisError> {
+        PedersenBoard::enforce_on_jubjub(cs, board, randomness, commitment)
+    }
+}
+
+/// 5 divides r - 1 for bls12-377, so x^5 isn't a permutation there and poseidon takes x^11
+impl CircuitField for ark_bls12_377::Fr {
+    const CURVE: Curve = Curve::Bls12_377;
+
+    fn poseidon_parameters() -> &'static PoseidonParameters<Self> {
+        static PARAMETERS: OnceLock<PoseidonParameters<ark_bls12_377::Fr>> = OnceLock::new();
+        PARAMETERS.get_or_init(|| grain_parameters(11, POSEIDON_FULL_ROUNDS, POSEIDON_PARTIAL_ROUNDS))
+    }
+
+    fn pedersen_inputs(_: &[u8]) -> Result<Vec<Self>, GameError> {
+        Err(GameError::CommitmentMismatch)
+    }
+
+    fn enforce_pedersen(_: ConstraintSystemRef<Self>, _: &[UInt8<Self>], _: &[UInt8<Self>], _: &[u8]) -> Result<(), SynthesisError> {
+        Err(SynthesisError::Unsatisfiable)
+    }
+}
+
+/**
+*   a way of committing to a whole board with 32 bytes of randomness.
+*   the native commitment and the gadget must agree, the board circuits
+*   enforce the gadget against the public inputs produced by to_inputs
+*/
+pub trait BoardCommitment {
+    /// commits to the board for circuits over F, returning the serialized commitment
+    fn commit<F: CircuitField>(board: &[u8], randomness: &[u8; RANDOMNESS_LEN]) -> Result<Vec<u8>, GameError>;
+
+    /// the public inputs the commitment is allocated as in the board circuits, or
+    /// CommitmentMismatch if the bytes aren't a commitment of this scheme
+    fn to_inputs<F: CircuitField>(commitment: &[u8]) -> Result<Vec<F>, GameError>;
+
+    /// whether the bytes are a commitment this scheme could have produced. commitments
+    /// from the opponent are checked with this before anything else touches them
+    fn is_well_formed<F: CircuitField>(commitment: &[u8]) -> bool;
+
+    /// allocates the commitment as a public input and enforces that the board and
+    /// randomness open it
+    fn enforce_commitment<F: CircuitField>(cs: ConstraintSystemRef<F>, board: &[UInt8<F>], randomness: &[UInt8<F>], commitment: &[u8])
+        -> Result<(), SynthesisError>;
+}
+
+/// the commitment scheme used for both players' boards, chosen when the game is created
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitmentBackend {
+    Blake2s,
+    Pedersen,
+    Poseidon,
+}
+
+impl CommitmentBackend {
+    pub fn from_name(name: &str) -> Option<CommitmentBackend> {
+        match name {
+            "blake2s" => Some(CommitmentBackend::Blake2s),
+            "pedersen" => Some(CommitmentBackend::Pedersen),
+            "poseidon" => Some(CommitmentBackend::Poseidon),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CommitmentBackend::Blake2s => "blake2s",
+            CommitmentBackend::Pedersen => "pedersen",
+            CommitmentBackend::Poseidon => "poseidon",
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            CommitmentBackend::Blake2s => 0,
+            CommitmentBackend::Pedersen => 1,
+            CommitmentBackend::Poseidon => 2,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<CommitmentBackend> {
+        match id {
+            0 => Some(CommitmentBackend::Blake2s),
+            1 => Some(CommitmentBackend::Pedersen),
+            2 => Some(CommitmentBackend::Poseidon),
+            _ => None,
+        }
+    }
+
+    /// the largest board the scheme can commit to. pedersen is limited by its windows
+    pub fn max_board_tiles(self) -> usize {
+        match self {
+            CommitmentBackend::Pedersen => BoardWindow::NUM_WINDOWS * BoardWindow::WINDOW_SIZE / 8,
+            CommitmentBackend::Blake2s | CommitmentBackend::Poseidon => usize::MAX,
+        }
+    }
+
+    /// whether the scheme can be opened in circuits on the curve. pedersen only can on bls12-381
+    pub fn supports(self, curve: Curve) -> bool {
+        match self {
+            CommitmentBackend::Pedersen => curve == Curve::Bls12_381,
+            CommitmentBackend::Blake2s | CommitmentBackend::Poseidon => true,
+        }
+    }
+
+    /// commits to the board for the circuits on the curve, poseidon commitments differ between curves
+    pub fn commit(self, curve: Curve, board: &[u8], randomness: &[u8]) -> Result<Vec<u8>, GameError> {
+        match curve {
+            Curve::Bls12_381 => self.commit_over::<ConstraintF>(board, randomness),
+            Curve::Bls12_377 => self.commit_over::<ark_bls12_377::Fr>(board, randomness),
+        }
+    }
+
+    fn commit_over<F: CircuitField>(self, board: &[u8], randomness: &[u8]) -> Result<Vec<u8>, GameError> {
+        let randomness: &[u8; RANDOMNESS_LEN] = randomness.try_into()
+            .map_err(|_| GameError::BadInput(format!("commitment randomness must be {} bytes, not {}", RANDOMNESS_LEN, randomness.len())))?;
+        match self {
+            CommitmentBackend::Blake2s => Blake2sBoard::commit::<F>(board, randomness),
+            CommitmentBackend::Pedersen => PedersenBoard::commit::<F>(board, randomness),
+            CommitmentBackend::Poseidon => PoseidonBoard::commit::<F>(board, randomness),
+        }
+    }
+
+    pub fn to_inputs<F: CircuitField>(self, commitment: &[u8]) -> Result<Vec<F>, GameError> {
+        match self {
+            CommitmentBackend::Blake2s => Blake2sBoard::to_inputs(commitment),
+            CommitmentBackend::Pedersen => PedersenBoard::to_inputs(commitment),
+            CommitmentBackend::Poseidon => PoseidonBoard::to_inputs(commitment),
+        }
+    }
+
+    /// whether the bytes are a commitment the scheme could have produced on the curve
+    pub fn is_well_formed(self, curve: Curve, commitment: &[u8]) -> bool {
+        match curve {
+            Curve::Bls12_381 => self.is_well_formed_over::<ConstraintF>(commitment),
+            Curve::Bls12_377 => self.is_well_formed_over::<ark_bls12_377::Fr>(commitment),
+        }
+    }
+
+    fn is_well_formed_over<F: CircuitField>(self, commitment: &[u8]) -> bool {
+        match self {
+            CommitmentBackend::Blake2s => Blake2sBoard::is_well_formed::<F>(commitment),
+            CommitmentBackend::Pedersen => PedersenBoard::is_well_formed::<F>(commitment),
+            CommitmentBackend::Poseidon => PoseidonBoard::is_well_formed::<F>(commitment),
+        }
+    }
+
+    pub fn enforce_commitment<F: CircuitField>(self, cs: ConstraintSystemRef<F>, board: &[UInt8<F>], randomness: &[UInt8<F>], commitment: &[u8])
+        -> Result<(), SynthesisError> {
+        match self {
+            CommitmentBackend::Blake2s => Blake2sBoard::enforce_commitment(cs, board, randomness, commitment),
+            CommitmentBackend::Pedersen => PedersenBoard::enforce_commitment(cs, board, randomness, commitment),
+            CommitmentBackend::Poseidon => PoseidonBoard::enforce_commitment(cs, board, randomness, commitment),
+        }
+    }
+}
+
+/**
+*   blake2s(randomness || board)
+*/
+pub struct Blake2sBoard;
+
+impl BoardCommitment for Blake2sBoard {
+    fn commit<F: CircuitField>(board: &[u8], randomness: &[u8; RANDOMNESS_LEN]) -> Result<Vec<u8>, GameError> {
+        let commitment = blake2s::Commitment::commit(&(), board, randomness)
+            .map_err(|e| GameError::BadInput(e.to_string()))?;
+        Ok(commitment.to_vec())
+    }
+
+    fn to_inputs<F: CircuitField>(commitment: &[u8]) -> Result<Vec<F>, GameError> {
+        if !Self::is_well_formed::<F>(commitment) {
+            return Err(GameError::CommitmentMismatch);
+        }
+        commitment.to_field_elements().ok_or(GameError::CommitmentMismatch)
+    }
+
+    fn is_well_formed<F: CircuitField>(commitment: &[u8]) -> bool {
+        commitment.len() == 32
+    }
+
+    fn enforce_commitment<F: CircuitField>(cs: ConstraintSystemRef<F>, board: &[UInt8<F>], randomness: &[UInt8<F>], commitment: &[u8])
+        -> Result<(), SynthesisError> {
+
+        // setup rng
+        let rng_witness = blake2s::constraints::RandomnessVar(randomness.to_vec());
+
+        // setup commitment
+        let comm_witness = UInt8::new_input_vec(ark_relations::ns!(cs, "commitment byte"), commitment)?;
+        let comm_witness = OutputVar(comm_witness);
+
+
+        let parameters = ();
+        let parameters_var = <blake2s::constraints::CommGadget as CommitmentGadget<blake2s::Commitment, F>>::ParametersVar::new_input(
+            ark_relations::ns!(cs, "gadget_parameters"),
+            || Ok(&parameters),
+        )?;
+
+
+        let result_var = <blake2s::constraints::CommGadget as CommitmentGadget<blake2s::Commitment, F>>::commit(
+            &parameters_var,
+            board,
+            &rng_witness,
+        )?;
+
+        comm_witness.enforce_equal(&result_var)
+    }
+}
+
+/// 512 windows of 4 bits, enough for a 16x16 board at one byte per tile
+#[derive(Clone)]
+pub struct BoardWindow;
+
+impl pedersen::Window for BoardWindow {
+    const WINDOW_SIZE: usize = 4;
+    const NUM_WINDOWS: usize = 512;
+}
+
+type PedersenCommitment = pedersen::Commitment<EdwardsProjective, BoardWindow>;
+
+/**
+*   the pedersen generators are sampled as random curve points from a fixed public seed,
+*   so nobody knows any discrete log relation between them. chacha20 is used rather than
+*   StdRng because its output is fixed, StdRng may change between rand versions
+*/
+fn pedersen_parameters() -> &'static pedersen::Parameters<EdwardsProjective> {
+    static PARAMETERS: OnceLock<pedersen::Parameters<EdwardsProjective>> = OnceLock::new();
+    PARAMETERS.get_or_init(|| {
+        let mut rng = ChaCha20Rng::from_seed(*b"zk-battleships pedersen params!!");
+        PedersenCommitment::setup(&mut rng).unwrap()
+    })
+}
+
+/**
+*   pedersen commitment over the jubjub curve (ark-ed-on-bls12-381), whose base field
+*   is the bls12-381 scalar field so the commitment point is two public inputs
+*/
+pub struct PedersenBoard;
+
+impl PedersenBoard {
+    /// the randomness bytes as a scalar. the gadget only has generators for the low
+    /// size_in_bits bits, so the bits above them are dropped here too
+    fn randomness(randomness: &[u8]) -> pedersen::Randomness<EdwardsProjective> {
+        let bits = JubJubFr::size_in_bits();
+        let masked: Vec<u8> = randomness.iter().enumerate()
+            .map(|(i, byte)| byte & (((1u16 << bits.saturating_sub(i * 8).min(8)) - 1) as u8))
+            .collect();
+        pedersen::Randomness(JubJubFr::from_le_bytes_mod_order(&masked))
+    }
+
+    /// the commitment point as its two coordinates, in the jubjub base field
+    fn jubjub_inputs(commitment: &[u8]) -> Result<Vec<ConstraintF>, GameError> {
+        let mut reader = commitment;
+        match EdwardsAffine::deserialize(&mut reader) {
+            Ok(point) if reader.is_empty() => Ok(vec![point.x, point.y]),
+            _ => Err(GameError::CommitmentMismatch),
+        }
+    }
+
+    /// the pedersen commitment gadget, which only takes randomness it allocates itself,
+    /// redone over randomness that is already a variable
+    fn enforce_on_jubjub(cs: ConstraintSystemRef<ConstraintF>, board: &[UInt8<ConstraintF>], randomness: &[UInt8<ConstraintF>], commitment: &[u8])
+        -> Result<(), SynthesisError> {
+
+        let point: EdwardsProjective = EdwardsAffine::deserialize(commitment)
+            .map_err(|_| SynthesisError::AssignmentMissing)?
+            .into();
+        let comm_witness = EdwardsVar::new_input(ark_relations::ns!(cs, "commitment"), || Ok(point))?;
+
+        let parameters = pedersen_parameters();
+        let window_bytes = BoardWindow::WINDOW_SIZE * BoardWindow::NUM_WINDOWS / 8;
+        if board.len() > window_bytes {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let mut padded = board.to_vec();
+        padded.resize(window_bytes, UInt8::constant(0));
+        let mut board_bits = vec![];
+        for byte in &padded {
+            board_bits.append(&mut byte.to_bits_le()?);
+        }
+        let mut result_var = EdwardsVar::precomputed_base_multiscalar_mul_le(&parameters.generators, board_bits.chunks(BoardWindow::WINDOW_SIZE))?;
+
+        let mut rng_bits = vec![];
+        for byte in randomness {
+            rng_bits.append(&mut byte.to_bits_le()?);
+        }
+        result_var.precomputed_base_scalar_mul_le(rng_bits.iter().zip(&parameters.randomness_generator))?;
+
+        comm_witness.enforce_equal(&result_var)
+    }
+}
+
+impl BoardCommitment for PedersenBoard {
+    fn commit<F: CircuitField>(board: &[u8], randomness: &[u8; RANDOMNESS_LEN]) -> Result<Vec<u8>, GameError> {
+        if !CommitmentBackend::Pedersen.supports(F::CURVE) {
+            return Err(GameError::BadInput(format!("pedersen commitments can't be opened in circuits on {}", F::CURVE.name())));
+        }
+        // fails if the board is longer than the windows cover
+        let commitment = PedersenCommitment::commit(pedersen_parameters(), board, &Self::randomness(randomness))
+            .map_err(|e| GameError::BadInput(e.to_string()))?;
+        let mut bytes = vec![];
+        commitment.serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn to_inputs<F: CircuitField>(commitment: &[u8]) -> Result<Vec<F>, GameError> {
+        F::pedersen_inputs(commitment)
+    }
+
+    fn is_well_formed<F: CircuitField>(commitment: &[u8]) -> bool {
+        F::pedersen_inputs(commitment).is_ok()
+    }
+
+    fn enforce_commitment<F: CircuitField>(cs: ConstraintSystemRef<F>, board: &[UInt8<F>], randomness: &[UInt8<F>], commitment: &[u8])
+        -> Result<(), SynthesisError> {
+        F::enforce_pedersen(cs, board, randomness, commitment)
+    }
+}
+
+const POSEIDON_WIDTH: usize = 3;
+const POSEIDON_FULL_ROUNDS: usize = 8;
+const POSEIDON_PARTIAL_ROUNDS: usize = 57;
+
+/**
+*   x^alpha poseidon over the circuit field, width 3 (rate 2), with the 8 full and 57 partial
+*   rounds the poseidon paper gives for x^5 over a 255 bit field. alpha is 5 where that permutes
+*   the field, as it does for bls12-381, and the smallest exponent that does otherwise. a bigger
+*   alpha needs fewer rounds for the same security, so the rounds are kept the same for every
+*   field. the constants and mds matrix come from the reference grain lfsr rather than anything
+*   chosen by us.
+*
+*   a cauchy matrix over distinct x_i, y_j is always mds. the reference script also screens the
+*   matrix for invariant subspace trails before accepting it, that check isn't rerun here: like
+*   ark-sponge with skip_matrices = 0 we take the first matrix grain produces
+*/
+pub struct PoseidonParameters<F: PrimeField> {
+    // the s-box exponent
+    alpha: u64,
+    round_constants: Vec<[F; POSEIDON_WIDTH]>,
+    mds: [[F; POSEIDON_WIDTH]; POSEIDON_WIDTH],
+}
+
+/**
+*   the grain lfsr from the poseidon reference implementation (generate_parameters_grain.sage),
+*   seeded with the instance parameters. this is the same generator ark-sponge uses, so the
+*   constants can be checked against its test vectors
+*/
+struct GrainLfsr {
+    state: [bool; 80],
+    head: usize,
+}
+
+impl GrainLfsr {
+    fn new(field_bits: u64, width: u64, full_rounds: u64, partial_rounds: u64) -> GrainLfsr {
+        let mut state = [false; 80];
+
+        // b0, b1 = 0b01 for a prime field, b2..b5 = 0 for an x^alpha s-box, then n, t,
+        // R_F and R_P msb first and the rest set to 1
+        state[1] = true;
+        for (bits, value) in [(6..18, field_bits), (18..30, width), (30..40, full_rounds), (40..50, partial_rounds)] {
+            let mut value = value;
+            for i in bits.rev() {
+                state[i] = value & 1 == 1;
+                value >>= 1;
+            }
+        }
+        for bit in state.iter_mut().skip(50) {
+            *bit = true;
+        }
+
+        let mut lfsr = GrainLfsr { state, head: 0 };
+        for _ in 0..160 {
+            lfsr.update();
+        }
+        lfsr
+    }
+
+    fn update(&mut self) -> bool {
+        let bit = |offset: usize| self.state[(self.head + offset) % 80];
+        let new_bit = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+        self.state[self.head] = new_bit;
+        self.head = (self.head + 1) % 80;
+        new_bit
+    }
+
+    /// output bits are filtered in pairs, the second bit is kept when the first is set
+    fn bits(&mut self, n: usize) -> Vec<bool> {
+        (0..n).map(|_| {
+            while !self.update() {
+                self.update();
+            }
+            self.update()
+        }).collect()
+    }
+
+    /// n bits read msb first, resampled until they're below the modulus
+    fn field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            let bits = self.bits(F::size_in_bits());
+            if let Some(element) = F::from_repr(BigInteger::from_bits_be(&bits)) {
+                return element;
+            }
+        }
+    }
+
+    /// n bits read msb first, reduced mod p
+    fn field_element_mod_p<F: PrimeField>(&mut self) -> F {
+        let mut bits = self.bits(F::size_in_bits());
+        bits.reverse();
+        let bytes: Vec<u8> = bits.chunks(8)
+            .map(|chunk| chunk.iter().enumerate().fold(0u8, |byte, (i, bit)| byte | (u8::from(*bit) << i)))
+            .collect();
+        F::from_le_bytes_mod_order(&bytes)
+    }
+}
+
+/**
+*   round constants and the cauchy mds matrix 1 / (x_i + y_j) as generated by the grain lfsr,
+*   exactly as ark-sponge's find_poseidon_ark_and_mds does with no skipped matrices. the lfsr
+*   is seeded the same for any x^alpha s-box, so alpha doesn't change the constants
+*/
+fn grain_parameters<F: PrimeField>(alpha: u64, full_rounds: usize, partial_rounds: usize) -> PoseidonParameters<F> {
+    let mut lfsr = GrainLfsr::new(F::size_in_bits() as u64, POSEIDON_WIDTH as u64, full_rounds as u64, partial_rounds as u64);
+
+    let round_constants = (0..full_rounds + partial_rounds)
+        .map(|_| [lfsr.field_element(), lfsr.field_element(), lfsr.field_element()])
+        .collect();
+
+    let xs: Vec<F> = (0..POSEIDON_WIDTH).map(|_| lfsr.field_element_mod_p()).collect();
+    let ys: Vec<F> = (0..POSEIDON_WIDTH).map(|_| lfsr.field_element_mod_p()).collect();
+    let mut mds = [[F::zero(); POSEIDON_WIDTH]; POSEIDON_WIDTH];
+    for (row, x) in mds.iter_mut().zip(&xs) {
+        for (entry, y) in row.iter_mut().zip(&ys) {
+            *entry = (*x + y).inverse().unwrap();
+        }
+    }
+
+    PoseidonParameters { alpha, round_constants, mds }
+}
+
+fn is_full_round(round: usize) -> bool {
+    let partial_rounds = POSEIDON_FULL_ROUNDS / 2..POSEIDON_FULL_ROUNDS / 2 + POSEIDON_PARTIAL_ROUNDS;
+    !partial_rounds.contains(&round)
+}
+
+fn poseidon_permute<F: CircuitField>(state: &mut [F; POSEIDON_WIDTH]) {
+    let params = F::poseidon_parameters();
+    for (round, constants) in params.round_constants.iter().enumerate() {
+        for (s, c) in state.iter_mut().zip(constants) {
+            *s += c;
+        }
+
+        let sbox_width = if is_full_round(round) { POSEIDON_WIDTH } else { 1 };
+        for s in state.iter_mut().take(sbox_width) {
+            *s = s.pow([params.alpha]);
+        }
+
+        let mut mixed = [F::zero(); POSEIDON_WIDTH];
+        for (m, row) in mixed.iter_mut().zip(&params.mds) {
+            for (entry, s) in row.iter().zip(state.iter()) {
+                *m += *entry * s;
+            }
+        }
+        *state = mixed;
+    }
+}
+
+fn poseidon_permute_gadget<F: CircuitField>(state: &mut [FpVar<F>]) -> Result<(), SynthesisError> {
+    let params = F::poseidon_parameters();
+    for (round, constants) in params.round_constants.iter().enumerate() {
+        for (s, c) in state.iter_mut().zip(constants) {
+            *s += *c;
+        }
+
+        let sbox_width = if is_full_round(round) { POSEIDON_WIDTH } else { 1 };
+        for s in state.iter_mut().take(sbox_width) {
+            *s = s.pow_by_constant([params.alpha])?;
+        }
+
+        let mut mixed = vec![FpVar::zero(); POSEIDON_WIDTH];
+        for (m, row) in mixed.iter_mut().zip(&params.mds) {
+            for (entry, s) in row.iter().zip(state.iter()) {
+                *m += s * *entry;
+            }
+        }
+        state.clone_from_slice(&mixed);
+    }
+    Ok(())
+}
+
+/// sponge over the inputs, two at a time, with the input length as the capacity element
+fn poseidon_hash<F: CircuitField>(inputs: &[F]) -> F {
+    let mut state = [F::from(inputs.len() as u64), F::zero(), F::zero()];
+    for chunk in inputs.chunks(2) {
+        for (s, x) in state[1..].iter_mut().zip(chunk) {
+            *s += x;
+        }
+        poseidon_permute(&mut state);
+    }
+    state[1]
+}
+
+fn poseidon_hash_gadget<F: CircuitField>(inputs: &[FpVar<F>]) -> Result<FpVar<F>, SynthesisError> {
+    let mut state = vec![FpVar::constant(F::from(inputs.len() as u64)), FpVar::zero(), FpVar::zero()];
+    for chunk in inputs.chunks(2) {
+        for (s, x) in state[1..].iter_mut().zip(chunk) {
+            *s += x;
+        }
+        poseidon_permute_gadget(&mut state)?;
+    }
+    Ok(state[1].clone())
+}
+
+/**
+*   poseidon(randomness || board), with the randomness and board bytes packed into field
+*   elements 31 bytes at a time
+*/
+pub struct PoseidonBoard;
+
+impl BoardCommitment for PoseidonBoard {
+    fn commit<F: CircuitField>(board: &[u8], randomness: &[u8; RANDOMNESS_LEN]) -> Result<Vec<u8>, GameError> {
+        let packing_failed = || GameError::BadInput("couldn't pack the board into field elements".to_string());
+        let mut inputs: Vec<F> = randomness.to_field_elements().ok_or_else(packing_failed)?;
+        inputs.append(&mut board.to_field_elements().ok_or_else(packing_failed)?);
+
+        let mut bytes = vec![];
+        poseidon_hash(&inputs).serialize(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn to_inputs<F: CircuitField>(commitment: &[u8]) -> Result<Vec<F>, GameError> {
+        if !Self::is_well_formed::<F>(commitment) {
+            return Err(GameError::CommitmentMismatch);
+        }
+        Ok(vec![F::deserialize(commitment)?])
+    }
+
+    fn is_well_formed<F: CircuitField>(commitment: &[u8]) -> bool {
+        let mut reader = commitment;
+        F::deserialize(&mut reader).is_ok() && reader.is_empty()
+    }
+
+    fn enforce_commitment<F: CircuitField>(cs: ConstraintSystemRef<F>, board: &[UInt8<F>], randomness: &[UInt8<F>], commitment: &[u8])
+        -> Result<(), SynthesisError> {
+
+        let comm_witness = FpVar::new_input(ark_relations::ns!(cs, "commitment"), || {
+            Self::to_inputs::<F>(commitment).map(|inputs| inputs[0]).map_err(|_| SynthesisError::AssignmentMissing)
+        })?;
+
+        let mut inputs = randomness.to_constraint_field()?;
+        inputs.append(&mut board.to_constraint_field()?);
+
+        let result_var = poseidon_hash_gadget(&inputs)?;
+
+        comm_witness.enforce_equal(&result_var)
+    }
+}
+
+#[test]
+fn test_grain_parameters_match_ark_sponge() {
+    use std::str::FromStr;
+
+    // ark-sponge's test vectors for the bls12-381 scalar field, width 3, 8 full and 31 partial rounds
+    let params = grain_parameters::<ConstraintF>(5, 8, 31);
+    assert_eq!(params.round_constants[0][0], ConstraintF::from_str("27117311055620256798560880810000042840428971800021819916023577129547249660720").unwrap());
+    assert_eq!(params.round_constants[0][1], ConstraintF::from_str("51641662388546346858987925410984003801092143452466182801674685248597955169158").unwrap());
+    assert_eq!(params.mds[0][0], ConstraintF::from_str("26017457457808754696901916760153646963713419596921330311675236858336250747575").unwrap());
+}
+
+#[test]
+fn test_native_and_gadget_agree() {
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
+    let randomness = [7u8; 32];
+
+    for backend in [CommitmentBackend::Blake2s, CommitmentBackend::Pedersen, CommitmentBackend::Poseidon] {
+        let commitment = backend.commit(Curve::Bls12_381, &board, &randomness).unwrap();
+
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        let board_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"), &board).unwrap();
+        let rng_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "randomness"), &randomness).unwrap();
+        backend.enforce_commitment(cs.clone(), &board_var, &rng_var, &commitment).unwrap();
+        assert!(cs.is_satisfied().unwrap(), "{:?}", backend);
+
+        // the number of public inputs matches what the verifier passes in, plus the constant one
+        assert_eq!(cs.num_instance_variables(), backend.to_inputs::<ConstraintF>(&commitment).unwrap().len() + 1, "{:?}", backend);
+
+        assert!(backend.is_well_formed(Curve::Bls12_381, &commitment), "{:?}", backend);
+        assert!(!backend.is_well_formed(Curve::Bls12_381, &commitment[1..]), "{:?}", backend);
+        assert!(matches!(backend.to_inputs::<ConstraintF>(&commitment[1..]), Err(GameError::CommitmentMismatch)), "{:?}", backend);
+        assert!(matches!(backend.commit(Curve::Bls12_381, &board, &randomness[1..]), Err(GameError::BadInput(_))), "{:?}", backend);
+    }
+}
+
+#[test]
+fn test_derived_randomness() {
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
+    let secret = [3u8; SECRET_LEN];
+    let randomness = derive_randomness(&secret).unwrap();
+    assert_ne!(randomness, derive_randomness(&[4u8; SECRET_LEN]).unwrap());
+    assert!(matches!(derive_randomness(&secret[1..]), Err(GameError::BadInput(_))));
+
+    // randomness derived in-circuit opens the commitment made with the native derivation,
+    // and another secret's doesn't
+    for backend in [CommitmentBackend::Blake2s, CommitmentBackend::Pedersen, CommitmentBackend::Poseidon] {
+        let commitment = backend.commit(Curve::Bls12_381, &board, &randomness).unwrap();
+        for (other, satisfied) in [(secret, true), ([4u8; SECRET_LEN], false)] {
+            let cs = ConstraintSystem::<ConstraintF>::new_ref();
+            let board_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"), &board).unwrap();
+            let secret_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "secret"), &other).unwrap();
+            let rng_var = derive_randomness_gadget(&secret_var).unwrap();
+            backend.enforce_commitment(cs.clone(), &board_var, &rng_var, &commitment).unwrap();
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied, "{:?}", backend);
+        }
+    }
+}
+
+#[test]
+fn test_commitment_binds_board() {
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
+    let other_board: Vec<u8> = vec![0,0,0,1,1,1,0,0,0];
+    let randomness = [7u8; 32];
+
+    for backend in [CommitmentBackend::Blake2s, CommitmentBackend::Pedersen, CommitmentBackend::Poseidon] {
+        let commitment = backend.commit(Curve::Bls12_381, &board, &randomness).unwrap();
+        assert_ne!(commitment, backend.commit(Curve::Bls12_381, &other_board, &randomness).unwrap());
+        assert_ne!(commitment, backend.commit(Curve::Bls12_381, &board, &[8u8; 32]).unwrap());
+
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        let board_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"), &other_board).unwrap();
+        let rng_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "randomness"), &randomness).unwrap();
+        backend.enforce_commitment(cs.clone(), &board_var, &rng_var, &commitment).unwrap();
+        assert!(!cs.is_satisfied().unwrap(), "{:?}", backend);
+    }
+}
+
+#[test]
+fn test_constraint_counts() {
+    use ark_relations::r1cs::ConstraintSystem;
+
+    // the snark-friendly hashes should be much cheaper than blake2s on a full size board
+    let board: Vec<u8> = vec![0; 100];
+    let randomness = [7u8; 32];
+
+    let mut counts = vec![];
+    for backend in [CommitmentBackend::Blake2s, CommitmentBackend::Pedersen, CommitmentBackend::Poseidon] {
+        let commitment = backend.commit(Curve::Bls12_381, &board, &randomness).unwrap();
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        let board_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"), &board).unwrap();
+        let rng_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "randomness"), &randomness).unwrap();
+        backend.enforce_commitment(cs.clone(), &board_var, &rng_var, &commitment).unwrap();
+        counts.push(cs.num_constraints());
+    }
+
+    assert!(counts[1] < counts[0]);
+    assert!(counts[2] < counts[1]);
+}
+
+#[test]
+fn test_commitments_on_bls12_377() {
+    use ark_bls12_377::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
+    let secret = [3u8; SECRET_LEN];
+    let randomness = derive_randomness(&secret).unwrap();
+
+    // blake2s and poseidon open over the bls12-377 scalar field just the same
+    for backend in [CommitmentBackend::Blake2s, CommitmentBackend::Poseidon] {
+        assert!(backend.supports(Curve::Bls12_377));
+        let commitment = backend.commit(Curve::Bls12_377, &board, &randomness).unwrap();
+        assert!(backend.is_well_formed(Curve::Bls12_377, &commitment), "{:?}", backend);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let board_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"), &board).unwrap();
+        let secret_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "secret"), &secret).unwrap();
+        let rng_var = derive_randomness_gadget(&secret_var).unwrap();
+        backend.enforce_commitment(cs.clone(), &board_var, &rng_var, &commitment).unwrap();
+        assert!(cs.is_satisfied().unwrap(), "{:?}", backend);
+        assert_eq!(cs.num_instance_variables(), backend.to_inputs::<Fr>(&commitment).unwrap().len() + 1, "{:?}", backend);
+    }
+
+    // poseidon hashes in the field, so its commitments differ between the curves
+    let poseidon = CommitmentBackend::Poseidon;
+    assert_ne!(poseidon.commit(Curve::Bls12_377, &board, &randomness).unwrap(), poseidon.commit(Curve::Bls12_381, &board, &randomness).unwrap());
+
+    // and pedersen, on jubjub, can only be opened over bls12-381
+    let pedersen = CommitmentBackend::Pedersen;
+    assert!(!pedersen.supports(Curve::Bls12_377));
+    assert!(matches!(pedersen.commit(Curve::Bls12_377, &board, &randomness), Err(GameError::BadInput(_))));
+    let commitment = pedersen.commit(Curve::Bls12_381, &board, &randomness).unwrap();
+    assert!(!pedersen.is_well_formed(Curve::Bls12_377, &commitment));
+    assert!(matches!(pedersen.to_inputs::<Fr>(&commitment), Err(GameError::CommitmentMismatch)));
+}
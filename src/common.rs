@@ -1 +1,451 @@
-pub type ConstraintF = ark_ed_on_bls12_381::Fq;
\ No newline at end of file
+use crate::commitment::CircuitField;
+use crate::error::GameError;
+use crate::proof_system::ProofSystem;
+use ark_ec::PairingEngine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+pub type ConstraintF = ark_ed_on_bls12_381::Fq;
+
+/// direction a ship extends in from its origin tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    // along a row, towards higher tile numbers
+    Horizontal,
+    // down a column
+    Vertical,
+}
+
+impl Orientation {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Orientation::Horizontal => 0,
+            Orientation::Vertical => 1,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<Orientation> {
+        match id {
+            0 => Some(Orientation::Horizontal),
+            1 => Some(Orientation::Vertical),
+            _ => None,
+        }
+    }
+}
+
+/**
+*   house rules on how close ships may be placed. every rule forbids overlapping ships,
+*   the rule is a public input of the board proof so both players play by the same one
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdjacencyRule {
+    /// ships may lie right next to each other
+    AnythingGoes,
+    /// ships may not share an edge, but may meet at a corner
+    NoTouch,
+    /// ships may not touch at all, not even diagonally
+    NoDiagonalTouch,
+}
+
+impl AdjacencyRule {
+    pub fn name(self) -> &'static str {
+        match self {
+            AdjacencyRule::AnythingGoes => "anything-goes",
+            AdjacencyRule::NoTouch => "no-touch",
+            AdjacencyRule::NoDiagonalTouch => "no-diagonal-touch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AdjacencyRule> {
+        match name {
+            "anything-goes" => Some(AdjacencyRule::AnythingGoes),
+            "no-touch" => Some(AdjacencyRule::NoTouch),
+            "no-diagonal-touch" => Some(AdjacencyRule::NoDiagonalTouch),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            AdjacencyRule::AnythingGoes => 0,
+            AdjacencyRule::NoTouch => 1,
+            AdjacencyRule::NoDiagonalTouch => 2,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<AdjacencyRule> {
+        match id {
+            0 => Some(AdjacencyRule::AnythingGoes),
+            1 => Some(AdjacencyRule::NoTouch),
+            2 => Some(AdjacencyRule::NoDiagonalTouch),
+            _ => None,
+        }
+    }
+
+    /// whether two different ships may not occupy neighbouring tiles, diagonal ones or
+    /// ones sharing an edge
+    pub fn forbids(self, diagonal: bool) -> bool {
+        match self {
+            AdjacencyRule::AnythingGoes => false,
+            AdjacencyRule::NoTouch => !diagonal,
+            AdjacencyRule::NoDiagonalTouch => true,
+        }
+    }
+}
+
+/**
+*   the pairing curve proofs and keys are made over, chosen when the game is created.
+*   bls12-377 proofs can be verified in a circuit over bw6-761, see GameCurve
+*/
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Bls12_381,
+    Bls12_377,
+}
+
+impl Curve {
+    pub fn name(self) -> &'static str {
+        match self {
+            Curve::Bls12_381 => "bls12-381",
+            Curve::Bls12_377 => "bls12-377",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Curve> {
+        match name {
+            "bls12-381" => Some(Curve::Bls12_381),
+            "bls12-377" => Some(Curve::Bls12_377),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Curve::Bls12_381 => 0,
+            Curve::Bls12_377 => 1,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<Curve> {
+        match id {
+            0 => Some(Curve::Bls12_381),
+            1 => Some(Curve::Bls12_377),
+            _ => None,
+        }
+    }
+}
+
+/**
+*   a pairing engine the game can prove on: ark_bls12_381::Bls12_381 or ark_bls12_377::Bls12_377.
+*   the circuits are built over its scalar field, which says which Curve it is
+*/
+pub trait GameCurve: PairingEngine<Fr: CircuitField> {}
+
+impl<E: PairingEngine<Fr: CircuitField>> GameCurve for E {}
+
+/**
+*   the zk-snark proofs and keys are made with, chosen with the curve. groth16 needs a
+*   trusted setup for every game shape, plonk one universal setup for all of them, see
+*   ProofSystem
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum System {
+    Groth16,
+    Plonk,
+}
+
+impl System {
+    pub fn name(self) -> &'static str {
+        match self {
+            System::Groth16 => "groth16",
+            System::Plonk => "plonk",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<System> {
+        match name {
+            "groth16" => Some(System::Groth16),
+            "plonk" => Some(System::Plonk),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            System::Groth16 => 0,
+            System::Plonk => 1,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<System> {
+        match id {
+            0 => Some(System::Groth16),
+            1 => Some(System::Plonk),
+            _ => None,
+        }
+    }
+}
+
+/**
+*   a proof system the game can prove with: groth16 or plonk over one of the game's curves,
+*   e.g. Groth16<Bls12_381> or Plonk<Bls12_377>. its field says which Curve it is on and
+*   SYSTEM which System
+*/
+pub trait GameSystem: ProofSystem<Field: CircuitField> {}
+
+impl<P: ProofSystem<Field: CircuitField>> GameSystem for P {}
+
+/**
+*   how many shots a player fires each turn. in a salvo the defender answers every shot
+*   with a single proof, see SalvoVerifier
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Salvo {
+    /// the classic game, one shot a turn
+    Off,
+    /// the same number of shots every turn, at least 2
+    Fixed(u8),
+    /// one shot for each of the shooter's ships still afloat
+    Ships,
+}
+
+impl Salvo {
+    /// "off", "ships" or the number of shots
+    pub fn name(self) -> String {
+        match self {
+            Salvo::Off => "off".to_string(),
+            Salvo::Fixed(shots) => shots.to_string(),
+            Salvo::Ships => "ships".to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Salvo> {
+        match name {
+            "off" => Some(Salvo::Off),
+            "ships" => Some(Salvo::Ships),
+            shots => shots.parse().ok().and_then(Salvo::from_u8).filter(|salvo| matches!(salvo, Salvo::Fixed(_))),
+        }
+    }
+
+    /// 0 for off, 255 for ships and the number of shots otherwise
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Salvo::Off => 0,
+            Salvo::Fixed(shots) => shots,
+            Salvo::Ships => u8::MAX,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<Salvo> {
+        match id {
+            0 => Some(Salvo::Off),
+            1 => None,
+            u8::MAX => Some(Salvo::Ships),
+            shots => Some(Salvo::Fixed(shots)),
+        }
+    }
+
+    /// the most shots a turn can have with this many ships in the fleet, the number of
+    /// shots the salvo circuit is built for
+    pub fn max_shots(self, ships: usize) -> usize {
+        match self {
+            Salvo::Off => 1,
+            Salvo::Fixed(shots) => shots as usize,
+            Salvo::Ships => ships,
+        }
+    }
+
+    /// shots fired by a player with this many ships afloat, onto a board with this many
+    /// tiles not yet attacked
+    pub fn shots(self, afloat: usize, unattacked: usize) -> usize {
+        let shots = match self {
+            Salvo::Off => 1,
+            Salvo::Fixed(shots) => shots as usize,
+            Salvo::Ships => afloat,
+        };
+        shots.min(unattacked)
+    }
+}
+
+/**
+*   the dimensions of a board. tiles are numbered row by row from 0 at the top left,
+*   so tile t is in row t / width and column t % width
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardGeometry {
+    pub width: u8,
+    pub height: u8,
+}
+
+impl BoardGeometry {
+    pub fn new(width: u8, height: u8) -> BoardGeometry {
+        BoardGeometry { width, height }
+    }
+
+    /// a square board, e.g. the classic 10x10
+    pub fn square(side: u8) -> BoardGeometry {
+        BoardGeometry::new(side, side)
+    }
+
+    /// number of tiles on the board
+    pub fn tiles(self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    pub fn contains(self, tile: u16) -> bool {
+        (tile as usize) < self.tiles()
+    }
+}
+
+impl std::fmt::Display for BoardGeometry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// parses "WIDTHxHEIGHT", e.g. "10x10"
+impl FromStr for BoardGeometry {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<BoardGeometry, GameError> {
+        let bad = || GameError::BadInput(format!("'{}' is not a board size like 10x10", s.trim()));
+        let (width, height) = s.trim().split_once(['x', 'X']).ok_or_else(bad)?;
+        Ok(BoardGeometry::new(width.trim().parse().map_err(|_| bad())?, height.trim().parse().map_err(|_| bad())?))
+    }
+}
+
+/**
+*   a single ship placement. origin is the top/left-most tile of the ship, its length
+*   comes from the ship's entry in the fleet.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ship {
+    pub origin: u16,
+    pub orientation: Orientation,
+}
+
+/// origin as a u16 then the orientation as a u8
+impl CanonicalSerialize for Ship {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.origin.serialize(&mut writer)?;
+        self.orientation.to_u8().serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        3
+    }
+}
+
+impl CanonicalDeserialize for Ship {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Ship, SerializationError> {
+        Ok(Ship {
+            origin: u16::deserialize(&mut reader)?,
+            orientation: Orientation::from_u8(u8::deserialize(&mut reader)?).ok_or(SerializationError::InvalidData)?,
+        })
+    }
+}
+
+/**
+*   returns the tiles covered by a ship of the given length, or None if the ship is
+*   empty, leaves the board or wraps across the edge of a row
+*/
+pub fn ship_tiles(ship: &Ship, length: u8, geometry: BoardGeometry) -> Option<Vec<usize>> {
+    let width = geometry.width as usize;
+    let origin = ship.origin as usize;
+    let length = length as usize;
+    if length == 0 || !geometry.contains(ship.origin) {
+        return None;
+    }
+
+    let (row, col) = (origin / width, origin % width);
+    match ship.orientation {
+        Orientation::Horizontal => {
+            if col + length > width {
+                return None;
+            }
+            Some((0..length).map(|k| origin + k).collect())
+        }
+        Orientation::Vertical => {
+            if row + length > geometry.height as usize {
+                return None;
+            }
+            Some((0..length).map(|k| origin + k * width).collect())
+        }
+    }
+}
+
+/**
+*   every in-bounds placement of a ship of the given length
+*/
+pub fn legal_placements(length: u8, geometry: BoardGeometry) -> Vec<Ship> {
+    let mut placements = Vec::new();
+    for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+        for origin in 0..geometry.tiles() as u16 {
+            let ship = Ship { origin, orientation };
+            if ship_tiles(&ship, length, geometry).is_some() {
+                placements.push(ship);
+            }
+        }
+    }
+    placements
+}
+
+/**
+*   every pair of tiles that touch, once each, and whether they only meet at a corner
+*/
+pub fn neighbours(geometry: BoardGeometry) -> Vec<(usize, usize, bool)> {
+    let (width, height) = (geometry.width as usize, geometry.height as usize);
+    let mut pairs = Vec::new();
+    for row in 0..height {
+        for col in 0..width {
+            let t = row * width + col;
+            if col + 1 < width {
+                pairs.push((t, t + 1, false));
+            }
+            if row + 1 < height {
+                pairs.push((t, t + width, false));
+                if col + 1 < width {
+                    pairs.push((t, t + width + 1, true));
+                }
+                if col > 0 {
+                    pairs.push((t, t + width - 1, true));
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/**
+*   the first two touching tiles of the board that hold different ships the rule keeps
+*   apart, None if the board keeps to the rule
+*/
+pub fn rule_broken(board: &[u8], geometry: BoardGeometry, rule: AdjacencyRule) -> Option<(usize, usize)> {
+    neighbours(geometry).into_iter()
+        .filter(|(_, _, diagonal)| rule.forbids(*diagonal))
+        .map(|(t, u, _)| (t, u))
+        .find(|(t, u)| board[*t] != 0 && board[*u] != 0 && board[*t] != board[*u])
+}
+
+/**
+*   builds the board covered by the given ships, one per fleet entry, or None if any ship
+*   is out of bounds or two ships overlap. each tile holds the number of the ship on it,
+*   its index in the fleet + 1, and empty tiles are 0
+*/
+pub fn fleet_board(ships: &[Ship], fleet: &[u8], geometry: BoardGeometry) -> Option<Vec<u8>> {
+    if ships.len() != fleet.len() || fleet.len() > u8::MAX as usize {
+        return None;
+    }
+    let mut board = vec![0u8; geometry.tiles()];
+    for (i, (ship, length)) in ships.iter().zip(fleet).enumerate() {
+        for t in ship_tiles(ship, *length, geometry)? {
+            if board[t] != 0 {
+                return None;
+            }
+            board[t] = i as u8 + 1;
+        }
+    }
+    Some(board)
+}
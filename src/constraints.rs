@@ -1,117 +1,568 @@
 use crate::common::*;
-use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_r1cs_std::prelude::*;
 use ark_r1cs_std::{ToConstraintFieldGadget};
-use ark_std::rand::Rng;
 use ark_r1cs_std::fields::fp::FpVar;
-use ark_crypto_primitives::{CommitmentGadget, CommitmentScheme};
-use ark_crypto_primitives::commitment::blake2s::constraints::{CommGadget, RandomnessVar};
+use crate::commitment::{derive_randomness_gadget, CircuitField, CommitmentBackend};
+use ark_ff::PrimeField;
+#[cfg(test)]
 use ark_crypto_primitives::commitment::blake2s::Commitment;
-use ark_crypto_primitives::prf::blake2s::constraints::OutputVar;
 
 
 #[derive(Clone)]
 pub struct BoardVerifier {
     // public
-    pub ships: u8,
-    pub b_size : u8,
-    pub commitments: Vec<Vec<u8>>,
+    pub fleet: Vec<u8>, // length of each ship, e.g. [5, 4, 3, 3, 2]
+    pub geometry: BoardGeometry,
+    pub rule: AdjacencyRule,
+    pub commitment: Vec<u8>,
+    pub backend: CommitmentBackend,
 
     // private
-    pub board: Option<Vec<u8>>, // 0 for empty tile, 1 for battleship
-    pub rng_in: Option<Vec<Vec<u8>>>,
+    pub board: Option<Vec<u8>>, // 0 for empty tile, otherwise the number of the ship on it (its index in the fleet + 1)
+    pub ships: Option<Vec<Ship>>, // placement of each ship, in the same order as fleet
+    pub secret: Option<Vec<u8>>, // the player secret the commitment randomness is derived from
 }
 
-impl ConstraintSynthesizer<ConstraintF> for BoardVerifier {
-    fn generate_constraints(self, cs: ConstraintSystemRef<ConstraintF>) -> ark_relations::r1cs::Result<()> {
+impl<F: CircuitField> ConstraintSynthesizer<F> for BoardVerifier {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> ark_relations::r1cs::Result<()> {
+
+        // setup fleet
+        let mut fleet = vec![];
+        for length in &self.fleet {
+            let length = FpVar::<F>::new_input(ark_relations::ns!(cs, "fleet"), || Ok(F::from(*length)))?;
+            fleet.push(length);
+        }
 
-        // setup ship count
-        let ships = FpVar::<ConstraintF>::new_input(ark_relations::ns!(cs, "ships"), || Ok(ConstraintF::from(self.ships)))?;
+        // setup board dimensions
+        let width = FpVar::<F>::new_input(ark_relations::ns!(cs, "width"), || Ok(F::from(self.geometry.width)))?;
+        let height = FpVar::<F>::new_input(ark_relations::ns!(cs, "height"), || Ok(F::from(self.geometry.height)))?;
 
-        // setup board size
-        let b_size = FpVar::<ConstraintF>::new_input(ark_relations::ns!(cs, "b_size"), || Ok(ConstraintF::from(self.b_size)))?;
+        // setup adjacency rule, see AdjacencyRule::to_u8
+        let rule = FpVar::<F>::new_input(ark_relations::ns!(cs, "rule"), || Ok(F::from(self.rule.to_u8())))?;
 
         // setup board
-        let board = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"),  self.board.as_ref().unwrap())?;
-        let mut field_board: Vec<FpVar<ConstraintF>> = Vec::new();
+        let board = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"),  self.board.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        let mut field_board: Vec<FpVar<F>> = Vec::new();
         for i in board.clone() {
             let mut t = [i].to_constraint_field()?;
             field_board.push(t.pop().unwrap());
         }
 
 
-        // check that the sum of the board is equal to num ships
-        let mut board_sum = FpVar::zero();
         let mut board_len = FpVar::zero();
-        for i in &field_board {
-            board_sum = board_sum + i;
-            board_len = board_len + FpVar::one();
+        for _ in &field_board {
+            board_len += FpVar::one();
         }
 
-        let num_ships_correct = ships.is_eq(&board_sum)?;
+        // check the fleet lengths are the ones the placements below are built for
+        let mut fleet_is_public: Boolean<F> = Boolean::TRUE;
+        for (var, length) in fleet.iter().zip(&self.fleet) {
+            fleet_is_public = fleet_is_public.and(&var.is_eq(&FpVar::constant(F::from(*length)))?)?;
+        }
+
+        // check the board has width * height tiles
+        let board_size_correct = (&width * &height).is_eq(&board_len)?;
+
+        // check every ship is one of the straight, in-bounds placements for its length.
+        // each legal placement gets a selector bit and exactly one is set per ship. the
+        // tiles a ship covers come only from the selectors, the private placement just
+        // decides which selector to set
+        let ships = self.ships.ok_or(SynthesisError::AssignmentMissing)?;
+        let mut coverage: Vec<FpVar<F>> = vec![FpVar::zero(); field_board.len()];
+        let mut occupancy: Vec<FpVar<F>> = vec![FpVar::zero(); field_board.len()];
+        let mut ships_are_valid: Boolean<F> = Boolean::TRUE;
+        for (i, length) in self.fleet.iter().enumerate() {
+            let ship = *ships.get(i).ok_or(SynthesisError::AssignmentMissing)?;
+            let number = F::from((i + 1) as u64);
+
+            let mut selected = FpVar::zero();
+            for placement in legal_placements(*length, self.geometry) {
+                let selector: FpVar<F> = Boolean::new_witness(ark_relations::ns!(cs, "placement selector"), || Ok(placement == ship))?.into();
+                selected += &selector;
+
+                for t in ship_tiles(&placement, *length, self.geometry).unwrap() {
+                    if let (Some(tile), Some(count)) = (coverage.get_mut(t), occupancy.get_mut(t)) {
+                        *tile += &selector * number;
+                        *count += &selector;
+                    }
+                }
+            }
+
+            ships_are_valid = ships_are_valid.and(&selected.is_one()?)?;
+        }
 
-        // check everything within the board is 0 or 1
-        let mut values_are_valid: Boolean<ConstraintF> = Boolean::TRUE;
-        for i in field_board.clone() {
-            // true if i is zero or i is one
-            values_are_valid = values_are_valid.and(&i.is_zero()?.or(&i.is_one()?)?)?;
+        // check the board is exactly the union of the ships, every tile holding the number of
+        // the ship on it. overlapping ships cover a tile twice, and their numbers could add
+        // up to a third ship's, so each tile is also checked to be covered at most once
+        let mut board_matches_ships: Boolean<F> = Boolean::TRUE;
+        for ((tile, covered), count) in field_board.iter().zip(&coverage).zip(&occupancy) {
+            board_matches_ships = board_matches_ships.and(&tile.is_eq(covered)?)?;
+            board_matches_ships = board_matches_ships.and(&count.is_zero()?.or(&count.is_one()?)?)?;
         }
 
-        // check board size is correct
-        let board_size_correct = b_size.is_eq(&board_len)?;
+        // check no two ships touch where the rule forbids it. neighbouring tiles may both be
+        // taken only by the same ship, which a straight ship never is diagonally. the rule is
+        // only known as an input, so every pair is checked and the check switched on by it
+        let no_touching_edges = rule.is_zero()?.not();
+        let no_touching_corners = rule.is_eq(&FpVar::constant(F::from(AdjacencyRule::NoDiagonalTouch.to_u8())))?;
+        let rule_is_known = rule.is_zero()?.or(&rule.is_one()?)?.or(&no_touching_corners)?;
+        let mut taken = vec![];
+        for tile in &field_board {
+            taken.push(tile.is_zero()?.not());
+        }
+        let mut rule_is_kept: Boolean<F> = Boolean::TRUE;
+        for (t, u, diagonal) in neighbours(self.geometry) {
+            if let (Some(a), Some(b)) = (field_board.get(t), field_board.get(u)) {
+                let touching = taken[t].and(&taken[u])?.and(&a.is_eq(b)?.not())?;
+                let forbidden = if diagonal { &no_touching_corners } else { &no_touching_edges };
+                rule_is_kept = rule_is_kept.and(&touching.and(forbidden)?.not())?;
+            }
+        }
+
+        // check the board is the one that was committed to, with randomness derived from the
+        // player's secret. the other circuits take the derived randomness as it is, the
+        // commitment is already tied to the secret here
+        let secret = UInt8::new_witness_vec(ark_relations::ns!(cs, "secret"), self.secret.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        let randomness = derive_randomness_gadget(&secret)?;
+        self.backend.enforce_commitment(cs, &board, &randomness, &self.commitment)?;
+
+        fleet_is_public.enforce_equal(&Boolean::TRUE)?;
+        board_size_correct.enforce_equal(&Boolean::TRUE)?;
+        ships_are_valid.enforce_equal(&Boolean::TRUE)?;
+        board_matches_ships.enforce_equal(&Boolean::TRUE)?;
+        rule_is_known.enforce_equal(&Boolean::TRUE)?;
+        rule_is_kept.enforce_equal(&Boolean::TRUE)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct ShotVerifier {
+    // public
+    pub geometry: BoardGeometry,
+    pub target: u16,
+    pub hit: u8, // 1 if the target tile holds a battleship, 0 otherwise
+    pub commitment: Vec<u8>,
+    pub backend: CommitmentBackend,
+
+    // private
+    pub board: Option<Vec<u8>>, // 0 for empty tile, otherwise the number of the ship on it
+    pub rng_in: Option<Vec<u8>>,
+}
+
+impl<F: CircuitField> ConstraintSynthesizer<F> for ShotVerifier {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> ark_relations::r1cs::Result<()> {
 
-        // setup rng
-        let mut all_rng_witness = vec![];
-        for rng_vec in self.rng_in.unwrap() {
-            //let mut rng_witness = vec![];
-            let rng_witness = UInt8::new_witness_vec(ark_relations::ns!(cs, "rng witness"), &rng_vec);
-            let rng_witness = RandomnessVar(rng_witness.unwrap());
-            all_rng_witness.push(rng_witness);
+        // setup board dimensions
+        let width = FpVar::<F>::new_input(ark_relations::ns!(cs, "width"), || Ok(F::from(self.geometry.width)))?;
+        let height = FpVar::<F>::new_input(ark_relations::ns!(cs, "height"), || Ok(F::from(self.geometry.height)))?;
+
+        // setup shot
+        let target = FpVar::<F>::new_input(ark_relations::ns!(cs, "target"), || Ok(F::from(self.target)))?;
+        let hit = FpVar::<F>::new_input(ark_relations::ns!(cs, "hit"), || Ok(F::from(self.hit)))?;
+
+        // setup board
+        let board = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"),  self.board.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        let mut field_board: Vec<FpVar<F>> = Vec::new();
+        for i in board.clone() {
+            let mut t = [i].to_constraint_field()?;
+            field_board.push(t.pop().unwrap());
         }
 
-        // setup commitments
-        let mut all_comm_witness = vec![];
-        for comm_vec in self.commitments {
-            let comm_witness = UInt8::new_input_vec(ark_relations::ns!(cs, "commitment byte"), &comm_vec)?;
-            let out = OutputVar(comm_witness);
-            all_comm_witness.push(out);
+        let (target_on_board, occupied) = select_target(&target, &field_board)?;
+        let answer_correct = hit.is_eq(&occupied)?;
+
+        // check the board has width * height tiles
+        let mut board_len = FpVar::zero();
+        for _ in &field_board {
+            board_len += FpVar::one();
         }
+        let board_size_correct = (&width * &height).is_eq(&board_len)?;
+
+        // check the board is the one that was committed to
+        let rng_in = UInt8::new_witness_vec(ark_relations::ns!(cs, "rng witness"), self.rng_in.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        self.backend.enforce_commitment(cs, &board, &rng_in, &self.commitment)?;
+
+        target_on_board.enforce_equal(&Boolean::TRUE)?;
+        answer_correct.enforce_equal(&Boolean::TRUE)?;
+        board_size_correct.enforce_equal(&Boolean::TRUE)?;
 
+        Ok(())
+    }
+}
+
+/**
+*   selects the tile at the target. returns whether the target is on the board, exactly one
+*   tile index matching it, and 1 if that tile holds a battleship or 0 if it is empty
+*/
+fn select_target<F: PrimeField>(target: &FpVar<F>, field_board: &[FpVar<F>]) -> Result<(Boolean<F>, FpVar<F>), SynthesisError> {
+    let mut matches = FpVar::zero();
+    let mut target_tile = FpVar::zero();
+    for (i, tile) in field_board.iter().enumerate() {
+        let is_target: FpVar<F> = target.is_eq(&FpVar::constant(F::from(i as u64)))?.into();
+        matches += &is_target;
+        target_tile += is_target * tile;
+    }
+    Ok((matches.is_one()?, target_tile.is_zero()?.not().into()))
+}
 
-        let parameters = ();
-        let parameters_var = <CommGadget as CommitmentGadget<Commitment, ConstraintF>>::ParametersVar::new_input(
-            ark_relations::ns!(cs, "gadget_parameters"),
-            || Ok(&parameters),
-        ).unwrap();
+/**
+*   answers a whole salvo with one proof: every target is on the board and each hit bit
+*   says whether its target holds a battleship. the circuit is built for a fixed number of
+*   shots, a shorter salvo repeats its last shot to fill the rest (see pad_salvo).
+*   whether the targets are distinct and unattacked is public, so the players check that
+*   themselves
+*/
+#[derive(Clone)]
+pub struct SalvoVerifier {
+    // public
+    pub geometry: BoardGeometry,
+    pub targets: Vec<u16>,
+    pub hits: Vec<u8>, // one per target, 1 if it holds a battleship, 0 otherwise
+    pub commitment: Vec<u8>,
+    pub backend: CommitmentBackend,
+
+    // private
+    pub board: Option<Vec<u8>>, // 0 for empty tile, otherwise the number of the ship on it
+    pub rng_in: Option<Vec<u8>>,
+}
 
+impl<F: CircuitField> ConstraintSynthesizer<F> for SalvoVerifier {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> ark_relations::r1cs::Result<()> {
 
-        let mut results_vec = vec![];
-        for i in 0..field_board.len() {
-            let result_var = <CommGadget as CommitmentGadget<Commitment, ConstraintF>>::commit(
-                &parameters_var,
-                &[board[i].clone()],
-                &all_rng_witness[i],
-            ).unwrap();
-            results_vec.push(result_var);
+        // setup board dimensions
+        let width = FpVar::<F>::new_input(ark_relations::ns!(cs, "width"), || Ok(F::from(self.geometry.width)))?;
+        let height = FpVar::<F>::new_input(ark_relations::ns!(cs, "height"), || Ok(F::from(self.geometry.height)))?;
+
+        // setup salvo, all the targets and then all the hits
+        if self.targets.len() != self.hits.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let mut targets = vec![];
+        for target in &self.targets {
+            targets.push(FpVar::<F>::new_input(ark_relations::ns!(cs, "target"), || Ok(F::from(*target)))?);
+        }
+        let mut hits = vec![];
+        for hit in &self.hits {
+            hits.push(FpVar::<F>::new_input(ark_relations::ns!(cs, "hit"), || Ok(F::from(*hit)))?);
+        }
+
+        // setup board
+        let board = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"),  self.board.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        let mut field_board: Vec<FpVar<F>> = Vec::new();
+        for i in board.clone() {
+            let mut t = [i].to_constraint_field()?;
+            field_board.push(t.pop().unwrap());
+        }
+
+        // check every shot is on the board and answered truthfully
+        let mut salvo_correct: Boolean<F> = Boolean::TRUE;
+        for (target, hit) in targets.iter().zip(&hits) {
+            let (target_on_board, occupied) = select_target(target, &field_board)?;
+            salvo_correct = salvo_correct.and(&target_on_board)?.and(&hit.is_eq(&occupied)?)?;
         }
 
-        for i in 0..results_vec.len() {
-            all_comm_witness[i].enforce_equal(&results_vec[i])?;
+        // check the board has width * height tiles
+        let mut board_len = FpVar::zero();
+        for _ in &field_board {
+            board_len += FpVar::one();
         }
+        let board_size_correct = (&width * &height).is_eq(&board_len)?;
+
+        // check the board is the one that was committed to, once for the whole salvo
+        let rng_in = UInt8::new_witness_vec(ark_relations::ns!(cs, "rng witness"), self.rng_in.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        self.backend.enforce_commitment(cs, &board, &rng_in, &self.commitment)?;
 
-        num_ships_correct.enforce_equal(&Boolean::TRUE)?;
-        values_are_valid.enforce_equal(&Boolean::TRUE)?;
+        salvo_correct.enforce_equal(&Boolean::TRUE)?;
         board_size_correct.enforce_equal(&Boolean::TRUE)?;
 
         Ok(())
     }
 }
 
+/**
+*   fills a salvo of at least one shot up to the number of shots the circuit is built for by
+*   repeating its last shot. None if the salvo is empty or too long
+*/
+pub fn pad_salvo(targets: &[u16], hits: &[bool], shots: usize) -> Option<(Vec<u16>, Vec<u8>)> {
+    if targets.is_empty() || targets.len() > shots || targets.len() != hits.len() {
+        return None;
+    }
+    let mut targets = targets.to_vec();
+    let mut hits: Vec<u8> = hits.iter().map(|hit| *hit as u8).collect();
+    targets.resize(shots, *targets.last()?);
+    hits.resize(shots, *hits.last()?);
+    Some((targets, hits))
+}
+
+/// hit tiles packed into each public input of the sunk circuit, well under the 253 bit
+/// modulus of the smaller field, bls12-377's
+pub const HITS_PER_INPUT: usize = 248;
+
+/**
+*   packs the attacker's hits (one bool per tile) into public inputs, little endian
+*   within each chunk of HITS_PER_INPUT tiles
+*/
+pub fn pack_hits<F: PrimeField>(hits: &[bool]) -> Vec<F> {
+    hits.chunks(HITS_PER_INPUT)
+        .map(|chunk| chunk.iter().rev().fold(F::zero(), |acc, hit| acc.double() + F::from(*hit)))
+        .collect()
+}
+
+/**
+*   proves the ship at index `ship` of the fleet was sunk by the shot at `target`: the
+*   target tile holds that ship and every tile holding it is in the attacker's hits.
+*   nothing else about the board is revealed
+*/
+#[derive(Clone)]
+pub struct SunkVerifier {
+    // public
+    pub geometry: BoardGeometry,
+    pub target: u16,
+    pub ship: u8, // index in the fleet of the sunk ship
+    pub hits: Vec<bool>, // tiles the attacker has hit, one per tile
+    pub commitment: Vec<u8>,
+    pub backend: CommitmentBackend,
+
+    // private
+    pub board: Option<Vec<u8>>, // 0 for empty tile, otherwise the number of the ship on it
+    pub rng_in: Option<Vec<u8>>,
+}
+
+impl<F: CircuitField> ConstraintSynthesizer<F> for SunkVerifier {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> ark_relations::r1cs::Result<()> {
+
+        // setup board dimensions
+        let width = FpVar::<F>::new_input(ark_relations::ns!(cs, "width"), || Ok(F::from(self.geometry.width)))?;
+        let height = FpVar::<F>::new_input(ark_relations::ns!(cs, "height"), || Ok(F::from(self.geometry.height)))?;
+
+        // setup the announcement
+        let target = FpVar::<F>::new_input(ark_relations::ns!(cs, "target"), || Ok(F::from(self.target)))?;
+        let ship = FpVar::<F>::new_input(ark_relations::ns!(cs, "ship"), || Ok(F::from(self.ship)))?;
+        let ship_number = &ship + FpVar::one();
+
+        // setup hits, each packed input is the sum of its bits
+        let mut hits = Vec::new();
+        for (chunk, packed) in self.hits.chunks(HITS_PER_INPUT).zip(pack_hits::<F>(&self.hits)) {
+            let packed = FpVar::<F>::new_input(ark_relations::ns!(cs, "hits"), || Ok(packed))?;
+            let bits = Vec::<Boolean<F>>::new_witness(ark_relations::ns!(cs, "hit bits"), || Ok(chunk.to_vec()))?;
+            Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(&packed)?;
+            hits.extend(bits);
+        }
+
+        // setup board
+        let board = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"),  self.board.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        let mut field_board: Vec<FpVar<F>> = Vec::new();
+        for i in board.clone() {
+            let mut t = [i].to_constraint_field()?;
+            field_board.push(t.pop().unwrap());
+        }
+        if hits.len() != field_board.len() {
+            return Err(SynthesisError::AssignmentMissing);
+        }
+
+        // select the target tile and check every tile of the ship has been hit
+        let mut board_len = FpVar::zero();
+        let mut matches = FpVar::zero();
+        let mut target_tile = FpVar::zero();
+        let mut ship_is_sunk: Boolean<F> = Boolean::TRUE;
+        for (i, (tile, hit)) in field_board.iter().zip(&hits).enumerate() {
+            let is_target: FpVar<F> = target.is_eq(&FpVar::constant(F::from(i as u64)))?.into();
+            matches += &is_target;
+            target_tile += is_target * tile;
+            board_len += FpVar::one();
+
+            let on_ship = tile.is_eq(&ship_number)?;
+            ship_is_sunk = ship_is_sunk.and(&on_ship.not().or(hit)?)?;
+        }
+
+        let target_on_board = matches.is_one()?;
+        let target_on_ship = target_tile.is_eq(&ship_number)?;
+
+        // check the board has width * height tiles
+        let board_size_correct = (&width * &height).is_eq(&board_len)?;
+
+        // check the board is the one that was committed to
+        let rng_in = UInt8::new_witness_vec(ark_relations::ns!(cs, "rng witness"), self.rng_in.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        self.backend.enforce_commitment(cs, &board, &rng_in, &self.commitment)?;
+
+        target_on_board.enforce_equal(&Boolean::TRUE)?;
+        target_on_ship.enforce_equal(&Boolean::TRUE)?;
+        ship_is_sunk.enforce_equal(&Boolean::TRUE)?;
+        board_size_correct.enforce_equal(&Boolean::TRUE)?;
+
+        Ok(())
+    }
+}
+
+/**
+*   proves how many ships of the committed fleet are still afloat given the attacker's hits:
+*   a ship is afloat if any tile holding it hasn't been hit. remaining = 0 proves the
+*   attacker has sunk the whole fleet
+*/
+#[derive(Clone)]
+pub struct RemainingVerifier {
+    // public
+    pub geometry: BoardGeometry,
+    pub ships: u8, // number of ships in the fleet
+    pub hits: Vec<bool>, // tiles the attacker has hit, one per tile
+    pub remaining: u8,
+    pub commitment: Vec<u8>,
+    pub backend: CommitmentBackend,
+
+    // private
+    pub board: Option<Vec<u8>>, // 0 for empty tile, otherwise the number of the ship on it
+    pub rng_in: Option<Vec<u8>>,
+}
+
+impl<F: CircuitField> ConstraintSynthesizer<F> for RemainingVerifier {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> ark_relations::r1cs::Result<()> {
+
+        // setup board dimensions and fleet size
+        let width = FpVar::<F>::new_input(ark_relations::ns!(cs, "width"), || Ok(F::from(self.geometry.width)))?;
+        let height = FpVar::<F>::new_input(ark_relations::ns!(cs, "height"), || Ok(F::from(self.geometry.height)))?;
+        let ships = FpVar::<F>::new_input(ark_relations::ns!(cs, "ships"), || Ok(F::from(self.ships)))?;
+
+        // setup hits, each packed input is the sum of its bits
+        let mut hits = Vec::new();
+        for (chunk, packed) in self.hits.chunks(HITS_PER_INPUT).zip(pack_hits::<F>(&self.hits)) {
+            let packed = FpVar::<F>::new_input(ark_relations::ns!(cs, "hits"), || Ok(packed))?;
+            let bits = Vec::<Boolean<F>>::new_witness(ark_relations::ns!(cs, "hit bits"), || Ok(chunk.to_vec()))?;
+            Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(&packed)?;
+            hits.extend(bits);
+        }
+
+        let remaining = FpVar::<F>::new_input(ark_relations::ns!(cs, "remaining"), || Ok(F::from(self.remaining)))?;
+
+        // setup board
+        let board = UInt8::new_witness_vec(ark_relations::ns!(cs, "board"),  self.board.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        let mut field_board: Vec<FpVar<F>> = Vec::new();
+        for i in board.clone() {
+            let mut t = [i].to_constraint_field()?;
+            field_board.push(t.pop().unwrap());
+        }
+        if hits.len() != field_board.len() {
+            return Err(SynthesisError::AssignmentMissing);
+        }
+
+        let mut board_len = FpVar::zero();
+        for _ in &field_board {
+            board_len += FpVar::one();
+        }
+
+        // check the fleet size is the one the count below is built for
+        let ships_is_public = ships.is_eq(&FpVar::constant(F::from(self.ships)))?;
+
+        // count the ships with a tile that hasn't been hit
+        let mut afloat = FpVar::zero();
+        for number in 1..=self.ships {
+            let number = FpVar::constant(F::from(number));
+            let mut ship_afloat: Boolean<F> = Boolean::FALSE;
+            for (tile, hit) in field_board.iter().zip(&hits) {
+                ship_afloat = ship_afloat.or(&tile.is_eq(&number)?.and(&hit.not())?)?;
+            }
+            afloat += FpVar::from(ship_afloat);
+        }
+        let remaining_correct = remaining.is_eq(&afloat)?;
+
+        // check the board has width * height tiles
+        let board_size_correct = (&width * &height).is_eq(&board_len)?;
+
+        // check the board is the one that was committed to
+        let rng_in = UInt8::new_witness_vec(ark_relations::ns!(cs, "rng witness"), self.rng_in.as_ref().ok_or(SynthesisError::AssignmentMissing)?)?;
+        self.backend.enforce_commitment(cs, &board, &rng_in, &self.commitment)?;
+
+        ships_is_public.enforce_equal(&Boolean::TRUE)?;
+        remaining_correct.enforce_equal(&Boolean::TRUE)?;
+        board_size_correct.enforce_equal(&Boolean::TRUE)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+use ark_std::rand::Rng;
+#[cfg(test)]
+use ark_crypto_primitives::CommitmentScheme;
+#[cfg(test)]
+use crate::commitment::derive_randomness;
+
+/// blake2s commitment to the board with fresh test randomness, returns (randomness, commitment)
+#[cfg(test)]
+fn test_commitment(board: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut rng = ark_std::test_rng();
+    let mut randomness = [0u8; 32];
+    rng.fill(&mut randomness);
+
+    let comm = Commitment::commit(&(), board, &randomness).unwrap().to_vec();
+    (randomness.to_vec(), comm)
+}
+
+/// like test_commitment, with the randomness derived from a test secret, returns (secret, commitment)
+#[cfg(test)]
+fn test_board_commitment(board: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut rng = ark_std::test_rng();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+
+    let comm = Commitment::commit(&(), board, &derive_randomness(&secret).unwrap()).unwrap().to_vec();
+    (secret.to_vec(), comm)
+}
+
+/// a board circuit over a 3x3 board committed with blake2s
+#[cfg(test)]
+fn board_circuit(board: Vec<u8>, fleet: Vec<u8>, ships: Vec<Ship>) -> BoardVerifier {
+    let (secret, comm) = test_board_commitment(&board);
+    BoardVerifier {
+        fleet,
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
+        board: Some(board),
+        ships: Some(ships),
+        secret: Some(secret),
+    }
+}
+
+/// a shot at the 3x3 board [1,1,1,0,0,0,0,0,0] committed with blake2s
+#[cfg(test)]
+fn shot_circuit(target: u16, hit: u8) -> ShotVerifier {
+    let board = vec![1,1,1,0,0,0,0,0,0];
+    let (randomness, comm) = test_commitment(&board);
+    ShotVerifier {
+        geometry: BoardGeometry::square(3),
+        target,
+        hit,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
+        board: Some(board),
+        rng_in: Some(randomness),
+    }
+}
+
+/// generates the circuit's constraints and checks whether the witness satisfies them
+#[cfg(test)]
+fn satisfied<C: ConstraintSynthesizer<ConstraintF>>(circuit: C) -> bool {
+    use ark_relations::r1cs::{ConstraintLayer, ConstraintSystem, TracingMode};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    // First, some boilerplate that helps with debugging
+    let mut layer = ConstraintLayer::default();
+    layer.mode = TracingMode::OnlyConstraints;
+    let subscriber = tracing_subscriber::Registry::default().with(layer);
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    circuit.generate_constraints(cs.clone()).unwrap();
+    cs.is_satisfied().unwrap()
+}
+
 // you probably want to comment this test out as it takes a LONG time to run
 #[test]
 fn benchmark(){
-    use ark_relations::r1cs::{ConstraintLayer, ConstraintSystem, TracingMode};
-    use tracing_subscriber::layer::SubscriberExt;
     use ark_groth16::{
         create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
     };
@@ -119,34 +570,37 @@ fn benchmark(){
     use std::time::Instant;
     use ark_ff::{ToConstraintField};
 
-    let board_sizes = [4, 9, 16, 25, 36, 49, 64, 81, 100];
+    let board_sides = [2, 3, 4, 5, 6, 7, 8, 9, 10];
 
-    for size in board_sizes {
+    for side in board_sides {
+        let size = side as usize * side as usize;
         let mut board: Vec<u8> = vec![0; size];
         board[0] = 1;
-        board[1] = 1;
-        board[2] = 1;
+        board[1] = 2;
+        board[2] = 3;
 
         let mut rng = ark_std::test_rng();
-        let mut randomness:Vec<Vec<u8>> = Vec::new();
-
+        let mut secret = [0u8; 32];
+        rng.fill(&mut secret);
+        let randomness = derive_randomness(&secret).unwrap();
 
         let params = ();
-        let mut comms:Vec<Vec<u8>> = Vec::new();
-        for i in 0..board.len() {
-            let mut rand = [0u8; 32];
-            rng.fill(&mut rand);
-            randomness.push(rand.to_vec());
-            comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-        }
+        let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
         let circuit = BoardVerifier {
-            ships: 3,
-            b_size: size as u8,
-            commitments: comms.clone(),
+            fleet: vec![1, 1, 1],
+            geometry: BoardGeometry::square(side),
+            rule: AdjacencyRule::AnythingGoes,
+            commitment: comm.clone(),
+            backend: CommitmentBackend::Blake2s,
 
-            rng_in: Some(randomness),
+            secret: Some(secret.to_vec()),
             board: Some(board),
+            ships: Some(vec![
+                Ship { origin: 0, orientation: Orientation::Horizontal },
+                Ship { origin: 1, orientation: Orientation::Horizontal },
+                Ship { origin: 2, orientation: Orientation::Horizontal },
+            ]),
         };
 
 
@@ -166,14 +620,13 @@ fn benchmark(){
 
         println!("Proving time for {}: {}", size, start.elapsed().as_secs());
 
-        let  mut inputs: Vec<_> = Vec::new();
-        inputs.push(Fr::from(3));
-        inputs.push(Fr::from(size as u8));
+        let  mut inputs: Vec<_> = vec![Fr::from(1); 3];
+        inputs.push(Fr::from(side));
+        inputs.push(Fr::from(side));
+        inputs.push(Fr::from(AdjacencyRule::AnythingGoes.to_u8()));
 
-        for i in comms {
-            let mut field_elements: Vec<Fr> = ToConstraintField::<Fr>::to_field_elements(&i).unwrap();
-            inputs.append(&mut field_elements);
-        }
+        let mut field_elements: Vec<Fr> = ToConstraintField::<Fr>::to_field_elements(&comm).unwrap();
+        inputs.append(&mut field_elements);
 
         let start = Instant::now();
         // Check the proof
@@ -188,8 +641,6 @@ fn benchmark(){
 
 #[test]
 fn test_zk() {
-    use ark_relations::r1cs::{ConstraintLayer, ConstraintSystem, TracingMode};
-    use tracing_subscriber::layer::SubscriberExt;
     use ark_groth16::{
         create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
     };
@@ -200,25 +651,23 @@ fn test_zk() {
     let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
 
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
-
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
     let circuit = BoardVerifier {
-        ships: 3,
-        b_size: 9,
-        commitments: comms.clone(),
+        fleet: vec![3],
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm.clone(),
+        backend: CommitmentBackend::Blake2s,
 
-        rng_in: Some(randomness),
+        secret: Some(secret.to_vec()),
         board: Some(board),
+        ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }]),
     };
 
 
@@ -231,14 +680,11 @@ fn test_zk() {
         create_random_proof(circuit, &params, &mut rng).unwrap()
     };
 
-    let  mut inputs: Vec<_> = Vec::new();
-    inputs.push(Fr::from(3));
-    inputs.push(Fr::from(9));
+    // fleet, width, height and rule
+    let  mut inputs: Vec<_> = vec![Fr::from(3), Fr::from(3), Fr::from(3), Fr::from(AdjacencyRule::AnythingGoes.to_u8())];
 
-    for i in comms {
-        let mut field_elements: Vec<Fr> = ToConstraintField::<Fr>::to_field_elements(&i).unwrap();
-        inputs.append(&mut field_elements);
-    }
+    let mut field_elements: Vec<Fr> = ToConstraintField::<Fr>::to_field_elements(&comm).unwrap();
+    inputs.append(&mut field_elements);
 
     // Check the proof
     let r = verify_proof(&pvk, &proof, &inputs).unwrap();
@@ -254,25 +700,23 @@ fn constraints_test() {
     let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
 
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
-
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
     let circuit = BoardVerifier {
-        ships: 3,
-        b_size: 9,
-        commitments: comms,
+        fleet: vec![3],
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
 
-        rng_in: Some(randomness),
+        secret: Some(secret.to_vec()),
         board: Some(board),
+        ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }]),
     };
 
     // First, some boilerplate that helps with debugging
@@ -282,7 +726,7 @@ fn constraints_test() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     // Next, let's make the circuit!
-    let cs = ConstraintSystem::new_ref();
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
     circuit.generate_constraints(cs.clone()).unwrap();
     // Let's check whether the constraint system is satisfied
     let is_satisfied = cs.is_satisfied().unwrap();
@@ -301,24 +745,23 @@ fn test_incorrect_ships() {
 
     let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
 
     let circuit = BoardVerifier {
-        ships: 4,
-        b_size: 9,
-        commitments: comms,
+        fleet: vec![3, 1],
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
         board: Some(board),
-        rng_in: Some(randomness),
+        ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 4, orientation: Orientation::Horizontal }]),
+        secret: Some(secret.to_vec()),
     };
 
     // First, some boilerplate that helps with debugging
@@ -328,7 +771,7 @@ fn test_incorrect_ships() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     // Next, let's make the circuit!
-    let cs = ConstraintSystem::new_ref();
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
     circuit.generate_constraints(cs.clone()).unwrap();
     // Let's check whether the constraint system is satisfied
     let is_satisfied = cs.is_satisfied().unwrap();
@@ -343,24 +786,23 @@ fn test_incorrect_size() {
 
     let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
 
     let circuit = BoardVerifier {
-        ships: 3,
-        b_size: 10,
-        commitments: comms,
+        fleet: vec![3],
+        geometry: BoardGeometry::new(5, 2),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
         board: Some(board),
-        rng_in: Some(randomness),
+        ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }]),
+        secret: Some(secret.to_vec()),
     };
 
     // First, some boilerplate that helps with debugging
@@ -370,7 +812,7 @@ fn test_incorrect_size() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     // Next, let's make the circuit!
-    let cs = ConstraintSystem::new_ref();
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
     circuit.generate_constraints(cs.clone()).unwrap();
     // Let's check whether the constraint system is satisfied
     let is_satisfied = cs.is_satisfied().unwrap();
@@ -383,26 +825,25 @@ fn test_ships_same_as_size() {
     use ark_relations::r1cs::{ConstraintLayer, ConstraintSystem, TracingMode};
     use tracing_subscriber::layer::SubscriberExt;
 
-    let board: Vec<u8> = vec![1,1,1,1,1,1,1,1,1];
+    let board: Vec<u8> = vec![1,1,1,2,2,2,3,3,3];
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
 
     let circuit = BoardVerifier {
-        ships: 9,
-        b_size: 9,
-        commitments: comms,
+        fleet: vec![3, 3, 3],
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
         board: Some(board),
-        rng_in: Some(randomness),
+        ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 3, orientation: Orientation::Horizontal }, Ship { origin: 6, orientation: Orientation::Horizontal }]),
+        secret: Some(secret.to_vec()),
     };
 
     // First, some boilerplate that helps with debugging
@@ -412,7 +853,7 @@ fn test_ships_same_as_size() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     // Next, let's make the circuit!
-    let cs = ConstraintSystem::new_ref();
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
     circuit.generate_constraints(cs.clone()).unwrap();
     // Let's check whether the constraint system is satisfied
     let is_satisfied = cs.is_satisfied().unwrap();
@@ -427,24 +868,23 @@ fn test_no_ships() {
 
     let board: Vec<u8> = vec![0,0,0,0,0,0,0,0,0];
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
 
     let circuit = BoardVerifier {
-        ships: 0,
-        b_size: 9,
-        commitments: comms,
+        fleet: vec![],
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
         board: Some(board),
-        rng_in: Some(randomness),
+        ships: Some(vec![]),
+        secret: Some(secret.to_vec()),
     };
 
     // First, some boilerplate that helps with debugging
@@ -454,7 +894,7 @@ fn test_no_ships() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     // Next, let's make the circuit!
-    let cs = ConstraintSystem::new_ref();
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
     circuit.generate_constraints(cs.clone()).unwrap();
     // Let's check whether the constraint system is satisfied
     let is_satisfied = cs.is_satisfied().unwrap();
@@ -469,24 +909,23 @@ fn test_incorrect_board_value() {
 
     let board: Vec<u8> = vec![2,0,0,0,0,0,0,0,0];
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
 
     let circuit = BoardVerifier {
-        ships: 1,
-        b_size: 9,
-        commitments: comms,
+        fleet: vec![1],
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
         board: Some(board),
-        rng_in: Some(randomness),
+        ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }]),
+        secret: Some(secret.to_vec()),
     };
 
     // First, some boilerplate that helps with debugging
@@ -496,7 +935,7 @@ fn test_incorrect_board_value() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     // Next, let's make the circuit!
-    let cs = ConstraintSystem::new_ref();
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
     circuit.generate_constraints(cs.clone()).unwrap();
     // Let's check whether the constraint system is satisfied
     let is_satisfied = cs.is_satisfied().unwrap();
@@ -511,24 +950,23 @@ fn test_incorrect_board_value_no_ships() {
 
     let board: Vec<u8> = vec![2,0,0,0,0,0,0,0,0];
     let mut rng = ark_std::test_rng();
-    let mut randomness:Vec<Vec<u8>> = Vec::new();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
 
     let params = ();
-    let mut comms:Vec<Vec<u8>> = Vec::new();
-    for i in 0..board.len() {
-        let mut rand = [0u8; 32];
-        rng.fill(&mut rand);
-        randomness.push(rand.to_vec());
-        comms.push(Commitment::commit(&params, &[board[i]], &rand).unwrap().to_vec());
-    }
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
 
 
     let circuit = BoardVerifier {
-        ships: 0,
-        b_size: 9,
-        commitments: comms,
+        fleet: vec![],
+        geometry: BoardGeometry::square(3),
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
         board: Some(board),
-        rng_in: Some(randomness),
+        ships: Some(vec![]),
+        secret: Some(secret.to_vec()),
     };
 
     // First, some boilerplate that helps with debugging
@@ -538,10 +976,443 @@ fn test_incorrect_board_value_no_ships() {
     let _guard = tracing::subscriber::set_default(subscriber);
 
     // Next, let's make the circuit!
-    let cs = ConstraintSystem::new_ref();
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
     circuit.generate_constraints(cs.clone()).unwrap();
     // Let's check whether the constraint system is satisfied
     let is_satisfied = cs.is_satisfied().unwrap();
 
     assert!(!is_satisfied);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_vertical_ship() {
+    let circuit = board_circuit(vec![0,1,0,0,1,0,0,1,0], vec![3], vec![Ship { origin: 1, orientation: Orientation::Vertical }]);
+    assert!(satisfied(circuit));
+}
+
+#[test]
+fn test_mixed_fleet() {
+    let circuit = board_circuit(vec![1,1,0,0,0,2,0,0,2], vec![2, 2], vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 5, orientation: Orientation::Vertical }]);
+    assert!(satisfied(circuit));
+}
+
+#[test]
+fn test_ship_wraps_row() {
+    let circuit = board_circuit(vec![0,0,1,1,1,0,0,0,0], vec![3], vec![Ship { origin: 2, orientation: Orientation::Horizontal }]);
+    assert!(!satisfied(circuit));
+}
+
+#[test]
+fn test_ship_off_board() {
+    let circuit = board_circuit(vec![0,0,0,0,0,0,1,1,1], vec![3], vec![Ship { origin: 6, orientation: Orientation::Vertical }]);
+    assert!(!satisfied(circuit));
+}
+
+#[test]
+fn test_overlapping_ships() {
+    let circuit = board_circuit(vec![1,1,1,1,0,0,1,0,0], vec![3, 3], vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 0, orientation: Orientation::Vertical }]);
+    assert!(!satisfied(circuit));
+}
+
+#[test]
+fn test_tiles_hold_ship_numbers() {
+    // the board numbers its ships in fleet order
+    let ships = vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 5, orientation: Orientation::Vertical }];
+    assert!(!satisfied(board_circuit(vec![1,1,0,0,0,1,0,0,1], vec![2, 2], ships.clone())));
+    assert!(!satisfied(board_circuit(vec![2,2,0,0,0,1,0,0,1], vec![2, 2], ships)));
+
+    // two ships stacked on one tile add up to the number of a third, which still isn't a board
+    let ships = vec![
+        Ship { origin: 0, orientation: Orientation::Horizontal },
+        Ship { origin: 0, orientation: Orientation::Horizontal },
+        Ship { origin: 4, orientation: Orientation::Horizontal },
+    ];
+    assert!(!satisfied(board_circuit(vec![3,0,0,0,3,0,0,0,0], vec![1, 1, 1], ships)));
+}
+
+#[test]
+fn test_adjacency_rule() {
+    // ships side by side, ships meeting at a corner and ships apart
+    let fleet = vec![2, 2];
+    let side_by_side = (vec![1,1,0,2,2,0,0,0,0], vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 3, orientation: Orientation::Horizontal }]);
+    let corner = (vec![1,1,0,0,0,2,0,0,2], vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 5, orientation: Orientation::Vertical }]);
+    let apart = (vec![1,1,0,0,0,0,2,2,0], vec![Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 6, orientation: Orientation::Horizontal }]);
+
+    for (rule, allowed) in [
+        (AdjacencyRule::AnythingGoes, [true, true, true]),
+        (AdjacencyRule::NoTouch, [false, true, true]),
+        (AdjacencyRule::NoDiagonalTouch, [false, false, true]),
+    ] {
+        for ((board, ships), allowed) in [&side_by_side, &corner, &apart].into_iter().zip(allowed) {
+            let mut circuit = board_circuit(board.clone(), fleet.clone(), ships.clone());
+            circuit.rule = rule;
+            assert_eq!(satisfied(circuit), allowed, "{:?} {:?}", rule, board);
+            assert_eq!(rule_broken(board, BoardGeometry::square(3), rule).is_none(), allowed);
+        }
+    }
+
+    // a ship touching itself is fine under any rule
+    let mut circuit = board_circuit(vec![1,1,1,0,0,0,0,0,0], vec![3], vec![Ship { origin: 0, orientation: Orientation::Horizontal }]);
+    circuit.rule = AdjacencyRule::NoDiagonalTouch;
+    assert!(satisfied(circuit));
+}
+
+#[test]
+fn test_board_differs_from_ships() {
+    let circuit = board_circuit(vec![1,1,1,0,0,0,0,0,0], vec![3], vec![Ship { origin: 3, orientation: Orientation::Horizontal }]);
+    assert!(!satisfied(circuit));
+}
+
+#[test]
+fn test_rectangular_board() {
+    // 4 wide, 2 high: a vertical ship in the last column and a horizontal one along the bottom
+    let mut circuit = board_circuit(vec![0,0,0,1,2,2,2,1], vec![2, 3], vec![
+        Ship { origin: 3, orientation: Orientation::Vertical },
+        Ship { origin: 4, orientation: Orientation::Horizontal },
+    ]);
+    circuit.geometry = BoardGeometry::new(4, 2);
+    assert!(satisfied(circuit));
+}
+
+#[test]
+fn test_geometry_is_bound() {
+    // the same 6 tiles as a 3x2 board hold a horizontal ship of length 3, but not as a 2x3 board
+    let mut circuit = board_circuit(vec![1,1,1,0,0,0], vec![3], vec![Ship { origin: 0, orientation: Orientation::Horizontal }]);
+    circuit.geometry = BoardGeometry::new(3, 2);
+    assert!(satisfied(circuit.clone()));
+    circuit.geometry = BoardGeometry::new(2, 3);
+    assert!(!satisfied(circuit.clone()));
+
+    // and the board must have exactly width * height tiles
+    circuit.geometry = BoardGeometry::new(3, 3);
+    assert!(!satisfied(circuit));
+}
+
+#[test]
+fn test_large_board() {
+    let geometry = BoardGeometry::square(16);
+    let fleet = vec![5, 4, 3, 3, 2];
+    let ships = vec![
+        Ship { origin: 0, orientation: Orientation::Horizontal },
+        Ship { origin: 255, orientation: Orientation::Horizontal },
+        Ship { origin: 252, orientation: Orientation::Horizontal },
+        Ship { origin: 15, orientation: Orientation::Vertical },
+        Ship { origin: 100, orientation: Orientation::Vertical },
+    ];
+    assert!(fleet_board(&ships, &fleet, geometry).is_none());
+
+    let ships = vec![
+        Ship { origin: 0, orientation: Orientation::Horizontal },
+        Ship { origin: 252, orientation: Orientation::Horizontal },
+        Ship { origin: 240, orientation: Orientation::Horizontal },
+        Ship { origin: 15, orientation: Orientation::Vertical },
+        Ship { origin: 100, orientation: Orientation::Vertical },
+    ];
+    let board = fleet_board(&ships, &fleet, geometry).unwrap();
+    let mut circuit = board_circuit(board.clone(), fleet, ships);
+    circuit.geometry = geometry;
+    assert!(satisfied(circuit));
+
+    let (randomness, comm) = test_commitment(&board);
+    let shot = ShotVerifier {
+        geometry,
+        target: 255,
+        hit: 1,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
+        board: Some(board),
+        rng_in: Some(randomness),
+    };
+    assert!(satisfied(shot.clone()));
+    assert!(!satisfied(ShotVerifier { target: 256, ..shot }));
+}
+
+#[test]
+fn test_shot_hit() {
+    assert!(satisfied(shot_circuit(1, 1)));
+}
+
+#[test]
+fn test_shot_miss() {
+    assert!(satisfied(shot_circuit(4, 0)));
+}
+
+#[test]
+fn test_shot_lie_about_hit() {
+    assert!(!satisfied(shot_circuit(0, 0)));
+}
+
+#[test]
+fn test_shot_lie_about_miss() {
+    assert!(!satisfied(shot_circuit(4, 1)));
+}
+
+#[test]
+fn test_shot_off_board() {
+    assert!(!satisfied(shot_circuit(9, 0)));
+}
+
+#[test]
+fn test_salvo() {
+    let shot = shot_circuit(0, 1);
+    let salvo = |targets: &[u16], hits: &[bool]| {
+        let (targets, hits) = pad_salvo(targets, hits, 3).unwrap();
+        SalvoVerifier {
+            geometry: shot.geometry,
+            targets,
+            hits,
+            commitment: shot.commitment.clone(),
+            backend: shot.backend,
+            board: shot.board.clone(),
+            rng_in: shot.rng_in.clone(),
+        }
+    };
+
+    assert!(satisfied(salvo(&[0, 4, 2], &[true, false, true])));
+    // a short salvo is padded out with its last shot
+    assert!(satisfied(salvo(&[8, 1], &[false, true])));
+    assert!(satisfied(salvo(&[5], &[false])));
+
+    // one lie anywhere in the salvo fails it
+    assert!(!satisfied(salvo(&[0, 4, 2], &[true, true, true])));
+    assert!(!satisfied(salvo(&[0, 4, 2], &[true, false, false])));
+    assert!(!satisfied(salvo(&[0, 9], &[true, false])));
+
+    assert!(pad_salvo(&[], &[], 3).is_none());
+    assert!(pad_salvo(&[0, 1, 2, 3], &[true, true, true, false], 3).is_none());
+}
+
+#[test]
+fn test_shot_zk() {
+    use ark_groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use ark_bls12_381::{Bls12_381, Fr};
+    use ark_ff::{ToConstraintField};
+
+
+    let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
+
+    let mut rng = ark_std::test_rng();
+    let mut randomness = [0u8; 32];
+    rng.fill(&mut randomness);
+
+    let params = ();
+    let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
+
+    let circuit = ShotVerifier {
+        geometry: BoardGeometry::square(3),
+        target: 2,
+        hit: 1,
+        commitment: comm.clone(),
+        backend: CommitmentBackend::Blake2s,
+
+        rng_in: Some(randomness.to_vec()),
+        board: Some(board),
+    };
+
+
+    let params = generate_random_parameters::<Bls12_381, _, _>(circuit.clone(), &mut rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+
+    let proof = {
+        // Create a proof with our parameters.
+        create_random_proof(circuit, &params, &mut rng).unwrap()
+    };
+
+    let  mut inputs: Vec<_> = vec![Fr::from(3), Fr::from(3), Fr::from(2), Fr::from(1)];
+
+    let mut field_elements: Vec<Fr> = ToConstraintField::<Fr>::to_field_elements(&comm).unwrap();
+    inputs.append(&mut field_elements);
+
+    // Check the proof
+    let r = verify_proof(&pvk, &proof, &inputs).unwrap();
+    assert!(r);
+
+    // the same proof can't be passed off as a miss
+    inputs[3] = Fr::from(0);
+    let r = verify_proof(&pvk, &proof, &inputs).unwrap();
+    assert!(!r);
+}
+
+/// a sunk announcement over the 3x3 board [1,1,0,2,0,0,0,0,0] committed with blake2s
+#[cfg(test)]
+fn sunk_circuit(target: u16, ship: u8, hit_tiles: &[usize]) -> SunkVerifier {
+    let board = vec![1,1,0,2,0,0,0,0,0];
+    let (randomness, comm) = test_commitment(&board);
+    let mut hits = vec![false; 9];
+    for t in hit_tiles {
+        hits[*t] = true;
+    }
+    SunkVerifier {
+        geometry: BoardGeometry::square(3),
+        target,
+        ship,
+        hits,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
+        board: Some(board),
+        rng_in: Some(randomness),
+    }
+}
+
+#[test]
+fn test_sunk() {
+    assert!(satisfied(sunk_circuit(1, 0, &[0, 1])));
+    assert!(satisfied(sunk_circuit(3, 1, &[3, 8])));
+    // the other ship being afloat doesn't matter
+    assert!(satisfied(sunk_circuit(0, 0, &[0, 1, 4])));
+}
+
+#[test]
+fn test_sunk_lies() {
+    // a tile of the ship hasn't been hit
+    assert!(!satisfied(sunk_circuit(1, 0, &[1, 3])));
+    // the target isn't on the announced ship
+    assert!(!satisfied(sunk_circuit(3, 0, &[0, 1, 3])));
+    assert!(!satisfied(sunk_circuit(2, 0, &[0, 1, 2])));
+    // there is no such ship
+    assert!(!satisfied(sunk_circuit(1, 5, &[0, 1])));
+}
+
+#[test]
+fn test_pack_hits() {
+    let mut hits = vec![false; 300];
+    hits[0] = true;
+    hits[2] = true;
+    hits[HITS_PER_INPUT] = true;
+    assert_eq!(pack_hits::<ConstraintF>(&hits), vec![ConstraintF::from(5u8), ConstraintF::from(1u8)]);
+    assert_eq!(pack_hits::<ConstraintF>(&hits[..100]).len(), 1);
+}
+
+/// ships remaining on the 3x3 board [1,1,0,2,0,0,0,0,0] committed with blake2s
+#[cfg(test)]
+fn remaining_circuit(remaining: u8, hit_tiles: &[usize]) -> RemainingVerifier {
+    let board = vec![1,1,0,2,0,0,0,0,0];
+    let (randomness, comm) = test_commitment(&board);
+    let mut hits = vec![false; 9];
+    for t in hit_tiles {
+        hits[*t] = true;
+    }
+    RemainingVerifier {
+        geometry: BoardGeometry::square(3),
+        ships: 2,
+        hits,
+        remaining,
+        commitment: comm,
+        backend: CommitmentBackend::Blake2s,
+        board: Some(board),
+        rng_in: Some(randomness),
+    }
+}
+
+#[test]
+fn test_remaining() {
+    assert!(satisfied(remaining_circuit(2, &[])));
+    assert!(satisfied(remaining_circuit(2, &[0, 2, 4])));
+    assert!(satisfied(remaining_circuit(1, &[0, 1])));
+    assert!(satisfied(remaining_circuit(0, &[0, 1, 3])));
+    assert!(satisfied(remaining_circuit(0, &[0, 1, 3, 5, 8])));
+}
+
+#[test]
+fn test_remaining_lies() {
+    // claiming victory with a tile left, or ships left after they've all been hit
+    assert!(!satisfied(remaining_circuit(0, &[0, 1])));
+    assert!(!satisfied(remaining_circuit(0, &[0, 3, 4, 5, 6, 7, 8])));
+    assert!(!satisfied(remaining_circuit(1, &[0, 1, 3])));
+    assert!(!satisfied(remaining_circuit(1, &[])));
+}
+
+#[test]
+fn test_commitment_mismatch() {
+    // a valid board, but not the one that was committed to
+    let mut circuit = board_circuit(vec![1,1,1,0,0,0,0,0,0], vec![3], vec![Ship { origin: 3, orientation: Orientation::Horizontal }]);
+    circuit.board = Some(vec![0,0,0,1,1,1,0,0,0]);
+    assert!(!satisfied(circuit));
+}
+
+#[test]
+fn test_public_input_size_is_constant() {
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let mut num_inputs = vec![];
+    for side in [3u8, 10] {
+        let mut board: Vec<u8> = vec![0; side as usize * side as usize];
+        board[0] = 1;
+        let mut rng = ark_std::test_rng();
+        let mut randomness = [0u8; 32];
+        rng.fill(&mut randomness);
+
+        let params = ();
+        let comm = Commitment::commit(&params, &board, &randomness).unwrap().to_vec();
+
+        let circuit = ShotVerifier {
+            geometry: BoardGeometry::square(side),
+            target: 0,
+            hit: 1,
+            commitment: comm,
+            backend: CommitmentBackend::Blake2s,
+            board: Some(board),
+            rng_in: Some(randomness.to_vec()),
+        };
+
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        num_inputs.push(cs.num_instance_variables());
+    }
+
+    assert_eq!(num_inputs[0], num_inputs[1]);
+}
+
+#[test]
+fn test_zk_backends() {
+    use ark_groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use ark_bls12_381::{Bls12_381, Fr};
+
+    let board: Vec<u8> = vec![1,1,1,0,0,0,0,0,0];
+
+    let mut rng = ark_std::test_rng();
+    let mut secret = [0u8; 32];
+    rng.fill(&mut secret);
+    let randomness = derive_randomness(&secret).unwrap();
+
+    for backend in [CommitmentBackend::Pedersen, CommitmentBackend::Poseidon] {
+        let comm = backend.commit(Curve::Bls12_381, &board, &randomness).unwrap();
+
+        let circuit = BoardVerifier {
+            fleet: vec![3],
+            geometry: BoardGeometry::square(3),
+            rule: AdjacencyRule::AnythingGoes,
+            commitment: comm.clone(),
+            backend,
+
+            secret: Some(secret.to_vec()),
+            board: Some(board.clone()),
+            ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }]),
+        };
+
+        let params = generate_random_parameters::<Bls12_381, _, _>(circuit.clone(), &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let proof = create_random_proof(circuit, &params, &mut rng).unwrap();
+
+        let  mut inputs: Vec<Fr> = vec![Fr::from(3), Fr::from(3), Fr::from(3), Fr::from(AdjacencyRule::AnythingGoes.to_u8())];
+        inputs.append(&mut backend.to_inputs(&comm).unwrap());
+
+        // Check the proof
+        let r = verify_proof(&pvk, &proof, &inputs).unwrap();
+        assert!(r);
+
+        // and that it doesn't verify against a commitment to a different board
+        let other = backend.commit(Curve::Bls12_381, &[0,0,0,1,1,1,0,0,0], &randomness).unwrap();
+        let mut inputs: Vec<Fr> = vec![Fr::from(3), Fr::from(3), Fr::from(3), Fr::from(AdjacencyRule::AnythingGoes.to_u8())];
+        inputs.append(&mut backend.to_inputs(&other).unwrap());
+        let r = verify_proof(&pvk, &proof, &inputs).unwrap();
+        assert!(!r);
+    }
+}
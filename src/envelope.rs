@@ -0,0 +1,385 @@
+use crate::common::*;
+use crate::commitment::CircuitField;
+use crate::error::GameError;
+use crate::game::Game;
+use crate::proof_system::{Groth16, ProofSystem};
+use ark_bls12_381::Bls12_381;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use blake2::{Blake2s, Digest};
+use std::io::{Read, Write};
+
+/**
+*   a self-describing wrapper for the proofs, verifying keys and commitments of a game, so
+*   they can be written to disk, handed to another program or pasted into a chat and still
+*   be told apart and checked against the game they belong to.
+*
+*   an envelope is:
+*
+*   ```text
+*   4 bytes   ENVELOPE_MAGIC, "ZKBE"
+*   u8        ENVELOPE_VERSION
+*   u8        curve id, see Curve::to_u8
+*   u8        proof system id, see System::to_u8
+*   u8        circuit id, see Circuit::to_u8
+*   u8, u8    board width and height
+*   32 bytes  blake2s hash of the fleet, see fleet_hash
+*   u8        payload kind, see Payload::kind
+*   ...       the payload, ark-serialize compressed encoding
+*   ```
+*
+*   an envelope with different magic bytes, another version, an unknown id or trailing bytes
+*   is rejected, as is one for another curve or proof system than the one it is read as. to_hex and to_base64 give the same bytes as text.
+*/
+pub const ENVELOPE_MAGIC: &[u8; 4] = b"ZKBE";
+pub const ENVELOPE_VERSION: u8 = 2;
+
+/// the circuits a game proves with, one set of keys each
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Circuit {
+    Board,
+    Shot,
+    Sunk,
+    Remaining,
+    Salvo,
+}
+
+impl Circuit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Circuit::Board => "board",
+            Circuit::Shot => "shot",
+            Circuit::Sunk => "sunk",
+            Circuit::Remaining => "remaining",
+            Circuit::Salvo => "salvo",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Circuit> {
+        match name {
+            "board" => Some(Circuit::Board),
+            "shot" => Some(Circuit::Shot),
+            "sunk" => Some(Circuit::Sunk),
+            "remaining" => Some(Circuit::Remaining),
+            "salvo" => Some(Circuit::Salvo),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Circuit::Board => 0,
+            Circuit::Shot => 1,
+            Circuit::Sunk => 2,
+            Circuit::Remaining => 3,
+            Circuit::Salvo => 4,
+        }
+    }
+
+    pub fn from_u8(id: u8) -> Option<Circuit> {
+        match id {
+            0 => Some(Circuit::Board),
+            1 => Some(Circuit::Shot),
+            2 => Some(Circuit::Sunk),
+            3 => Some(Circuit::Remaining),
+            4 => Some(Circuit::Salvo),
+            _ => None,
+        }
+    }
+}
+
+/// what an envelope carries. a commitment is filed under the board circuit
+// envelopes are short lived, so the larger payloads aren't boxed
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Payload<P: ProofSystem = Groth16<Bls12_381>> {
+    Proof(P::Proof),
+    VerifyingKey(P::VerifyingKey),
+    Commitment(Vec<u8>),
+}
+
+impl<P: ProofSystem> Payload<P> {
+    fn kind(&self) -> u8 {
+        match self {
+            Payload::Proof(_) => 0,
+            Payload::VerifyingKey(_) => 1,
+            Payload::Commitment(_) => 2,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Payload::Proof(_) => "a proof",
+            Payload::VerifyingKey(_) => "a verifying key",
+            Payload::Commitment(_) => "a commitment",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope<P: ProofSystem = Groth16<Bls12_381>> {
+    pub curve: Curve,
+    pub system: System,
+    pub circuit: Circuit,
+    pub geometry: BoardGeometry,
+    pub fleet_hash: [u8; 32],
+    pub payload: Payload<P>,
+}
+
+/// blake2s hash of the fleet's ship lengths in order
+pub fn fleet_hash(fleet: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Blake2s::digest(fleet));
+    hash
+}
+
+impl<P: GameSystem> Envelope<P> {
+    /// wraps the payload for the given circuit of the game
+    pub fn new(game: &Game, circuit: Circuit, payload: Payload<P>) -> Envelope<P> {
+        Envelope {
+            curve: P::Field::CURVE,
+            system: P::SYSTEM,
+            circuit,
+            geometry: game.geometry(),
+            fleet_hash: fleet_hash(game.fleet()),
+            payload,
+        }
+    }
+
+    /**
+    *   checks the envelope was made for this circuit of this game, on the curve and with the
+    *   proof system the game is proven with
+    */
+    pub fn check(&self, game: &Game, circuit: Circuit) -> Result<(), GameError> {
+        if self.curve != game.curve() {
+            return Err(GameError::BadInput(format!("the envelope is for the {} curve, not {}", self.curve.name(), game.curve().name())));
+        }
+        if self.system != game.system() {
+            return Err(GameError::BadInput(format!("the envelope is for {} proofs, not {}", self.system.name(), game.system().name())));
+        }
+        if self.circuit != circuit {
+            return Err(GameError::BadInput(format!("the envelope is for the {} circuit, not the {} circuit", self.circuit.name(), circuit.name())));
+        }
+        if self.geometry != game.geometry() {
+            return Err(GameError::BadInput(format!("the envelope is for a {} board, not {}", self.geometry, game.geometry())));
+        }
+        if self.fleet_hash != fleet_hash(game.fleet()) {
+            return Err(GameError::BadInput("the envelope is for another fleet".to_string()));
+        }
+        Ok(())
+    }
+
+    /// the proof inside, once the envelope is checked against the game and circuit
+    pub fn into_proof(self, game: &Game, circuit: Circuit) -> Result<P::Proof, GameError> {
+        self.check(game, circuit)?;
+        match self.payload {
+            Payload::Proof(proof) => Ok(proof),
+            other => Err(GameError::BadInput(format!("the envelope holds {}, not a proof", other.name()))),
+        }
+    }
+
+    /// the verifying key inside, once the envelope is checked against the game and circuit
+    pub fn into_verifying_key(self, game: &Game, circuit: Circuit) -> Result<P::VerifyingKey, GameError> {
+        self.check(game, circuit)?;
+        match self.payload {
+            Payload::VerifyingKey(vk) => Ok(vk),
+            other => Err(GameError::BadInput(format!("the envelope holds {}, not a verifying key", other.name()))),
+        }
+    }
+
+    /// the board commitment inside, once the envelope is checked against the game
+    pub fn into_commitment(self, game: &Game) -> Result<Vec<u8>, GameError> {
+        self.check(game, Circuit::Board)?;
+        match self.payload {
+            Payload::Commitment(commitment) if game.backend().is_well_formed(game.curve(), &commitment) => Ok(commitment),
+            Payload::Commitment(_) => Err(GameError::CommitmentMismatch),
+            other => Err(GameError::BadInput(format!("the envelope holds {}, not a commitment", other.name()))),
+        }
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), GameError> {
+        writer.write_all(ENVELOPE_MAGIC)?;
+        ENVELOPE_VERSION.serialize(&mut writer)?;
+        self.curve.to_u8().serialize(&mut writer)?;
+        self.system.to_u8().serialize(&mut writer)?;
+        self.circuit.to_u8().serialize(&mut writer)?;
+        self.geometry.width.serialize(&mut writer)?;
+        self.geometry.height.serialize(&mut writer)?;
+        writer.write_all(&self.fleet_hash)?;
+        self.payload.kind().serialize(&mut writer)?;
+        match &self.payload {
+            Payload::Proof(proof) => proof.serialize(&mut writer)?,
+            Payload::VerifyingKey(vk) => vk.serialize(&mut writer)?,
+            Payload::Commitment(commitment) => commitment.serialize(&mut writer)?,
+        }
+        Ok(writer.flush()?)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Envelope<P>, GameError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != ENVELOPE_MAGIC {
+            return Err(GameError::BadInput("not a zk-battleships envelope".to_string()));
+        }
+        let version = u8::deserialize(&mut reader)?;
+        if version != ENVELOPE_VERSION {
+            return Err(GameError::BadInput(format!("unsupported envelope version {}", version)));
+        }
+
+        let curve = Curve::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        if curve != P::Field::CURVE {
+            return Err(GameError::BadInput(format!("the envelope is for the {} curve, not {}", curve.name(), P::Field::CURVE.name())));
+        }
+        let system = System::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        if system != P::SYSTEM {
+            return Err(GameError::BadInput(format!("the envelope is for {} proofs, not {}", system.name(), P::SYSTEM.name())));
+        }
+        let circuit = Circuit::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        let geometry = BoardGeometry::new(u8::deserialize(&mut reader)?, u8::deserialize(&mut reader)?);
+        let mut fleet_hash = [0u8; 32];
+        reader.read_exact(&mut fleet_hash)?;
+        let payload = match u8::deserialize(&mut reader)? {
+            0 => Payload::Proof(P::Proof::deserialize(&mut reader)?),
+            1 => Payload::VerifyingKey(P::VerifyingKey::deserialize(&mut reader)?),
+            2 => Payload::Commitment(Vec::<u8>::deserialize(&mut reader)?),
+            _ => return Err(SerializationError::InvalidData.into()),
+        };
+
+        Ok(Envelope { curve, system, circuit, geometry, fleet_hash, payload })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GameError> {
+        let mut bytes = vec![];
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// reads an envelope that must take up all of the bytes
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Envelope<P>, GameError> {
+        let envelope = Envelope::read(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(GameError::BadInput("trailing bytes after the envelope".to_string()));
+        }
+        Ok(envelope)
+    }
+
+    /// lower case hex of the envelope's bytes
+    pub fn to_hex(&self) -> Result<String, GameError> {
+        Ok(hex::encode(self.to_bytes()?))
+    }
+
+    /// reads hex from to_hex, ignoring whitespace around it
+    pub fn from_hex(text: &str) -> Result<Envelope<P>, GameError> {
+        let bytes = hex::decode(text.trim()).map_err(|e| GameError::BadInput(format!("the envelope isn't valid hex: {}", e)))?;
+        Envelope::from_bytes(&bytes)
+    }
+
+    /// standard padded base64 of the envelope's bytes, shorter than hex to paste into a chat
+    pub fn to_base64(&self) -> Result<String, GameError> {
+        Ok(base64::encode(self.to_bytes()?))
+    }
+
+    /// reads base64 from to_base64, ignoring whitespace around it
+    pub fn from_base64(text: &str) -> Result<Envelope<P>, GameError> {
+        let bytes = base64::decode(text.trim()).map_err(|e| GameError::BadInput(format!("the envelope isn't valid base64: {}", e)))?;
+        Envelope::from_bytes(&bytes)
+    }
+
+    /**
+    *   reads an envelope pasted as either hex or base64. every envelope starts with the
+    *   magic bytes, whose base64 has letters hex doesn't, so the two can't be confused
+    */
+    pub fn from_text(text: &str) -> Result<Envelope<P>, GameError> {
+        let text = text.trim();
+        if !text.is_empty() && text.chars().all(|c| c.is_ascii_hexdigit()) {
+            Envelope::from_hex(text)
+        } else {
+            Envelope::from_base64(text)
+        }
+    }
+}
+
+#[test]
+fn test_envelope_round_trip() {
+    use rand::rngs::OsRng;
+
+    let (game, keys) = crate::game::test_game();
+    let mut player = game.new_player();
+    player.place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }]).unwrap();
+    let commitment = player.commit(&mut OsRng).unwrap();
+    let proof = player.prove_board(&keys, &mut OsRng).unwrap();
+
+    let envelopes: [Envelope; 3] = [
+        Envelope::new(&game, Circuit::Board, Payload::Proof(proof.clone())),
+        Envelope::new(&game, Circuit::Shot, Payload::VerifyingKey(keys.shot_vk.vk.clone())),
+        Envelope::new(&game, Circuit::Board, Payload::Commitment(commitment.clone())),
+    ];
+    for envelope in &envelopes {
+        let bytes = envelope.to_bytes().unwrap();
+        assert_eq!(&bytes[..4], ENVELOPE_MAGIC);
+        assert_eq!(bytes[4], ENVELOPE_VERSION);
+        assert_eq!(&Envelope::from_bytes(&bytes).unwrap(), envelope);
+        assert_eq!(&Envelope::from_hex(&envelope.to_hex().unwrap()).unwrap(), envelope);
+        assert_eq!(&Envelope::from_base64(&format!("  {}\n", envelope.to_base64().unwrap())).unwrap(), envelope);
+        assert_eq!(&Envelope::from_text(&envelope.to_hex().unwrap()).unwrap(), envelope);
+        assert_eq!(&Envelope::from_text(&envelope.to_base64().unwrap()).unwrap(), envelope);
+    }
+
+    // the proof that comes out still verifies
+    let [proof_envelope, vk_envelope, commitment_envelope] = envelopes;
+    let opened = <Envelope>::from_base64(&proof_envelope.to_base64().unwrap()).unwrap().into_proof(&game, Circuit::Board).unwrap();
+    let mut verifier = game.new_player();
+    verifier.receive_commitment(commitment_envelope.into_commitment(&game).unwrap()).unwrap();
+    verifier.verify_board(&keys, opened).unwrap();
+
+    // and the envelope only opens for what it was made for
+    assert!(vk_envelope.clone().into_proof(&game, Circuit::Shot).is_err());
+    assert!(vk_envelope.clone().into_verifying_key(&game, Circuit::Sunk).is_err());
+    assert_eq!(&vk_envelope.clone().into_verifying_key(&game, Circuit::Shot).unwrap(), keys.verifying_key(Circuit::Shot).unwrap());
+    assert!(keys.verifying_key(Circuit::Salvo).is_err());
+    let other = Game::new(BoardGeometry::square(3), vec![1, 2], game.backend()).unwrap();
+    assert!(proof_envelope.clone().into_proof(&other, Circuit::Board).is_err());
+    let other = Game::new(BoardGeometry::new(3, 4), vec![2, 1], game.backend()).unwrap();
+    assert!(proof_envelope.into_proof(&other, Circuit::Board).is_err());
+}
+
+#[test]
+fn test_envelope_rejects_bad_input() {
+    type Envelope = self::Envelope<Groth16<Bls12_381>>;
+
+    let (game, _) = crate::game::test_game();
+    let bytes = Envelope::new(&game, Circuit::Board, Payload::Commitment(vec![7u8; 32])).to_bytes().unwrap();
+
+    let mut magic = bytes.clone();
+    magic[0] = b'X';
+    assert!(Envelope::from_bytes(&magic).is_err());
+    let mut version = bytes.clone();
+    version[4] = ENVELOPE_VERSION + 1;
+    assert!(Envelope::from_bytes(&version).is_err());
+    let mut curve = bytes.clone();
+    curve[5] = 200;
+    assert!(Envelope::from_bytes(&curve).is_err());
+    // a commitment carries no curve points, but it is still only read on its own curve
+    assert!(self::Envelope::<Groth16<ark_bls12_377::Bls12_377>>::from_bytes(&bytes).is_err());
+    // and with its own proof system
+    let mut system = bytes.clone();
+    system[6] = 200;
+    assert!(Envelope::from_bytes(&system).is_err());
+    assert!(self::Envelope::<crate::plonk::Plonk<Bls12_381>>::from_bytes(&bytes).is_err());
+    let mut circuit = bytes.clone();
+    circuit[7] = 200;
+    assert!(Envelope::from_bytes(&circuit).is_err());
+    let mut kind = bytes.clone();
+    kind[42] = 200;
+    assert!(Envelope::from_bytes(&kind).is_err());
+
+    assert!(Envelope::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(Envelope::from_bytes(&trailing).is_err());
+
+    assert!(Envelope::from_hex("zz").is_err());
+    assert!(Envelope::from_base64("not base64!").is_err());
+}
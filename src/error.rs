@@ -0,0 +1,77 @@
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::SerializationError;
+use std::fmt;
+
+/**
+*   everything that can go wrong in a game. the engine returns these rather than
+*   panicking or exiting, so a front end decides what to do with a cheating opponent
+*   or a typo.
+*/
+#[derive(Debug)]
+pub enum GameError {
+    /// game settings, ship placements or commands that don't make sense
+    BadInput(String),
+    /// a shot at a tile that isn't on the board
+    OutOfBounds { target: u16, board_size: usize },
+    /// a shot at a tile that has already been fired at
+    RepeatedShot(u16),
+    /// a commitment that the game's commitment scheme couldn't have produced. tiles are
+    /// never opened during play (shots are answered with proofs over the commitment), so
+    /// this is the only way a commitment can fail to match before a proof is checked against it
+    CommitmentMismatch,
+    /// an opponent's proof that didn't verify, i.e. the opponent cheating
+    ProofFailure(String),
+    /// the board an opponent revealed once the game was over doesn't open their commitment
+    /// or contradicts one of their answers, i.e. the opponent cheating
+    AuditFailure(String),
+    /// a proof we couldn't create, or a verifying key that doesn't fit the proof's public
+    /// inputs. these are local problems and never the opponent's fault
+    Proving(String),
+    /// the opponent's verifying keys aren't the ones loaded locally
+    KeyMismatch,
+    /// keys or messages that couldn't be read or written
+    Serialization(SerializationError),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::BadInput(e) => write!(f, "{}", e),
+            GameError::OutOfBounds { target, board_size } => write!(f, "tile {} is not on the board of {} tiles", target, board_size),
+            GameError::RepeatedShot(target) => write!(f, "tile {} has already been attacked", target),
+            GameError::CommitmentMismatch => write!(f, "the commitment doesn't match the game's commitment scheme"),
+            GameError::ProofFailure(e) => write!(f, "{}", e),
+            GameError::AuditFailure(e) => write!(f, "{}", e),
+            GameError::Proving(e) => write!(f, "proving failed: {}", e),
+            GameError::KeyMismatch => write!(f, "the opponent's keys don't match the local keys, both players need the key files from the same setup"),
+            GameError::Serialization(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for GameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameError::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SerializationError> for GameError {
+    fn from(e: SerializationError) -> GameError {
+        GameError::Serialization(e)
+    }
+}
+
+impl From<std::io::Error> for GameError {
+    fn from(e: std::io::Error) -> GameError {
+        GameError::Serialization(SerializationError::IoError(e))
+    }
+}
+
+impl From<SynthesisError> for GameError {
+    fn from(e: SynthesisError) -> GameError {
+        GameError::Proving(e.to_string())
+    }
+}
@@ -0,0 +1,1432 @@
+use crate::common::*;
+use crate::commitment::{derive_randomness, CircuitField, CommitmentBackend, SECRET_LEN};
+use crate::constraints::{pack_hits, pad_salvo, BoardVerifier, RemainingVerifier, SalvoVerifier, ShotVerifier, SunkVerifier};
+use crate::error::GameError;
+use crate::keys::GameKeys;
+use ark_bls12_381::Bls12_381;
+use crate::proof_system::{Groth16, ProofSystem};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use std::io::{Read, Write};
+use rand::{CryptoRng, Rng, RngCore};
+#[cfg(test)]
+use rand::rngs::OsRng;
+
+/**
+*   the public settings of a game, agreed by both players before ships are placed
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    geometry: BoardGeometry,
+    fleet: Vec<u8>,
+    backend: CommitmentBackend,
+    rule: AdjacencyRule,
+    salvo: Salvo,
+    curve: Curve,
+    system: System,
+}
+
+impl Game {
+    /**
+    *   checks the board isn't empty, every ship in the fleet fits on it, the fleet
+    *   doesn't cover more tiles than the board has and the commitment scheme can take
+    *   a board this big
+    */
+    pub fn new(geometry: BoardGeometry, fleet: Vec<u8>, backend: CommitmentBackend) -> Result<Game, GameError> {
+        if geometry.width == 0 || geometry.height == 0 {
+            return Err(GameError::BadInput(format!("a {} board has no tiles", geometry)));
+        }
+        if geometry.tiles() > backend.max_board_tiles() {
+            return Err(GameError::BadInput(format!("{:?} commitments only fit boards of up to {} tiles", backend, backend.max_board_tiles())));
+        }
+        if fleet.is_empty() {
+            return Err(GameError::BadInput("the fleet has no ships".to_string()));
+        }
+        // each tile holds the number of the ship on it in one byte
+        if fleet.len() > u8::MAX as usize {
+            return Err(GameError::BadInput(format!("the fleet can have at most {} ships", u8::MAX)));
+        }
+        if let Some(length) = fleet.iter().find(|length| legal_placements(**length, geometry).is_empty()) {
+            return Err(GameError::BadInput(format!("a ship of length {} doesn't fit on the board", length)));
+        }
+
+        let game = Game { geometry, fleet, backend, rule: AdjacencyRule::AnythingGoes, salvo: Salvo::Off, curve: Curve::Bls12_381, system: System::Groth16 };
+        if game.fleet_tiles() > geometry.tiles() {
+            return Err(GameError::BadInput(format!("the fleet covers {} tiles, more than the board's {}", game.fleet_tiles(), geometry.tiles())));
+        }
+        Ok(game)
+    }
+
+    pub fn geometry(&self) -> BoardGeometry {
+        self.geometry
+    }
+
+    /// length of each ship, in the order they're placed
+    pub fn fleet(&self) -> &[u8] {
+        &self.fleet
+    }
+
+    pub fn backend(&self) -> CommitmentBackend {
+        self.backend
+    }
+
+    /// how close ships may be placed, anything goes unless set with with_rule
+    pub fn rule(&self) -> AdjacencyRule {
+        self.rule
+    }
+
+    /// the same game played under a house rule on ships touching
+    pub fn with_rule(mut self, rule: AdjacencyRule) -> Game {
+        self.rule = rule;
+        self
+    }
+
+    /// how many shots a turn has, one unless set with with_salvo
+    pub fn salvo(&self) -> Salvo {
+        self.salvo
+    }
+
+    /// the same game played in salvos. a fixed salvo has between 2 and 254 shots
+    pub fn with_salvo(mut self, salvo: Salvo) -> Result<Game, GameError> {
+        if let Salvo::Fixed(0 | 1 | u8::MAX) = salvo {
+            return Err(GameError::BadInput(format!("a salvo can't have {} shots", salvo.to_u8())));
+        }
+        self.salvo = salvo;
+        Ok(self)
+    }
+
+    /// the curve the game is proven on, bls12-381 unless set with with_curve
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    /// the same game proven on another curve, with keys made for it. pedersen commitments
+    /// can only be proven on bls12-381
+    pub fn with_curve(mut self, curve: Curve) -> Result<Game, GameError> {
+        if !self.backend.supports(curve) {
+            return Err(GameError::BadInput(format!("{:?} commitments can't be proven on {}", self.backend, curve.name())));
+        }
+        self.curve = curve;
+        Ok(self)
+    }
+
+    /// the proof system the game is proven with, groth16 unless set with with_system
+    pub fn system(&self) -> System {
+        self.system
+    }
+
+    /// the same game proven with another proof system, with keys made for it
+    pub fn with_system(mut self, system: System) -> Game {
+        self.system = system;
+        self
+    }
+
+    /// the most shots any salvo of this game has
+    pub fn max_salvo(&self) -> usize {
+        self.salvo.max_shots(self.fleet.len())
+    }
+
+    /// total number of battleship tiles on each board
+    pub fn fleet_tiles(&self) -> usize {
+        self.fleet.iter().map(|length| *length as usize).sum()
+    }
+
+    /// a new player with an empty board
+    pub fn new_player(&self) -> PlayerState {
+        PlayerState {
+            game: self.clone(),
+            ships: Vec::new(),
+            board: vec![0; self.geometry.tiles()],
+            secret: None,
+            commitment: None,
+            opponent_commitment: None,
+            opponent_verified: false,
+            sunk: Vec::new(),
+            proven_remaining: None,
+            view: vec![0; self.geometry.tiles()],
+            opponent_view: vec![0; self.geometry.tiles()],
+        }
+    }
+}
+
+/**
+*   the ships and randomness behind a player's commitment, revealed once the game is over
+*   so the opponent can audit the whole board
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reveal {
+    pub ships: Vec<Ship>,
+    pub randomness: Vec<u8>,
+}
+
+impl CanonicalSerialize for Reveal {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.ships.serialize(&mut writer)?;
+        self.randomness.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.ships.serialized_size() + self.randomness.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for Reveal {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Reveal, SerializationError> {
+        Ok(Reveal {
+            ships: Vec::<Ship>::deserialize(&mut reader)?,
+            randomness: Vec::<u8>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+/**
+*   the defender's proof that a hit sank a ship, naming the ship by its index in the fleet
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sunk<P: ProofSystem = Groth16<Bls12_381>> {
+    pub ship: u8,
+    pub proof: P::Proof,
+}
+
+impl<P: ProofSystem> CanonicalSerialize for Sunk<P> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.ship.serialize(&mut writer)?;
+        self.proof.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.ship.serialized_size() + self.proof.serialized_size()
+    }
+}
+
+impl<P: ProofSystem> CanonicalDeserialize for Sunk<P> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Sunk<P>, SerializationError> {
+        Ok(Sunk {
+            ship: u8::deserialize(&mut reader)?,
+            proof: P::Proof::deserialize(&mut reader)?,
+        })
+    }
+}
+
+/**
+*   one player's side of a game: their own board and the secrets behind its commitment,
+*   the opponent's commitment and what each player knows of the other's board.
+*
+*   the board holds the number of the ship on each tile (its index in the fleet + 1),
+*   0 for empty tiles. views: 0 = not shot at, 1 = miss, 2 = hit
+*/
+pub struct PlayerState {
+    game: Game,
+    ships: Vec<Ship>,
+    board: Vec<u8>,
+    // the commitment randomness is derived from this, see commitment::derive_randomness
+    secret: Option<Vec<u8>>,
+    commitment: Option<Vec<u8>>,
+    opponent_commitment: Option<Vec<u8>>,
+    // set once the opponent's board proof has verified. until then their answers
+    // can't be trusted to add up to a sunk fleet
+    opponent_verified: bool,
+    // fleet indices of the opponent's ships this player has been proven to have sunk
+    sunk: Vec<u8>,
+    // how many of the opponent's ships their last remaining ships proof showed afloat
+    proven_remaining: Option<u8>,
+    view: Vec<u8>,
+    opponent_view: Vec<u8>,
+}
+
+impl PlayerState {
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// own board, the number of the ship on each tile or 0 if it's empty
+    pub fn board(&self) -> &[u8] {
+        &self.board
+    }
+
+    pub fn ships(&self) -> &[Ship] {
+        &self.ships
+    }
+
+    /// this player's view of the opponent's board
+    pub fn view(&self) -> &[u8] {
+        &self.view
+    }
+
+    /// the opponent's view of this player's board
+    pub fn opponent_view(&self) -> &[u8] {
+        &self.opponent_view
+    }
+
+    pub fn commitment(&self) -> Option<&[u8]> {
+        self.commitment.as_deref()
+    }
+
+    /// length of the next ship of the fleet to place, None once the whole fleet is placed
+    pub fn next_ship_length(&self) -> Option<u8> {
+        self.game.fleet.get(self.ships.len()).copied()
+    }
+
+    /**
+    *   places the next ship of the fleet. it must be straight and on the board, not
+    *   overlap a ship already placed and not touch one where the game's rule forbids it
+    */
+    pub fn place_ship(&mut self, ship: Ship) -> Result<(), GameError> {
+        if self.commitment.is_some() {
+            return Err(GameError::BadInput("the board has already been committed to".to_string()));
+        }
+        let length = self.next_ship_length()
+            .ok_or_else(|| GameError::BadInput("the whole fleet has already been placed".to_string()))?;
+
+        let tiles = ship_tiles(&ship, length, self.game.geometry)
+            .ok_or_else(|| GameError::BadInput("that ship doesn't fit on the board".to_string()))?;
+        if tiles.iter().any(|t| self.board[*t] != 0) {
+            return Err(GameError::BadInput("that ship overlaps another ship".to_string()));
+        }
+
+        let number = self.ships.len() as u8 + 1;
+        let mut board = self.board.clone();
+        for t in tiles {
+            board[t] = number;
+        }
+        if rule_broken(&board, self.game.geometry, self.game.rule).is_some() {
+            return Err(GameError::BadInput(format!("that ship touches another ship, which the {} rule forbids", self.game.rule.name())));
+        }
+        self.board = board;
+        self.ships.push(ship);
+        Ok(())
+    }
+
+    /**
+    *   places the whole fleet at once, in fleet order
+    */
+    pub fn place_fleet(&mut self, ships: &[Ship]) -> Result<(), GameError> {
+        if ships.len() != self.game.fleet.len() - self.ships.len() {
+            return Err(GameError::BadInput(format!("expected {} ships, got {}", self.game.fleet.len() - self.ships.len(), ships.len())));
+        }
+        for ship in ships {
+            self.place_ship(*ship)?;
+        }
+        Ok(())
+    }
+
+    /**
+    *   commits to the placed fleet with randomness derived from a fresh secret from rng,
+    *   returning the commitment to send to the opponent
+    */
+    pub fn commit<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> Result<Vec<u8>, GameError> {
+        if self.next_ship_length().is_some() {
+            return Err(GameError::BadInput("the whole fleet must be placed before committing".to_string()));
+        }
+        if self.commitment.is_some() {
+            return Err(GameError::BadInput("the board has already been committed to".to_string()));
+        }
+
+        let secret = generate_secret(rng);
+        let commitment = generate_commitment(self.game.curve, self.game.backend, &self.board, &derive_randomness(&secret)?)?;
+        self.secret = Some(secret);
+        self.commitment = Some(commitment.clone());
+        Ok(commitment)
+    }
+
+    /**
+    *   records the opponent's board commitment, which every later proof is checked against.
+    *   it must be a commitment the game's scheme could have produced
+    */
+    pub fn receive_commitment(&mut self, commitment: Vec<u8>) -> Result<(), GameError> {
+        if !self.game.backend.is_well_formed(self.game.curve, &commitment) {
+            return Err(GameError::CommitmentMismatch);
+        }
+        self.opponent_commitment = Some(commitment);
+        Ok(())
+    }
+
+    fn secrets(&self) -> Result<(&[u8], &[u8]), GameError> {
+        match (&self.secret, &self.commitment) {
+            (Some(secret), Some(commitment)) => Ok((secret, commitment)),
+            _ => Err(GameError::BadInput("the board hasn't been committed to yet".to_string())),
+        }
+    }
+
+    fn opponent_commitment(&self) -> Result<&[u8], GameError> {
+        self.opponent_commitment.as_deref()
+            .ok_or_else(|| GameError::BadInput("no commitment received from the opponent".to_string()))
+    }
+
+    /// keys for another curve or proof system than the game's make proofs that could never verify
+    fn check_system<P: GameSystem>(&self) -> Result<(), GameError> {
+        if P::Field::CURVE != self.game.curve {
+            Err(GameError::BadInput(format!("the game is proven on {}, not {}", self.game.curve.name(), P::Field::CURVE.name())))
+        } else if P::SYSTEM != self.game.system {
+            Err(GameError::BadInput(format!("the game is proven with {}, not {}", self.game.system.name(), P::SYSTEM.name())))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_opponent_verified(&self) -> Result<(), GameError> {
+        if self.opponent_verified {
+            Ok(())
+        } else {
+            Err(GameError::BadInput("the opponent's board proof hasn't been verified yet".to_string()))
+        }
+    }
+
+    /**
+    *   proves the committed board holds the fleet
+    */
+    pub fn prove_board<P: GameSystem, R: RngCore + CryptoRng>(&self, keys: &GameKeys<P>, rng: &mut R) -> Result<P::Proof, GameError> {
+        self.check_system::<P>()?;
+        let (secret, commitment) = self.secrets()?;
+        generate_proof::<P, _>(self.game.backend, &keys.board_pk, &self.board, &self.ships, secret, commitment, &self.game.fleet, self.game.geometry, self.game.rule, rng)
+    }
+
+    /**
+    *   checks the opponent's proof that their committed board holds the fleet
+    */
+    pub fn verify_board<P: GameSystem>(&mut self, keys: &GameKeys<P>, proof: P::Proof) -> Result<(), GameError> {
+        self.check_system::<P>()?;
+        let commitment = self.opponent_commitment()?;
+        if verify_initial_proof::<P>(self.game.backend, &keys.board_vk, commitment, &self.game.fleet, self.game.geometry, self.game.rule, proof)? {
+            self.opponent_verified = true;
+            Ok(())
+        } else {
+            Err(GameError::ProofFailure("the opponent's board proof is invalid".to_string()))
+        }
+    }
+
+    /**
+    *   checks the target is on the board and hasn't been fired at before
+    */
+    pub fn fire(&self, target: u16) -> Result<u16, GameError> {
+        check_target(&self.view, target)
+    }
+
+    /**
+    *   answers the opponent's shot with a proof over the committed board, returning
+    *   whether it hit
+    */
+    pub fn answer<P: GameSystem, R: RngCore + CryptoRng>(&mut self, keys: &GameKeys<P>, target: u16, rng: &mut R) -> Result<(bool, P::Proof), GameError> {
+        self.check_system::<P>()?;
+        let (secret, commitment) = self.secrets()?;
+        let randomness = derive_randomness(secret)?;
+        check_target(&self.opponent_view, target)?;
+
+        let (hit, proof) = answer_shot::<P, _>(self.game.backend, &keys.shot_pk, self.game.geometry, &self.board, &randomness, commitment, target, rng)?;
+        self.opponent_view[target as usize] = if hit { 2 } else { 1 };
+        Ok((hit, proof))
+    }
+
+    /**
+    *   checks the opponent's answer to our shot against their commitment and
+    *   records the hit or miss
+    */
+    pub fn verify_answer<P: GameSystem>(&mut self, keys: &GameKeys<P>, target: u16, hit: bool, proof: P::Proof) -> Result<(), GameError> {
+        self.check_system::<P>()?;
+        self.check_opponent_verified()?;
+        self.fire(target)?;
+        let commitment = self.opponent_commitment()?;
+        if !verify_shot::<P>(self.game.backend, &keys.shot_vk, commitment, self.game.geometry, target, hit, proof)? {
+            return Err(GameError::ProofFailure("the opponent's shot proof is invalid".to_string()));
+        }
+
+        self.view[target as usize] = if hit { 2 } else { 1 };
+        Ok(())
+    }
+
+    /// how many of this player's ships the opponent hasn't sunk yet
+    pub fn ships_afloat(&self) -> usize {
+        (1..=self.game.fleet.len() as u8)
+            .filter(|number| self.board.iter().zip(&self.opponent_view).any(|(tile, seen)| tile == number && *seen != 2))
+            .count()
+    }
+
+    /// how many shots this player fires in their next turn
+    pub fn salvo_size(&self) -> usize {
+        self.game.salvo.shots(self.ships_afloat(), unattacked(&self.view))
+    }
+
+    /// how many shots the opponent fires in their next turn. their ships afloat are counted
+    /// from the sinkings they've announced, and a sinking they keep quiet is caught by the audit
+    pub fn opponent_salvo_size(&self) -> usize {
+        self.game.salvo.shots(self.game.fleet.len() - self.sunk.len(), unattacked(&self.opponent_view))
+    }
+
+    /**
+    *   checks the targets are a whole salvo for this player: salvo_size distinct tiles on the
+    *   board that haven't been fired at before
+    */
+    pub fn fire_salvo(&self, targets: &[u16]) -> Result<(), GameError> {
+        check_salvo(&self.view, targets, self.salvo_size())
+    }
+
+    /**
+    *   answers the opponent's salvo with one proof over the committed board, returning
+    *   whether each shot hit
+    */
+    pub fn answer_salvo<P: GameSystem, R: RngCore + CryptoRng>(&mut self, keys: &GameKeys<P>, targets: &[u16], rng: &mut R) -> Result<(Vec<bool>, P::Proof), GameError> {
+        self.check_system::<P>()?;
+        let (secret, commitment) = self.secrets()?;
+        let randomness = derive_randomness(secret)?;
+        check_salvo(&self.opponent_view, targets, self.opponent_salvo_size())?;
+        let (pk, _) = keys.salvo()?;
+
+        let (hits, proof) = answer_salvo::<P, _>(self.game.backend, pk, self.game.geometry, &self.board, &randomness, commitment, targets, self.game.max_salvo(), rng)?;
+        for (target, hit) in targets.iter().zip(&hits) {
+            self.opponent_view[*target as usize] = if *hit { 2 } else { 1 };
+        }
+        Ok((hits, proof))
+    }
+
+    /**
+    *   checks the opponent's answers to our salvo against their commitment and records
+    *   the hits and misses
+    */
+    pub fn verify_salvo<P: GameSystem>(&mut self, keys: &GameKeys<P>, targets: &[u16], hits: &[bool], proof: P::Proof) -> Result<(), GameError> {
+        self.check_system::<P>()?;
+        self.check_opponent_verified()?;
+        self.fire_salvo(targets)?;
+        let (_, vk) = keys.salvo()?;
+        let commitment = self.opponent_commitment()?;
+        if !verify_salvo::<P>(self.game.backend, vk, commitment, self.game.geometry, targets, hits, self.game.max_salvo(), proof)? {
+            return Err(GameError::ProofFailure("the opponent's salvo proof is invalid".to_string()));
+        }
+
+        for (target, hit) in targets.iter().zip(hits) {
+            self.view[*target as usize] = if *hit { 2 } else { 1 };
+        }
+        Ok(())
+    }
+
+    /**
+    *   proves which of this player's ships the opponent's salvo sank, each announced with
+    *   the first of its targets that hit the ship
+    */
+    pub fn announce_salvo_sunk<P: GameSystem, R: RngCore + CryptoRng>(&self, keys: &GameKeys<P>, targets: &[u16], rng: &mut R) -> Result<Vec<(u16, Sunk<P>)>, GameError> {
+        let mut announced: Vec<(u16, Sunk<P>)> = vec![];
+        for target in targets {
+            let number = self.board.get(*target as usize).copied().unwrap_or(0);
+            if announced.iter().any(|(_, sunk)| sunk.ship + 1 == number) {
+                continue;
+            }
+            if let Some(sunk) = self.announce_sunk(keys, *target, rng)? {
+                announced.push((*target, sunk));
+            }
+        }
+        Ok(announced)
+    }
+
+    /**
+    *   if the opponent's shot at the target sank one of this player's ships, proves which one.
+    *   None if the shot missed or the ship it hit still has tiles left
+    */
+    pub fn announce_sunk<P: GameSystem, R: RngCore + CryptoRng>(&self, keys: &GameKeys<P>, target: u16, rng: &mut R) -> Result<Option<Sunk<P>>, GameError> {
+        self.check_system::<P>()?;
+        let (secret, commitment) = self.secrets()?;
+        let randomness = derive_randomness(secret)?;
+        if self.opponent_view.get(target as usize) != Some(&2) {
+            return Ok(None);
+        }
+        let number = self.board[target as usize];
+        let sunk = self.board.iter().zip(&self.opponent_view).all(|(tile, seen)| *tile != number || *seen == 2);
+        if !sunk {
+            return Ok(None);
+        }
+
+        let hits: Vec<bool> = self.opponent_view.iter().map(|seen| *seen == 2).collect();
+        let proof = prove_sunk::<P, _>(self.game.backend, &keys.sunk_pk, self.game.geometry, &self.board, &randomness, commitment, target, number - 1, &hits, rng)?;
+        Ok(Some(Sunk { ship: number - 1, proof }))
+    }
+
+    /**
+    *   checks the opponent's proof that our hit at the target sank a ship, returning the
+    *   length of the sunk ship
+    */
+    pub fn verify_sunk<P: GameSystem>(&mut self, keys: &GameKeys<P>, target: u16, sunk: Sunk<P>) -> Result<u8, GameError> {
+        self.check_system::<P>()?;
+        self.check_opponent_verified()?;
+        if self.view.get(target as usize) != Some(&2) {
+            return Err(GameError::BadInput(format!("tile {} isn't one of our hits", target)));
+        }
+        let length = *self.game.fleet.get(sunk.ship as usize)
+            .ok_or_else(|| GameError::BadInput(format!("the fleet has no ship {}", sunk.ship)))?;
+        if self.sunk.contains(&sunk.ship) {
+            return Err(GameError::BadInput(format!("ship {} has already been sunk", sunk.ship)));
+        }
+
+        let commitment = self.opponent_commitment()?;
+        let hits: Vec<bool> = self.view.iter().map(|seen| *seen == 2).collect();
+        if !verify_sunk::<P>(self.game.backend, &keys.sunk_vk, commitment, self.game.geometry, target, sunk.ship, &hits, sunk.proof)? {
+            return Err(GameError::ProofFailure("the opponent's sunk ship proof is invalid".to_string()));
+        }
+        self.sunk.push(sunk.ship);
+        Ok(length)
+    }
+
+    /// fleet indices of the opponent's ships proven sunk so far
+    pub fn sunk_ships(&self) -> &[u8] {
+        &self.sunk
+    }
+
+    /**
+    *   proves how many of this player's ships are still afloat after the opponent's hits,
+    *   returning the count and the proof
+    */
+    pub fn prove_remaining<P: GameSystem, R: RngCore + CryptoRng>(&self, keys: &GameKeys<P>, rng: &mut R) -> Result<(u8, P::Proof), GameError> {
+        self.check_system::<P>()?;
+        let (secret, commitment) = self.secrets()?;
+        let randomness = derive_randomness(secret)?;
+        let remaining = (1..=self.game.fleet.len() as u8)
+            .filter(|number| self.board.iter().zip(&self.opponent_view).any(|(tile, seen)| tile == number && *seen != 2))
+            .count() as u8;
+
+        let hits: Vec<bool> = self.opponent_view.iter().map(|seen| *seen == 2).collect();
+        let proof = prove_remaining::<P, _>(self.game.backend, &keys.remaining_pk, self.game.geometry, self.game.fleet.len() as u8, &self.board, &randomness, commitment, &hits, remaining, rng)?;
+        Ok((remaining, proof))
+    }
+
+    /**
+    *   checks the opponent's proof of how many of their ships are still afloat after our hits.
+    *   a proof of 0 establishes this player's victory from the opponent's commitment alone
+    */
+    pub fn verify_remaining<P: GameSystem>(&mut self, keys: &GameKeys<P>, remaining: u8, proof: P::Proof) -> Result<(), GameError> {
+        self.check_system::<P>()?;
+        self.check_opponent_verified()?;
+        let commitment = self.opponent_commitment()?;
+        let hits: Vec<bool> = self.view.iter().map(|seen| *seen == 2).collect();
+        if !verify_remaining::<P>(self.game.backend, &keys.remaining_vk, commitment, self.game.geometry, self.game.fleet.len() as u8, &hits, remaining, proof)? {
+            return Err(GameError::ProofFailure("the opponent's remaining ships proof is invalid".to_string()));
+        }
+        self.proven_remaining = Some(remaining);
+        Ok(())
+    }
+
+    /**
+    *   checks the opponent's proof that none of their ships remain, which confirms this
+    *   player's victory. a valid proof that some remain, once every tile of the fleet has
+    *   been hit, means the opponent answered a shot falsely, so it is a ProofFailure too
+    */
+    pub fn confirm_victory<P: GameSystem>(&mut self, keys: &GameKeys<P>, remaining: u8, proof: P::Proof) -> Result<(), GameError> {
+        self.verify_remaining(keys, remaining, proof)?;
+        if remaining != 0 {
+            return Err(GameError::ProofFailure(format!("the opponent proved {} of their ships remain", remaining)));
+        }
+        Ok(())
+    }
+
+    /// how many of the opponent's ships their last remaining ships proof showed afloat
+    pub fn proven_remaining(&self) -> Option<u8> {
+        self.proven_remaining
+    }
+
+    /// true once this player has hit every tile of the opponent's fleet. only meaningful
+    /// once the opponent's board proof has verified, so it's an error before then
+    pub fn has_won(&self) -> Result<bool, GameError> {
+        self.check_opponent_verified()?;
+        Ok(check_winner(&self.view, &self.game.fleet))
+    }
+
+    /// true once the opponent has hit every tile of this player's fleet
+    pub fn has_lost(&self) -> bool {
+        check_winner(&self.opponent_view, &self.game.fleet)
+    }
+
+    /// true once either player has won
+    pub fn is_over(&self) -> bool {
+        self.has_lost() || (self.opponent_verified && check_winner(&self.view, &self.game.fleet))
+    }
+
+    /**
+    *   reveals the ships and randomness behind this player's commitment. only once the
+    *   game is over, since it gives the whole board away
+    */
+    pub fn reveal(&self) -> Result<Reveal, GameError> {
+        let (secret, _) = self.secrets()?;
+        let randomness = derive_randomness(secret)?;
+        if !self.is_over() {
+            return Err(GameError::BadInput("the board can't be revealed before the game is over".to_string()));
+        }
+        Ok(Reveal { ships: self.ships.clone(), randomness: randomness.to_vec() })
+    }
+
+    /**
+    *   checks the opponent's revealed board against their commitment and every answer
+    *   they gave to this player's shots
+    */
+    pub fn audit(&self, reveal: &Reveal) -> Result<(), GameError> {
+        audit_board(&self.game, self.opponent_commitment()?, &self.view, &self.sunk, reveal)
+    }
+
+    /// the ships and the secret behind the commitment, the part of a saved player that is
+    /// kept apart so it can be encrypted
+    pub fn player_secrets(&self) -> PlayerSecrets {
+        PlayerSecrets { ships: self.ships.clone(), secret: self.secret.clone() }
+    }
+
+    /**
+    *   writes everything but the player's secrets: both commitments, whether the opponent's
+    *   board proof verified, the sunk ships and both views
+    */
+    pub fn write_public<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.commitment.serialize(&mut writer)?;
+        self.opponent_commitment.serialize(&mut writer)?;
+        self.opponent_verified.serialize(&mut writer)?;
+        self.sunk.serialize(&mut writer)?;
+        self.proven_remaining.serialize(&mut writer)?;
+        self.view.serialize(&mut writer)?;
+        self.opponent_view.serialize(&mut writer)
+    }
+
+    /**
+    *   rebuilds a player from the output of write_public and their secrets. the ships must
+    *   be a legal placement of the fleet and the secret must open the saved commitment, so
+    *   a save that was edited or mixed up with another game is refused
+    */
+    pub fn read<R: Read>(game: &Game, mut reader: R, secrets: PlayerSecrets) -> Result<PlayerState, GameError> {
+        let mut player = game.new_player();
+        player.place_fleet(&secrets.ships)?;
+        player.secret = secrets.secret;
+        player.commitment = Option::<Vec<u8>>::deserialize(&mut reader)?;
+        player.opponent_commitment = Option::<Vec<u8>>::deserialize(&mut reader)?;
+        player.opponent_verified = bool::deserialize(&mut reader)?;
+        player.sunk = Vec::<u8>::deserialize(&mut reader)?;
+        player.proven_remaining = Option::<u8>::deserialize(&mut reader)?;
+        player.view = Vec::<u8>::deserialize(&mut reader)?;
+        player.opponent_view = Vec::<u8>::deserialize(&mut reader)?;
+
+        let tiles = game.geometry.tiles();
+        if player.view.len() != tiles || player.opponent_view.len() != tiles || player.sunk.iter().any(|ship| *ship as usize >= game.fleet.len()) {
+            return Err(SerializationError::InvalidData.into());
+        }
+        match (&player.secret, &player.commitment) {
+            (Some(secret), Some(commitment)) => {
+                if &generate_commitment(game.curve, game.backend, &player.board, &derive_randomness(secret)?)? != commitment {
+                    return Err(GameError::BadInput("the saved secret doesn't open the saved commitment".to_string()));
+                }
+            }
+            (None, None) => {}
+            _ => return Err(SerializationError::InvalidData.into()),
+        }
+        Ok(player)
+    }
+}
+
+/**
+*   the ships and the secret behind a player's commitment, everything the opponent must
+*   never see before the game is over
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerSecrets {
+    pub ships: Vec<Ship>,
+    /// None until the board has been committed to
+    pub secret: Option<Vec<u8>>,
+}
+
+impl CanonicalSerialize for PlayerSecrets {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.ships.serialize(&mut writer)?;
+        self.secret.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.ships.serialized_size() + self.secret.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for PlayerSecrets {
+    fn deserialize<R: Read>(mut reader: R) -> Result<PlayerSecrets, SerializationError> {
+        Ok(PlayerSecrets {
+            ships: Vec::<Ship>::deserialize(&mut reader)?,
+            secret: Option::<Vec<u8>>::deserialize(&mut reader)?,
+        })
+    }
+}
+
+/**
+*   a target is valid if it's on the board and hasn't been fired at in this view
+*/
+fn check_target(view: &[u8], target: u16) -> Result<u16, GameError> {
+    match view.get(target as usize) {
+        None => Err(GameError::OutOfBounds { target, board_size: view.len() }),
+        Some(0) => Ok(target),
+        Some(_) => Err(GameError::RepeatedShot(target)),
+    }
+}
+
+/// number of tiles in the view that haven't been fired at
+fn unattacked(view: &[u8]) -> usize {
+    view.iter().filter(|seen| **seen == 0).count()
+}
+
+/**
+*   checks a salvo has the given number of shots, all on the board, not fired at before and
+*   no two at the same tile
+*/
+fn check_salvo(view: &[u8], targets: &[u16], shots: usize) -> Result<(), GameError> {
+    if targets.len() != shots {
+        return Err(GameError::BadInput(format!("the salvo has {} shots, not {}", targets.len(), shots)));
+    }
+    for (i, target) in targets.iter().enumerate() {
+        check_target(view, *target)?;
+        if targets[..i].contains(target) {
+            return Err(GameError::RepeatedShot(*target));
+        }
+    }
+    Ok(())
+}
+
+/**
+*   generates the 32 byte player secret the board commitment randomness is derived from
+*/
+pub fn generate_secret<R: RngCore + CryptoRng>(rng: &mut R) -> Vec<u8> {
+    let mut secret = [0u8; SECRET_LEN];
+    rng.fill(&mut secret);
+    secret.to_vec()
+}
+
+/**
+*   generates a single commitment to the whole board using the randomness, for proofs on the curve
+*/
+pub fn generate_commitment(curve: Curve, backend: CommitmentBackend, board: &[u8], randomness: &[u8]) -> Result<Vec<u8>, GameError> {
+    backend.commit(curve, board, randomness)
+}
+
+/**
+*   generates a proof of the board with the proving key from setup. the commitment
+*   must be to randomness derived from the secret, which the proof checks too
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn generate_proof<P: GameSystem, R: RngCore + CryptoRng>(backend: CommitmentBackend, pk: &P::ProvingKey, board: &[u8], ships: &[Ship], secret: &[u8], commitment: &[u8], fleet: &[u8], geometry: BoardGeometry, rule: AdjacencyRule, rng: &mut R)
+-> Result<P::Proof, GameError> {
+    let circuit = BoardVerifier {
+        fleet: fleet.to_vec(),
+        geometry,
+        rule,
+        commitment: commitment.to_vec(),
+        backend,
+
+        secret: Some(secret.to_vec()),
+        board: Some(board.to_vec()),
+        ships: Some(ships.to_vec()),
+    };
+
+    // Create a proof with our parameters.
+    P::prove(pk, circuit, rng)
+}
+
+/**
+* verifies proof using public information, the proof and the verifying key from setup
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn verify_initial_proof<P: GameSystem>(backend: CommitmentBackend, pvk: &P::PreparedVerifyingKey, commitment: &[u8], fleet: &[u8], geometry: BoardGeometry, rule: AdjacencyRule, proof: P::Proof) -> Result<bool, GameError> {
+    let  mut inputs: Vec<_> = Vec::new();
+    for length in fleet {
+        inputs.push(P::Field::from(*length));
+    }
+    inputs.push(P::Field::from(geometry.width));
+    inputs.push(P::Field::from(geometry.height));
+    inputs.push(P::Field::from(rule.to_u8()));
+
+    inputs.append(&mut backend.to_inputs(commitment)?);
+
+    P::verify(pvk, &inputs, &proof)
+}
+
+/**
+*   answers a shot at the target tile with a proof that the committed board
+*   holds a battleship (or not) there. the board and randomness stay private
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn answer_shot<P: GameSystem, R: RngCore + CryptoRng>(backend: CommitmentBackend, pk: &P::ProvingKey, geometry: BoardGeometry, board: &[u8], randomness: &[u8], commitment: &[u8], target: u16, rng: &mut R)
+-> Result<(bool, P::Proof), GameError> {
+    if !geometry.contains(target) {
+        return Err(GameError::OutOfBounds { target, board_size: geometry.tiles() });
+    }
+    let hit = *board.get(target as usize).ok_or_else(|| GameError::BadInput(format!("the board has {} tiles, not {}", board.len(), geometry.tiles())))?;
+    let circuit = ShotVerifier {
+        geometry,
+        target,
+        hit: (hit != 0) as u8,
+        commitment: commitment.to_vec(),
+        backend,
+
+        rng_in: Some(randomness.to_vec()),
+        board: Some(board.to_vec()),
+    };
+
+    let proof = P::prove(pk, circuit, rng)?;
+
+    Ok((hit != 0, proof))
+}
+
+/**
+*   verifies the answer to a shot using only the opponent's public board commitment
+*/
+pub fn verify_shot<P: GameSystem>(backend: CommitmentBackend, pvk: &P::PreparedVerifyingKey, commitment: &[u8], geometry: BoardGeometry, target: u16, hit: bool, proof: P::Proof) -> Result<bool, GameError> {
+    let  mut inputs: Vec<_> = vec![P::Field::from(geometry.width), P::Field::from(geometry.height), P::Field::from(target), P::Field::from(hit as u8)];
+
+    inputs.append(&mut backend.to_inputs(commitment)?);
+
+    P::verify(pvk, &inputs, &proof)
+}
+
+/**
+*   answers every shot of a salvo with one proof over the committed board, returning
+*   whether each one hit. the salvo is padded to the `shots` the salvo keys were made for
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn answer_salvo<P: GameSystem, R: RngCore + CryptoRng>(backend: CommitmentBackend, pk: &P::ProvingKey, geometry: BoardGeometry, board: &[u8], randomness: &[u8], commitment: &[u8], targets: &[u16], shots: usize, rng: &mut R)
+-> Result<(Vec<bool>, P::Proof), GameError> {
+    let mut hits = vec![];
+    for target in targets {
+        if !geometry.contains(*target) {
+            return Err(GameError::OutOfBounds { target: *target, board_size: geometry.tiles() });
+        }
+        let tile = *board.get(*target as usize).ok_or_else(|| GameError::BadInput(format!("the board has {} tiles, not {}", board.len(), geometry.tiles())))?;
+        hits.push(tile != 0);
+    }
+    let (padded_targets, padded_hits) = pad_salvo(targets, &hits, shots)
+        .ok_or_else(|| GameError::BadInput(format!("a salvo has 1 to {} shots, not {}", shots, targets.len())))?;
+    let circuit = SalvoVerifier {
+        geometry,
+        targets: padded_targets,
+        hits: padded_hits,
+        commitment: commitment.to_vec(),
+        backend,
+
+        rng_in: Some(randomness.to_vec()),
+        board: Some(board.to_vec()),
+    };
+
+    let proof = P::prove(pk, circuit, rng)?;
+
+    Ok((hits, proof))
+}
+
+/**
+*   verifies the answers to a salvo using only the opponent's public board commitment
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn verify_salvo<P: GameSystem>(backend: CommitmentBackend, pvk: &P::PreparedVerifyingKey, commitment: &[u8], geometry: BoardGeometry, targets: &[u16], hits: &[bool], shots: usize, proof: P::Proof) -> Result<bool, GameError> {
+    let (targets, hits) = pad_salvo(targets, hits, shots)
+        .ok_or_else(|| GameError::BadInput(format!("a salvo has 1 to {} shots with one answer each", shots)))?;
+    let mut inputs: Vec<_> = vec![P::Field::from(geometry.width), P::Field::from(geometry.height)];
+    inputs.extend(targets.iter().map(|target| P::Field::from(*target)));
+    inputs.extend(hits.iter().map(|hit| P::Field::from(*hit)));
+
+    inputs.append(&mut backend.to_inputs(commitment)?);
+
+    P::verify(pvk, &inputs, &proof)
+}
+
+/**
+*   proves the ship at `ship` in the fleet was sunk by the shot at the target, given the
+*   tiles the attacker has hit so far
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn prove_sunk<P: GameSystem, R: RngCore + CryptoRng>(backend: CommitmentBackend, pk: &P::ProvingKey, geometry: BoardGeometry, board: &[u8], randomness: &[u8], commitment: &[u8], target: u16, ship: u8, hits: &[bool], rng: &mut R)
+-> Result<P::Proof, GameError> {
+    let circuit = SunkVerifier {
+        geometry,
+        target,
+        ship,
+        hits: hits.to_vec(),
+        commitment: commitment.to_vec(),
+        backend,
+
+        rng_in: Some(randomness.to_vec()),
+        board: Some(board.to_vec()),
+    };
+
+    P::prove(pk, circuit, rng)
+}
+
+/**
+*   verifies a sunk ship announcement against the defender's public board commitment and
+*   the tiles the attacker has hit
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn verify_sunk<P: GameSystem>(backend: CommitmentBackend, pvk: &P::PreparedVerifyingKey, commitment: &[u8], geometry: BoardGeometry, target: u16, ship: u8, hits: &[bool], proof: P::Proof) -> Result<bool, GameError> {
+    if hits.len() != geometry.tiles() {
+        return Err(GameError::BadInput(format!("expected {} hit tiles, got {}", geometry.tiles(), hits.len())));
+    }
+    let  mut inputs: Vec<_> = vec![P::Field::from(geometry.width), P::Field::from(geometry.height), P::Field::from(target), P::Field::from(ship)];
+    inputs.append(&mut pack_hits(hits));
+
+    inputs.append(&mut backend.to_inputs(commitment)?);
+
+    P::verify(pvk, &inputs, &proof)
+}
+
+/**
+*   proves how many of the fleet's ships have a tile outside the attacker's hits
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn prove_remaining<P: GameSystem, R: RngCore + CryptoRng>(backend: CommitmentBackend, pk: &P::ProvingKey, geometry: BoardGeometry, ships: u8, board: &[u8], randomness: &[u8], commitment: &[u8], hits: &[bool], remaining: u8, rng: &mut R)
+-> Result<P::Proof, GameError> {
+    let circuit = RemainingVerifier {
+        geometry,
+        ships,
+        hits: hits.to_vec(),
+        remaining,
+        commitment: commitment.to_vec(),
+        backend,
+
+        rng_in: Some(randomness.to_vec()),
+        board: Some(board.to_vec()),
+    };
+
+    P::prove(pk, circuit, rng)
+}
+
+/**
+*   verifies the number of ships still afloat against the defender's public board commitment
+*   and the tiles the attacker has hit
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn verify_remaining<P: GameSystem>(backend: CommitmentBackend, pvk: &P::PreparedVerifyingKey, commitment: &[u8], geometry: BoardGeometry, ships: u8, hits: &[bool], remaining: u8, proof: P::Proof) -> Result<bool, GameError> {
+    if hits.len() != geometry.tiles() {
+        return Err(GameError::BadInput(format!("expected {} hit tiles, got {}", geometry.tiles(), hits.len())));
+    }
+    let  mut inputs: Vec<_> = vec![P::Field::from(geometry.width), P::Field::from(geometry.height), P::Field::from(ships)];
+    inputs.append(&mut pack_hits(hits));
+    inputs.push(P::Field::from(remaining));
+
+    inputs.append(&mut backend.to_inputs(commitment)?);
+
+    P::verify(pvk, &inputs, &proof)
+}
+
+/**
+*   rebuilds the board from the revealed ships and checks that it and the revealed randomness
+*   open the commitment, that every shot in the view (1 = miss, 2 = hit) was answered
+*   truthfully and that every ship the hits sank is among the sunk ones announced. any
+*   mismatch is the revealing player cheating
+*/
+pub fn audit_board(game: &Game, commitment: &[u8], view: &[u8], sunk: &[u8], reveal: &Reveal) -> Result<(), GameError> {
+    let fleet = game.fleet();
+    let board = fleet_board(&reveal.ships, fleet, game.geometry)
+        .ok_or_else(|| GameError::AuditFailure("the revealed ships aren't a legal placement of the fleet".to_string()))?;
+    let opened = generate_commitment(game.curve, game.backend, &board, &reveal.randomness)
+        .map_err(|_| GameError::AuditFailure("the revealed randomness is malformed".to_string()))?;
+    if opened != commitment {
+        return Err(GameError::AuditFailure("the revealed board doesn't open the commitment".to_string()));
+    }
+
+    for (tile, (seen, actual)) in view.iter().zip(&board).enumerate() {
+        match (seen, actual) {
+            (1, 1..) => return Err(GameError::AuditFailure(format!("tile {} was answered as a miss but holds a battleship", tile))),
+            (2, 0) => return Err(GameError::AuditFailure(format!("tile {} was answered as a hit but is empty", tile))),
+            _ => {}
+        }
+    }
+
+    // a sinking kept quiet would leave the player more shots in a salvo game
+    for ship in 0..fleet.len() as u8 {
+        let hit = board.iter().zip(view).all(|(tile, seen)| *tile != ship + 1 || *seen == 2);
+        if hit && !sunk.contains(&ship) {
+            return Err(GameError::AuditFailure(format!("ship {} was sunk but never announced", ship)));
+        }
+    }
+    Ok(())
+}
+
+/**
+*   Winner when the verified hits cover the whole fleet. the board proof shows the
+*   committed board has exactly as many battleship tiles as the fleet's total length
+*/
+pub fn check_winner(view_board: &[u8], fleet: &[u8]) -> bool {
+    let hits = view_board.iter().filter(|tile| **tile == 2).count();
+    let total: usize = fleet.iter().map(|length| *length as usize).sum();
+    hits == total
+}
+
+#[cfg(test)]
+pub(crate) fn test_game() -> (Game, GameKeys) {
+    let game = Game::new(BoardGeometry::square(3), vec![2, 1], CommitmentBackend::Poseidon).unwrap();
+    let keys: GameKeys = GameKeys::generate(game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut OsRng).unwrap();
+    (game, keys)
+}
+
+#[test]
+fn test_full_game() {
+    let (game, keys) = test_game();
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+
+    a.place_fleet(&[
+        Ship { origin: 0, orientation: Orientation::Horizontal },
+        Ship { origin: 8, orientation: Orientation::Horizontal },
+    ]).unwrap();
+    b.place_fleet(&[
+        Ship { origin: 1, orientation: Orientation::Vertical },
+        Ship { origin: 6, orientation: Orientation::Horizontal },
+    ]).unwrap();
+
+    let commitment_a = a.commit(&mut OsRng).unwrap();
+    let commitment_b = b.commit(&mut OsRng).unwrap();
+    a.receive_commitment(commitment_b).unwrap();
+    b.receive_commitment(commitment_a).unwrap();
+
+    a.verify_board(&keys, b.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+    b.verify_board(&keys, a.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+
+    // a sinks b's fleet, missing once
+    for target in [0, 1, 4, 6] {
+        let target = a.fire(target).unwrap();
+        let (hit, proof) = b.answer(&keys, target, &mut OsRng).unwrap();
+        assert_eq!(hit, target != 0);
+        a.verify_answer(&keys, target, hit, proof).unwrap();
+    }
+
+    assert!(a.has_won().unwrap());
+    assert!(b.has_lost());
+    assert!(!b.has_won().unwrap());
+    assert_eq!(a.view(), b.opponent_view());
+}
+
+#[test]
+fn test_invalid_moves() {
+    let (game, keys) = test_game();
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+
+    assert!(Game::new(BoardGeometry::new(0, 3), vec![2], CommitmentBackend::Poseidon).is_err());
+    assert!(Game::new(BoardGeometry::square(3), vec![4], CommitmentBackend::Poseidon).is_err());
+    // every ship fits on its own, but not all of them together
+    assert!(Game::new(BoardGeometry::square(3), vec![3, 3, 3, 1], CommitmentBackend::Poseidon).is_err());
+    // pedersen windows only cover 256 tiles
+    assert!(Game::new(BoardGeometry::new(16, 17), vec![2], CommitmentBackend::Pedersen).is_err());
+
+    // off the board, overlapping
+    assert!(a.place_ship(Ship { origin: 2, orientation: Orientation::Horizontal }).is_err());
+    a.place_ship(Ship { origin: 0, orientation: Orientation::Horizontal }).unwrap();
+    assert!(a.commit(&mut OsRng).is_err());
+    assert!(a.place_ship(Ship { origin: 1, orientation: Orientation::Horizontal }).is_err());
+    a.place_ship(Ship { origin: 8, orientation: Orientation::Horizontal }).unwrap();
+    assert!(a.place_ship(Ship { origin: 4, orientation: Orientation::Horizontal }).is_err());
+
+    b.place_fleet(&[
+        Ship { origin: 3, orientation: Orientation::Horizontal },
+        Ship { origin: 2, orientation: Orientation::Horizontal },
+    ]).unwrap();
+    // a commitment the backend couldn't have produced is rejected
+    assert!(matches!(a.receive_commitment(vec![0xff; 31]), Err(GameError::CommitmentMismatch)));
+    a.receive_commitment(b.commit(&mut OsRng).unwrap()).unwrap();
+    b.receive_commitment(a.commit(&mut OsRng).unwrap()).unwrap();
+
+    // nothing counts towards a win before the opponent's board proof has verified
+    assert!(matches!(a.has_won(), Err(GameError::BadInput(_))));
+    let (hit, proof) = b.answer(&keys, 2, &mut OsRng).unwrap();
+    assert!(matches!(a.verify_answer(&keys, 2, hit, proof), Err(GameError::BadInput(_))));
+    a.verify_board(&keys, b.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+
+    // shots off the board or repeated
+    assert!(matches!(a.fire(9), Err(GameError::OutOfBounds { target: 9, board_size: 9 })));
+    let (hit, proof) = b.answer(&keys, 3, &mut OsRng).unwrap();
+    assert!(matches!(b.answer(&keys, 3, &mut OsRng), Err(GameError::RepeatedShot(3))));
+
+    // a lie about the answer is caught
+    assert!(matches!(a.verify_answer(&keys, 3, !hit, proof.clone()), Err(GameError::ProofFailure(_))));
+    a.verify_answer(&keys, 3, hit, proof).unwrap();
+    assert!(matches!(a.fire(3), Err(GameError::RepeatedShot(3))));
+}
+
+#[test]
+fn test_adjacency_rule() {
+    let (game, keys) = test_game();
+    let game = game.with_rule(AdjacencyRule::NoTouch);
+
+    // the ship of length 1 can't go next to the first ship, but may meet it at a corner
+    let mut a = game.new_player();
+    a.place_ship(Ship { origin: 0, orientation: Orientation::Horizontal }).unwrap();
+    assert!(a.place_ship(Ship { origin: 2, orientation: Orientation::Horizontal }).is_err());
+    assert!(a.place_ship(Ship { origin: 4, orientation: Orientation::Horizontal }).is_err());
+    let mut strict = game.clone().with_rule(AdjacencyRule::NoDiagonalTouch).new_player();
+    strict.place_ship(Ship { origin: 0, orientation: Orientation::Horizontal }).unwrap();
+    assert!(strict.place_ship(Ship { origin: 5, orientation: Orientation::Horizontal }).is_err());
+    a.place_ship(Ship { origin: 5, orientation: Orientation::Horizontal }).unwrap();
+
+    // the rule is a public input, so a board proof only verifies under the rule it was made for
+    let commitment = a.commit(&mut OsRng).unwrap();
+    let proof = a.prove_board(&keys, &mut OsRng).unwrap();
+    assert!(verify_initial_proof::<Groth16<Bls12_381>>(game.backend(), &keys.board_vk, &commitment, game.fleet(), game.geometry(), AdjacencyRule::NoTouch, proof.clone()).unwrap());
+    assert!(!verify_initial_proof::<Groth16<Bls12_381>>(game.backend(), &keys.board_vk, &commitment, game.fleet(), game.geometry(), AdjacencyRule::NoDiagonalTouch, proof).unwrap());
+}
+
+#[test]
+fn test_board_geometry() {
+    // a 16x16 board fits every backend, including pedersen's 256 byte windows
+    let game = Game::new(BoardGeometry::square(16), vec![5, 4, 3, 3, 2], CommitmentBackend::Pedersen).unwrap();
+    let mut a = game.new_player();
+    assert_eq!(a.board().len(), 256);
+    assert!(a.fire(255).is_ok());
+    assert!(matches!(a.fire(256), Err(GameError::OutOfBounds { target: 256, board_size: 256 })));
+    a.place_ship(Ship { origin: 251, orientation: Orientation::Horizontal }).unwrap();
+    assert!(a.place_ship(Ship { origin: 203, orientation: Orientation::Vertical }).is_err());
+
+    // on a rectangular board, rows are width tiles long and columns height tiles long
+    let game = Game::new(BoardGeometry::new(5, 2), vec![5, 2], CommitmentBackend::Poseidon).unwrap();
+    let mut b = game.new_player();
+    assert!(b.place_ship(Ship { origin: 0, orientation: Orientation::Vertical }).is_err());
+    b.place_ship(Ship { origin: 0, orientation: Orientation::Horizontal }).unwrap();
+    assert!(b.place_ship(Ship { origin: 8, orientation: Orientation::Horizontal }).is_ok());
+    assert!(Game::new(BoardGeometry::new(5, 2), vec![3], CommitmentBackend::Poseidon).is_ok());
+    assert!(Game::new(BoardGeometry::new(2, 5), vec![6], CommitmentBackend::Poseidon).is_err());
+}
+
+#[test]
+fn test_reveal_and_audit() {
+    let (game, keys) = test_game();
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+    a.place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }]).unwrap();
+    b.place_fleet(&[Ship { origin: 1, orientation: Orientation::Vertical }, Ship { origin: 6, orientation: Orientation::Horizontal }]).unwrap();
+    a.receive_commitment(b.commit(&mut OsRng).unwrap()).unwrap();
+    b.receive_commitment(a.commit(&mut OsRng).unwrap()).unwrap();
+    a.verify_board(&keys, b.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+    b.verify_board(&keys, a.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+
+    // nothing is revealed mid-game
+    assert!(matches!(b.reveal(), Err(GameError::BadInput(_))));
+    for target in [0, 1, 4, 6] {
+        let (hit, proof) = b.answer(&keys, target, &mut OsRng).unwrap();
+        a.verify_answer(&keys, target, hit, proof).unwrap();
+        if let Some(sunk) = b.announce_sunk(&keys, target, &mut OsRng).unwrap() {
+            a.verify_sunk(&keys, target, sunk).unwrap();
+        }
+    }
+    assert!(a.is_over() && b.is_over());
+
+    // the loser's board and the winner's both check out
+    let reveal = b.reveal().unwrap();
+    a.audit(&reveal).unwrap();
+    b.audit(&a.reveal().unwrap()).unwrap();
+
+    // other ships or other randomness don't open the commitment
+    let mut moved = reveal.clone();
+    moved.ships[1].origin = 7;
+    assert!(matches!(a.audit(&moved), Err(GameError::AuditFailure(_))));
+    let mut salted = reveal.clone();
+    salted.randomness[0] ^= 1;
+    assert!(matches!(a.audit(&salted), Err(GameError::AuditFailure(_))));
+    salted.randomness.pop();
+    assert!(matches!(a.audit(&salted), Err(GameError::AuditFailure(_))));
+
+    // and an answer that contradicts the opened board is caught
+    let commitment = b.commitment().unwrap();
+    let mut view = a.view().to_vec();
+    audit_board(&game, commitment, &view, a.sunk_ships(), &reveal).unwrap();
+    view[0] = 2;
+    assert!(matches!(audit_board(&game, commitment, &view, a.sunk_ships(), &reveal), Err(GameError::AuditFailure(_))));
+    view[0] = 0;
+    view[1] = 1;
+    assert!(matches!(audit_board(&game, commitment, &view, a.sunk_ships(), &reveal), Err(GameError::AuditFailure(_))));
+
+    // as is a ship that went down without being announced
+    assert!(matches!(audit_board(&game, commitment, a.view(), &[1], &reveal), Err(GameError::AuditFailure(_))));
+}
+
+#[test]
+fn test_salvo_game() {
+    let game = Game::new(BoardGeometry::square(3), vec![2, 1], CommitmentBackend::Poseidon).unwrap().with_salvo(Salvo::Ships).unwrap();
+    assert!(game.clone().with_salvo(Salvo::Fixed(1)).is_err());
+    let keys: GameKeys = GameKeys::generate(game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut OsRng).unwrap();
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+    a.place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }]).unwrap();
+    b.place_fleet(&[Ship { origin: 1, orientation: Orientation::Vertical }, Ship { origin: 6, orientation: Orientation::Horizontal }]).unwrap();
+    a.receive_commitment(b.commit(&mut OsRng).unwrap()).unwrap();
+    b.receive_commitment(a.commit(&mut OsRng).unwrap()).unwrap();
+    a.verify_board(&keys, b.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+    b.verify_board(&keys, a.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+
+    // one shot per ship afloat, each at a different tile
+    assert_eq!((a.salvo_size(), b.opponent_salvo_size()), (2, 2));
+    assert!(matches!(b.answer_salvo(&keys, &[0], &mut OsRng), Err(GameError::BadInput(_))));
+    assert!(matches!(b.answer_salvo(&keys, &[0, 0], &mut OsRng), Err(GameError::RepeatedShot(0))));
+    assert!(matches!(b.answer_salvo(&keys, &[0, 9], &mut OsRng), Err(GameError::OutOfBounds { .. })));
+
+    // the whole salvo is answered with one proof, and a lie about any shot in it fails
+    let (hits, proof) = b.answer_salvo(&keys, &[1, 4], &mut OsRng).unwrap();
+    assert_eq!(hits, vec![true, true]);
+    let sunk = b.announce_salvo_sunk(&keys, &[1, 4], &mut OsRng).unwrap();
+    assert_eq!(sunk.len(), 1);
+    assert!(matches!(a.verify_salvo(&keys, &[1, 4], &[true, false], proof.clone()), Err(GameError::ProofFailure(_))));
+    a.verify_salvo(&keys, &[1, 4], &hits, proof).unwrap();
+    let (target, sunk) = sunk.into_iter().next().unwrap();
+    assert_eq!(a.verify_sunk(&keys, target, sunk).unwrap(), 2);
+
+    // b has one ship left, so one shot
+    assert_eq!((b.salvo_size(), a.opponent_salvo_size()), (1, 1));
+    let (hits, proof) = a.answer_salvo(&keys, &[8], &mut OsRng).unwrap();
+    b.verify_salvo(&keys, &[8], &hits, proof).unwrap();
+    let (target, sunk) = a.announce_salvo_sunk(&keys, &[8], &mut OsRng).unwrap().pop().unwrap();
+    b.verify_sunk(&keys, target, sunk).unwrap();
+
+    assert_eq!(a.salvo_size(), 1);
+    let (hits, proof) = b.answer_salvo(&keys, &[6], &mut OsRng).unwrap();
+    a.verify_salvo(&keys, &[6], &hits, proof).unwrap();
+    assert!(a.has_won().unwrap() && b.has_lost());
+
+    // keys for the classic game can't prove salvos
+    let (_, classic) = test_game();
+    assert!(matches!(b.answer_salvo(&classic, &[2], &mut OsRng), Err(GameError::BadInput(_))));
+}
+
+#[test]
+fn test_game_on_bls12_377() {
+    use ark_bls12_377::Bls12_377;
+    use crate::transcript::Transcript;
+
+    let game = Game::new(BoardGeometry::square(3), vec![2, 1], CommitmentBackend::Poseidon).unwrap().with_curve(Curve::Bls12_377).unwrap();
+    assert!(matches!(Game::new(BoardGeometry::square(3), vec![2, 1], CommitmentBackend::Pedersen).unwrap().with_curve(Curve::Bls12_377), Err(GameError::BadInput(_))));
+    let keys = GameKeys::<Groth16<Bls12_377>>::generate(game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut OsRng).unwrap();
+    let mut transcript = Transcript::<Groth16<Bls12_377>>::new(&game, keys.fingerprint().unwrap());
+
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+    a.place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }]).unwrap();
+    b.place_fleet(&[Ship { origin: 1, orientation: Orientation::Vertical }, Ship { origin: 6, orientation: Orientation::Horizontal }]).unwrap();
+    let (commitment_a, commitment_b) = (a.commit(&mut OsRng).unwrap(), b.commit(&mut OsRng).unwrap());
+    transcript.record_commitment(0, &commitment_a);
+    transcript.record_commitment(1, &commitment_b);
+    a.receive_commitment(commitment_b).unwrap();
+    b.receive_commitment(commitment_a).unwrap();
+
+    // bls12-381 keys can't prove or verify a game on bls12-377
+    let (_, other) = test_game();
+    assert!(matches!(a.prove_board(&other, &mut OsRng), Err(GameError::BadInput(_))));
+
+    let (proof_a, proof_b) = (a.prove_board(&keys, &mut OsRng).unwrap(), b.prove_board(&keys, &mut OsRng).unwrap());
+    transcript.record_board_proof(0, &proof_a);
+    transcript.record_board_proof(1, &proof_b);
+    b.verify_board(&keys, proof_a).unwrap();
+    a.verify_board(&keys, proof_b).unwrap();
+
+    // a lie is still caught on the other curve
+    let (hit, proof) = b.answer(&keys, 1, &mut OsRng).unwrap();
+    assert!(hit);
+    assert!(matches!(a.verify_answer(&keys, 1, false, proof.clone()), Err(GameError::ProofFailure(_))));
+    a.verify_answer(&keys, 1, hit, proof.clone()).unwrap();
+    transcript.record_turn(0, 1, hit, &proof);
+
+    // and the transcript comes back on its curve only
+    let mut bytes = vec![];
+    transcript.write(&mut bytes).unwrap();
+    assert_eq!(crate::transcript::read_system(bytes.as_slice()).unwrap(), (Curve::Bls12_377, System::Groth16));
+    assert!(Transcript::<Groth16<Bls12_381>>::read(bytes.as_slice()).is_err());
+    let read = Transcript::<Groth16<Bls12_377>>::read(bytes.as_slice()).unwrap();
+    assert_eq!(read.game().unwrap(), game);
+    assert_eq!(read.verify(&keys).unwrap(), crate::transcript::Outcome::Unfinished);
+}
+
+#[test]
+fn test_sunk_announcements() {
+    let (game, keys) = test_game();
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+    a.place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }]).unwrap();
+    b.place_fleet(&[Ship { origin: 1, orientation: Orientation::Vertical }, Ship { origin: 6, orientation: Orientation::Horizontal }]).unwrap();
+    assert_eq!(b.board(), &[0, 1, 0, 0, 1, 0, 2, 0, 0]);
+    a.receive_commitment(b.commit(&mut OsRng).unwrap()).unwrap();
+    b.receive_commitment(a.commit(&mut OsRng).unwrap()).unwrap();
+    a.verify_board(&keys, b.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+
+    // a miss and a hit on a ship that's still afloat sink nothing
+    for target in [0, 1] {
+        let (hit, proof) = b.answer(&keys, target, &mut OsRng).unwrap();
+        a.verify_answer(&keys, target, hit, proof).unwrap();
+        assert!(b.announce_sunk(&keys, target, &mut OsRng).unwrap().is_none());
+    }
+
+    let (hit, proof) = b.answer(&keys, 4, &mut OsRng).unwrap();
+    a.verify_answer(&keys, 4, hit, proof).unwrap();
+    let sunk = b.announce_sunk(&keys, 4, &mut OsRng).unwrap().unwrap();
+    assert_eq!(sunk.ship, 0);
+
+    // claiming a different ship, or the same ship twice, is rejected
+    let wrong = Sunk { ship: 1, proof: sunk.proof.clone() };
+    assert!(matches!(a.verify_sunk(&keys, 4, wrong), Err(GameError::ProofFailure(_))));
+    assert!(matches!(a.verify_sunk(&keys, 0, sunk.clone()), Err(GameError::BadInput(_))));
+    assert_eq!(a.verify_sunk(&keys, 4, sunk.clone()).unwrap(), 2);
+    assert!(matches!(a.verify_sunk(&keys, 4, sunk), Err(GameError::BadInput(_))));
+    assert_eq!(a.sunk_ships(), &[0]);
+
+    // one ship left, and a claim of none is caught
+    let (remaining, proof) = b.prove_remaining(&keys, &mut OsRng).unwrap();
+    assert_eq!(remaining, 1);
+    assert!(matches!(a.verify_remaining(&keys, 0, proof.clone()), Err(GameError::ProofFailure(_))));
+    a.verify_remaining(&keys, remaining, proof.clone()).unwrap();
+    assert_eq!(a.proven_remaining(), Some(1));
+
+    // and a valid proof that one remains doesn't confirm a victory
+    assert!(matches!(a.confirm_victory(&keys, remaining, proof), Err(GameError::ProofFailure(_))));
+
+    // sinking the last ship is proven to leave none
+    let (hit, proof) = b.answer(&keys, 6, &mut OsRng).unwrap();
+    a.verify_answer(&keys, 6, hit, proof).unwrap();
+    let (remaining, proof) = b.prove_remaining(&keys, &mut OsRng).unwrap();
+    assert_eq!(remaining, 0);
+    a.confirm_victory(&keys, remaining, proof).unwrap();
+    assert!(a.has_won().unwrap());
+}
+
+#[test]
+fn test_bad_input_is_an_error() {
+    let (game, keys) = test_game();
+    let ships = [Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }];
+    let board = fleet_board(&ships, game.fleet(), game.geometry()).unwrap();
+    let secret = generate_secret(&mut OsRng);
+    let randomness = derive_randomness(&secret).unwrap();
+    let commitment = generate_commitment(game.curve(), game.backend(), &board, &randomness).unwrap();
+
+    // randomness or a secret of the wrong length, too few ships for the fleet, a malformed commitment
+    assert!(matches!(generate_commitment(game.curve(), game.backend(), &board, &randomness[1..]), Err(GameError::BadInput(_))));
+    assert!(matches!(derive_randomness(&secret[1..]), Err(GameError::BadInput(_))));
+    assert!(matches!(
+        generate_proof::<Groth16<Bls12_381>, _>(game.backend(), &keys.board_pk, &board, &ships[..1], &secret, &commitment, game.fleet(), game.geometry(), game.rule(), &mut OsRng),
+        Err(GameError::Proving(_))
+    ));
+
+    // the board proof only holds for the secret the commitment randomness came from
+    let forged = generate_proof::<Groth16<Bls12_381>, _>(game.backend(), &keys.board_pk, &board, &ships, &randomness, &commitment, game.fleet(), game.geometry(), game.rule(), &mut OsRng).unwrap();
+    assert!(!verify_initial_proof::<Groth16<Bls12_381>>(game.backend(), &keys.board_vk, &commitment, game.fleet(), game.geometry(), game.rule(), forged).unwrap());
+    let proof = generate_proof::<Groth16<Bls12_381>, _>(game.backend(), &keys.board_pk, &board, &ships, &secret, &commitment, game.fleet(), game.geometry(), game.rule(), &mut OsRng).unwrap();
+    assert!(matches!(
+        verify_initial_proof::<Groth16<Bls12_381>>(game.backend(), &keys.board_vk, &commitment[1..], game.fleet(), game.geometry(), game.rule(), proof.clone()),
+        Err(GameError::CommitmentMismatch)
+    ));
+
+    // a key that doesn't fit the public inputs is our problem, not a cheating opponent
+    assert!(matches!(
+        verify_initial_proof::<Groth16<Bls12_381>>(game.backend(), &keys.board_vk, &commitment, &[2], game.geometry(), game.rule(), proof),
+        Err(GameError::Proving(_))
+    ));
+}
+
+#[test]
+fn test_seeded_rng_replays() {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    let (game, keys) = test_game();
+    let play = |seed: u64| {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let mut a = game.new_player();
+        a.place_fleet(&[
+            Ship { origin: 0, orientation: Orientation::Horizontal },
+            Ship { origin: 8, orientation: Orientation::Horizontal },
+        ]).unwrap();
+        let commitment = a.commit(&mut rng).unwrap();
+        let board_proof = a.prove_board(&keys, &mut rng).unwrap();
+        let (_, shot_proof) = a.answer(&keys, 4, &mut rng).unwrap();
+        (commitment, board_proof, shot_proof)
+    };
+
+    // the same seed gives the same commitment and proofs, another seed doesn't
+    let first = play(1);
+    assert_eq!(play(1), first);
+    let other = play(2);
+    assert_ne!(other.0, first.0);
+    assert_ne!(other.1, first.1);
+}
@@ -0,0 +1,482 @@
+use crate::common::*;
+use crate::commitment::{derive_randomness, CircuitField, CommitmentBackend, SECRET_LEN};
+use crate::constraints::{BoardVerifier, RemainingVerifier, SalvoVerifier, ShotVerifier, SunkVerifier};
+use crate::envelope::Circuit;
+use crate::error::GameError;
+use ark_bls12_381::Bls12_381;
+use crate::proof_system::{Groth16, ProofSystem};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use blake2::{Blake2s, Digest};
+use rand::{CryptoRng, RngCore};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/**
+*   keys for the board, shot, sunk ship and remaining ships circuits of one game shape,
+*   and for the salvo circuit if the game is played in salvos.
+*
+*   `zk-battleships setup` generates the keys once per (board geometry, fleet, commitment,
+*   salvo, curve, proof system) and writes them to KEY_DIR. both players then load the same key files, so neither
+*   player ever proves with parameters they generated themselves or verifies against
+*   a key handed to them by their opponent. plonk keys are indexed from the one srs that
+*   `zk-battleships srs` writes, so only that needs trusting, not every game shape's keys.
+*/
+pub const KEY_DIR: &str = "keys";
+
+pub struct GameKeys<P: ProofSystem = Groth16<Bls12_381>> {
+    pub board_pk: P::ProvingKey,
+    pub board_vk: P::PreparedVerifyingKey,
+    pub shot_pk: P::ProvingKey,
+    pub shot_vk: P::PreparedVerifyingKey,
+    pub sunk_pk: P::ProvingKey,
+    pub sunk_vk: P::PreparedVerifyingKey,
+    pub remaining_pk: P::ProvingKey,
+    pub remaining_vk: P::PreparedVerifyingKey,
+    /// None unless the keys were made for a game played in salvos
+    pub salvo_pk: Option<P::ProvingKey>,
+    pub salvo_vk: Option<P::PreparedVerifyingKey>,
+}
+
+impl<P: GameSystem<Parameters = ()>> GameKeys<P> {
+    /**
+    *   generates fresh keys for this game shape without writing them anywhere, for a
+    *   system with a setup per circuit
+    */
+    pub fn generate<R: RngCore + CryptoRng>(geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, salvo: Salvo, rng: &mut R)
+        -> Result<GameKeys<P>, GameError> {
+        GameKeys::index(&(), geometry, fleet, backend, salvo, rng)
+    }
+}
+
+impl<P: GameSystem> GameKeys<P> {
+    /**
+    *   makes the keys for this game shape from the system's parameters without writing them
+    *   anywhere. with a universal setup the same parameters serve every game shape
+    */
+    pub fn index<R: RngCore + CryptoRng>(parameters: &P::Parameters, geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, salvo: Salvo, rng: &mut R)
+        -> Result<GameKeys<P>, GameError> {
+        let curve = P::Field::CURVE;
+        let board = P::index(parameters, board_circuit(geometry, fleet, backend, curve)?, rng)?.0;
+        let shot = P::index(parameters, shot_circuit(geometry, backend, curve)?, rng)?.0;
+        let sunk = P::index(parameters, sunk_circuit(geometry, backend, curve)?, rng)?.0;
+        let remaining = P::index(parameters, remaining_circuit(geometry, fleet, backend, curve)?, rng)?.0;
+        let salvo = match salvo {
+            Salvo::Off => None,
+            salvo => Some(P::index(parameters, salvo_circuit(geometry, salvo.max_shots(fleet.len()), backend, curve)?, rng)?.0),
+        };
+        Ok(GameKeys::from_proving_keys(board, shot, sunk, remaining, salvo))
+    }
+
+    /// the keys made elsewhere for each circuit, such as by a ceremony
+    pub fn from_proving_keys(board: P::ProvingKey, shot: P::ProvingKey, sunk: P::ProvingKey, remaining: P::ProvingKey, salvo: Option<P::ProvingKey>) -> GameKeys<P> {
+        GameKeys {
+            board_vk: P::prepare(P::verifying_key(&board)),
+            board_pk: board,
+            shot_vk: P::prepare(P::verifying_key(&shot)),
+            shot_pk: shot,
+            sunk_vk: P::prepare(P::verifying_key(&sunk)),
+            sunk_pk: sunk,
+            remaining_vk: P::prepare(P::verifying_key(&remaining)),
+            remaining_pk: remaining,
+            salvo_vk: salvo.as_ref().map(|salvo| P::prepare(P::verifying_key(salvo))),
+            salvo_pk: salvo,
+        }
+    }
+
+    /**
+    *   loads the keys written by setup_keys for this game shape
+    */
+    pub fn load(dir: &Path, geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, salvo: Salvo) -> Result<GameKeys<P>, GameError> {
+        let board = board_key_name::<P>(geometry, fleet, backend);
+        let shot = shot_key_name::<P>(geometry, backend);
+        let sunk = sunk_key_name::<P>(geometry, backend);
+        let remaining = remaining_key_name::<P>(geometry, fleet, backend);
+        let (salvo_pk, salvo_vk) = match salvo {
+            Salvo::Off => (None, None),
+            salvo => {
+                let name = salvo_key_name::<P>(geometry, salvo.max_shots(fleet.len()), backend);
+                (Some(load_proving_key::<P>(&dir.join(format!("{}.pk", name)))?), Some(P::prepare(&load_verifying_key::<P>(&dir.join(format!("{}.vk", name)))?)))
+            }
+        };
+
+        Ok(GameKeys {
+            board_pk: load_proving_key::<P>(&dir.join(format!("{}.pk", board)))?,
+            board_vk: P::prepare(&load_verifying_key::<P>(&dir.join(format!("{}.vk", board)))?),
+            shot_pk: load_proving_key::<P>(&dir.join(format!("{}.pk", shot)))?,
+            shot_vk: P::prepare(&load_verifying_key::<P>(&dir.join(format!("{}.vk", shot)))?),
+            sunk_pk: load_proving_key::<P>(&dir.join(format!("{}.pk", sunk)))?,
+            sunk_vk: P::prepare(&load_verifying_key::<P>(&dir.join(format!("{}.vk", sunk)))?),
+            remaining_pk: load_proving_key::<P>(&dir.join(format!("{}.pk", remaining)))?,
+            remaining_vk: P::prepare(&load_verifying_key::<P>(&dir.join(format!("{}.vk", remaining)))?),
+            salvo_pk,
+            salvo_vk,
+        })
+    }
+
+    /**
+    *   the salvo proving and verifying keys, an error if these keys weren't made for salvos
+    */
+    pub fn salvo(&self) -> Result<(&P::ProvingKey, &P::PreparedVerifyingKey), GameError> {
+        match (&self.salvo_pk, &self.salvo_vk) {
+            (Some(pk), Some(vk)) => Ok((pk, vk)),
+            _ => Err(GameError::BadInput("the keys weren't set up for salvos".to_string())),
+        }
+    }
+
+    /**
+    *   the verifying key of one circuit, an error for the salvo circuit if these keys
+    *   weren't made for salvos
+    */
+    pub fn verifying_key(&self, circuit: Circuit) -> Result<&P::VerifyingKey, GameError> {
+        Ok(P::unprepared(match circuit {
+            Circuit::Board => &self.board_vk,
+            Circuit::Shot => &self.shot_vk,
+            Circuit::Sunk => &self.sunk_vk,
+            Circuit::Remaining => &self.remaining_vk,
+            Circuit::Salvo => self.salvo()?.1,
+        }))
+    }
+
+    /**
+    *   blake2s hash of the board, shot, sunk, remaining and any salvo verifying keys. players exchange it before placing
+    *   ships, since each side only accepts proofs made with the keys it verifies against
+    */
+    pub fn fingerprint(&self) -> Result<Vec<u8>, GameError> {
+        let mut bytes = vec![];
+        P::unprepared(&self.board_vk).serialize(&mut bytes)?;
+        P::unprepared(&self.shot_vk).serialize(&mut bytes)?;
+        P::unprepared(&self.sunk_vk).serialize(&mut bytes)?;
+        P::unprepared(&self.remaining_vk).serialize(&mut bytes)?;
+        if let Some(salvo) = &self.salvo_vk {
+            P::unprepared(salvo).serialize(&mut bytes)?;
+        }
+        Ok(Blake2s::digest(&bytes).to_vec())
+    }
+}
+
+/// appended to every key name so keys for other curves and systems sit beside the groth16
+/// bls12-381 ones, which keep the names they had before either could be chosen
+fn key_suffix<P: GameSystem>() -> String {
+    let curve = match P::Field::CURVE {
+        Curve::Bls12_381 => String::new(),
+        curve => format!("_{}", curve.name()),
+    };
+    match P::SYSTEM {
+        System::Groth16 => curve,
+        system => format!("{}_{}", curve, system.name()),
+    }
+}
+
+/// file name (without extension) of the board circuit keys, e.g. board_10x10_5-4-3-3-2_poseidon
+fn board_key_name<P: GameSystem>(geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend) -> String {
+    let fleet: Vec<String> = fleet.iter().map(|length| length.to_string()).collect();
+    format!("board_{}_{}_{}{}", geometry, fleet.join("-"), backend.name(), key_suffix::<P>())
+}
+
+/// file name (without extension) of the shot circuit keys, e.g. shot_10x10_poseidon_bls12-377_plonk
+fn shot_key_name<P: GameSystem>(geometry: BoardGeometry, backend: CommitmentBackend) -> String {
+    format!("shot_{}_{}{}", geometry, backend.name(), key_suffix::<P>())
+}
+
+/// file name (without extension) of the sunk ship circuit keys, e.g. sunk_10x10_poseidon
+fn sunk_key_name<P: GameSystem>(geometry: BoardGeometry, backend: CommitmentBackend) -> String {
+    format!("sunk_{}_{}{}", geometry, backend.name(), key_suffix::<P>())
+}
+
+/// file name (without extension) of the remaining ships circuit keys, which only depend on
+/// the number of ships, e.g. remaining_10x10_5_poseidon
+fn remaining_key_name<P: GameSystem>(geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend) -> String {
+    format!("remaining_{}_{}_{}{}", geometry, fleet.len(), backend.name(), key_suffix::<P>())
+}
+
+/// file name (without extension) of the salvo circuit keys, which depend on the most shots
+/// a salvo has, e.g. salvo_10x10_5_poseidon
+fn salvo_key_name<P: GameSystem>(geometry: BoardGeometry, shots: usize, backend: CommitmentBackend) -> String {
+    format!("salvo_{}_{}_{}{}", geometry, shots, backend.name(), key_suffix::<P>())
+}
+
+/**
+*   the circuit shapes only depend on the public game settings, so the parameters are
+*   generated from placeholder witnesses. none of the placeholder values end up in the keys.
+*   the adjacency rule is an input rather than part of the shape, so one board key serves
+*   every rule
+*/
+pub(crate) fn board_circuit(geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, curve: Curve) -> Result<BoardVerifier, GameError> {
+    let board = vec![0u8; geometry.tiles()];
+    let secret = vec![0u8; SECRET_LEN];
+    Ok(BoardVerifier {
+        fleet: fleet.to_vec(),
+        geometry,
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: backend.commit(curve, &board, &derive_randomness(&secret)?)?,
+        backend,
+
+        ships: Some(vec![Ship { origin: 0, orientation: Orientation::Horizontal }; fleet.len()]),
+        board: Some(board),
+        secret: Some(secret),
+    })
+}
+
+pub(crate) fn shot_circuit(geometry: BoardGeometry, backend: CommitmentBackend, curve: Curve) -> Result<ShotVerifier, GameError> {
+    let board = vec![0u8; geometry.tiles()];
+    let randomness = vec![0u8; 32];
+    Ok(ShotVerifier {
+        geometry,
+        target: 0,
+        hit: 0,
+        commitment: backend.commit(curve, &board, &randomness)?,
+        backend,
+
+        board: Some(board),
+        rng_in: Some(randomness),
+    })
+}
+
+pub(crate) fn sunk_circuit(geometry: BoardGeometry, backend: CommitmentBackend, curve: Curve) -> Result<SunkVerifier, GameError> {
+    let board = vec![0u8; geometry.tiles()];
+    let randomness = vec![0u8; 32];
+    Ok(SunkVerifier {
+        geometry,
+        target: 0,
+        ship: 0,
+        hits: vec![false; geometry.tiles()],
+        commitment: backend.commit(curve, &board, &randomness)?,
+        backend,
+
+        board: Some(board),
+        rng_in: Some(randomness),
+    })
+}
+
+pub(crate) fn remaining_circuit(geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, curve: Curve) -> Result<RemainingVerifier, GameError> {
+    let board = vec![0u8; geometry.tiles()];
+    let randomness = vec![0u8; 32];
+    Ok(RemainingVerifier {
+        geometry,
+        ships: fleet.len() as u8,
+        hits: vec![false; geometry.tiles()],
+        remaining: 0,
+        commitment: backend.commit(curve, &board, &randomness)?,
+        backend,
+
+        board: Some(board),
+        rng_in: Some(randomness),
+    })
+}
+
+pub(crate) fn salvo_circuit(geometry: BoardGeometry, shots: usize, backend: CommitmentBackend, curve: Curve) -> Result<SalvoVerifier, GameError> {
+    let board = vec![0u8; geometry.tiles()];
+    let randomness = vec![0u8; 32];
+    Ok(SalvoVerifier {
+        geometry,
+        targets: vec![0; shots],
+        hits: vec![0; shots],
+        commitment: backend.commit(curve, &board, &randomness)?,
+        backend,
+
+        board: Some(board),
+        rng_in: Some(randomness),
+    })
+}
+
+/**
+*   makes the board, shot, sunk ship, remaining ships and any salvo circuit keys for a game shape from the
+*   system's parameters and writes them to dir, returning the paths written
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn setup_keys<P: GameSystem, R: RngCore + CryptoRng>(dir: &Path, parameters: &P::Parameters, geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, salvo: Salvo, rng: &mut R)
+    -> Result<Vec<PathBuf>, GameError> {
+    let keys = GameKeys::<P>::index(parameters, geometry, fleet, backend, salvo, rng)?;
+    write_game_keys(dir, geometry, fleet, backend, salvo, &keys)
+}
+
+/**
+*   writes keys for a game shape to dir under the names `GameKeys::load` looks for,
+*   returning the paths written
+*/
+pub fn write_game_keys<P: GameSystem>(dir: &Path, geometry: BoardGeometry, fleet: &[u8], backend: CommitmentBackend, salvo: Salvo, keys: &GameKeys<P>)
+    -> Result<Vec<PathBuf>, GameError> {
+    fs::create_dir_all(dir)?;
+    let mut all = vec![(board_key_name::<P>(geometry, fleet, backend), &keys.board_pk), (shot_key_name::<P>(geometry, backend), &keys.shot_pk),
+        (sunk_key_name::<P>(geometry, backend), &keys.sunk_pk), (remaining_key_name::<P>(geometry, fleet, backend), &keys.remaining_pk)];
+    match (salvo, &keys.salvo_pk) {
+        (Salvo::Off, _) => {}
+        (salvo, Some(params)) => all.push((salvo_key_name::<P>(geometry, salvo.max_shots(fleet.len()), backend), params)),
+        (_, None) => return Err(GameError::BadInput("the keys weren't set up for salvos".to_string())),
+    }
+
+    let mut written = Vec::new();
+    for (name, params) in all {
+        written.append(&mut write_keys::<P>(dir, &name, params)?);
+    }
+    Ok(written)
+}
+
+fn write_keys<P: ProofSystem>(dir: &Path, name: &str, params: &P::ProvingKey) -> Result<Vec<PathBuf>, GameError> {
+    let pk_path = dir.join(format!("{}.pk", name));
+    let vk_path = dir.join(format!("{}.vk", name));
+
+    // proving keys are large, so they're stored uncompressed to keep loading them quick
+    params.serialize_uncompressed(BufWriter::new(File::create(&pk_path)?))?;
+    P::verifying_key(params).serialize(BufWriter::new(File::create(&vk_path)?))?;
+    Ok(vec![pk_path, vk_path])
+}
+
+/**
+*   loads a proving key. it is only ever used to create our own proofs, so the
+*   (slow) curve point checks are skipped
+*/
+pub fn load_proving_key<P: ProofSystem>(path: &Path) -> Result<P::ProvingKey, GameError> {
+    Ok(P::ProvingKey::deserialize_unchecked(BufReader::new(File::open(path)?))?)
+}
+
+/**
+*   loads a verifying key, checking every point is valid
+*/
+pub fn load_verifying_key<P: ProofSystem>(path: &Path) -> Result<P::VerifyingKey, GameError> {
+    Ok(P::VerifyingKey::deserialize(BufReader::new(File::open(path)?))?)
+}
+
+#[cfg(test)]
+fn test_key_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("zk-battleships-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn test_keys_round_trip() {
+    use ark_groth16::{create_random_proof, verify_proof};
+    use ark_bls12_381::Fr;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let backend = CommitmentBackend::Poseidon;
+    let fleet = vec![2, 1];
+    let geometry = BoardGeometry::square(3);
+    let dir = test_key_dir("round-trip");
+
+    let written = setup_keys::<Groth16<Bls12_381>, _>(&dir, &(), geometry, &fleet, backend, Salvo::Off, &mut rng).unwrap();
+    assert_eq!(written.len(), 8);
+    let keys = GameKeys::<Groth16<Bls12_381>>::load(&dir, geometry, &fleet, backend, Salvo::Off).unwrap();
+
+    // a real board proven with the stored proving key verifies against the stored verifying key
+    let ships = vec![
+        Ship { origin: 3, orientation: Orientation::Horizontal },
+        Ship { origin: 8, orientation: Orientation::Horizontal },
+    ];
+    let board = fleet_board(&ships, &fleet, geometry).unwrap();
+    let secret = vec![7u8; SECRET_LEN];
+    let randomness = derive_randomness(&secret).unwrap().to_vec();
+    let commitment = backend.commit(Curve::Bls12_381, &board, &randomness).unwrap();
+
+    let circuit = BoardVerifier {
+        fleet: fleet.clone(),
+        geometry,
+        rule: AdjacencyRule::AnythingGoes,
+        commitment: commitment.clone(),
+        backend,
+
+        board: Some(board.clone()),
+        ships: Some(ships),
+        secret: Some(secret),
+    };
+    let proof = create_random_proof(circuit, &keys.board_pk, &mut rng).unwrap();
+
+    let mut inputs: Vec<Fr> = fleet.iter().map(|length| Fr::from(*length)).collect();
+    inputs.push(Fr::from(geometry.width));
+    inputs.push(Fr::from(geometry.height));
+    inputs.push(Fr::from(AdjacencyRule::AnythingGoes.to_u8()));
+    inputs.append(&mut backend.to_inputs(&commitment).unwrap());
+    assert!(verify_proof(&keys.board_vk, &proof, &inputs).unwrap());
+
+    // so does a shot at the board
+    let circuit = ShotVerifier {
+        geometry,
+        target: 4,
+        hit: 1,
+        commitment: commitment.clone(),
+        backend,
+
+        board: Some(board),
+        rng_in: Some(randomness),
+    };
+    let shot_proof = create_random_proof(circuit, &keys.shot_pk, &mut rng).unwrap();
+
+    let mut shot_inputs = vec![Fr::from(geometry.width), Fr::from(geometry.height), Fr::from(4u8), Fr::from(1u8)];
+    shot_inputs.append(&mut backend.to_inputs(&commitment).unwrap());
+    assert!(verify_proof(&keys.shot_vk, &shot_proof, &shot_inputs).unwrap());
+
+    // keys from a different setup don't accept the proof
+    let other_dir = test_key_dir("round-trip-other");
+    setup_keys::<Groth16<Bls12_381>, _>(&other_dir, &(), geometry, &fleet, backend, Salvo::Off, &mut rng).unwrap();
+    let other_keys = GameKeys::<Groth16<Bls12_381>>::load(&other_dir, geometry, &fleet, backend, Salvo::Off).unwrap();
+    assert!(!verify_proof(&other_keys.board_vk, &proof, &inputs).unwrap());
+
+    // and the two setups are told apart by their fingerprints
+    assert_eq!(keys.fingerprint().unwrap(), GameKeys::<Groth16<Bls12_381>>::load(&dir, geometry, &fleet, backend, Salvo::Off).unwrap().fingerprint().unwrap());
+    assert_ne!(keys.fingerprint().unwrap(), other_keys.fingerprint().unwrap());
+
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_dir_all(other_dir).unwrap();
+}
+
+#[test]
+fn test_missing_keys() {
+    let dir = test_key_dir("missing");
+    assert!(GameKeys::<Groth16<Bls12_381>>::load(&dir, BoardGeometry::square(3), &[2, 1], CommitmentBackend::Poseidon, Salvo::Off).is_err());
+}
+
+#[test]
+fn test_salvo_keys() {
+    use rand::rngs::OsRng;
+
+    let backend = CommitmentBackend::Poseidon;
+    let fleet = vec![2, 1];
+    let geometry = BoardGeometry::square(3);
+    let dir = test_key_dir("salvo");
+
+    // a salvo game gets the salvo keys on top of the usual ones
+    let written = setup_keys::<Groth16<Bls12_381>, _>(&dir, &(), geometry, &fleet, backend, Salvo::Ships, &mut OsRng).unwrap();
+    assert_eq!(written.len(), 10);
+    assert!(dir.join("salvo_3x3_2_poseidon.vk").exists());
+    let keys = GameKeys::<Groth16<Bls12_381>>::load(&dir, geometry, &fleet, backend, Salvo::Ships).unwrap();
+    assert!(keys.salvo_pk.is_some() && keys.salvo_vk.is_some());
+
+    // salvos of another size need their own keys, while the classic game needs none
+    assert!(GameKeys::<Groth16<Bls12_381>>::load(&dir, geometry, &fleet, backend, Salvo::Fixed(3)).is_err());
+    let classic = GameKeys::<Groth16<Bls12_381>>::load(&dir, geometry, &fleet, backend, Salvo::Off).unwrap();
+    assert!(classic.salvo_vk.is_none());
+    assert_ne!(classic.fingerprint().unwrap(), keys.fingerprint().unwrap());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_plonk_keys_from_one_srs() {
+    use crate::game::Game;
+    use crate::plonk::Plonk;
+    use rand::rngs::OsRng;
+
+    // one set of universal parameters serves boards of any size it covers
+    let srs = Plonk::<Bls12_381>::universal_setup(1 << 17, &mut OsRng).unwrap();
+    let dir = test_key_dir("plonk");
+    for geometry in [BoardGeometry::square(3), BoardGeometry::new(4, 5)] {
+        let game = Game::new(geometry, vec![2, 1], CommitmentBackend::Poseidon).unwrap().with_system(System::Plonk);
+        let written = setup_keys::<Plonk<Bls12_381>, _>(&dir, &srs, geometry, game.fleet(), game.backend(), game.salvo(), &mut OsRng).unwrap();
+        assert!(written[0].ends_with(format!("board_{}_2-1_poseidon_plonk.pk", geometry)));
+        let keys = GameKeys::<Plonk<Bls12_381>>::load(&dir, geometry, game.fleet(), game.backend(), game.salvo()).unwrap();
+
+        let mut a = game.new_player();
+        let mut b = game.new_player();
+        a.place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: geometry.tiles() as u16 - 1, orientation: Orientation::Horizontal }]).unwrap();
+        b.receive_commitment(a.commit(&mut OsRng).unwrap()).unwrap();
+        b.verify_board(&keys, a.prove_board(&keys, &mut OsRng).unwrap()).unwrap();
+
+        // the keys only fit the plonk game they were indexed for
+        let mut groth16 = Game::new(geometry, vec![2, 1], CommitmentBackend::Poseidon).unwrap().new_player();
+        groth16.place_fleet(a.ships()).unwrap();
+        groth16.commit(&mut OsRng).unwrap();
+        assert!(matches!(groth16.prove_board(&keys, &mut OsRng), Err(GameError::BadInput(_))));
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
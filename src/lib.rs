@@ -0,0 +1,50 @@
+/*!
+*   battleships with zero knowledge proofs of each player's board and of every shot.
+*
+*   `Game` holds the public settings both players agree on and `PlayerState` one
+*   player's side of the game: placing the fleet, committing to and proving the board,
+*   firing, answering shots and checking for a winner. a `Transcript` records the public
+*   side of a game so anyone can replay it later, a `GameState` saves a game in progress
+*   to be resumed, an `Envelope` carries a proof, verifying key or commitment out of the
+*   process, a `Bot` plays either side and a `Ceremony` makes the game's keys without
+*   trusting whoever runs it. proofs are made with groth16 or plonk on bls12-381 or
+*   bls12-377, whichever the game was set up with (see `common::GameSystem`), and with the `recursion` feature a
+*   won game on bls12-377 folds into one bw6-761 proof. the `zk-battleships` binary is a
+*   console front end over this API.
+*/
+
+extern crate ark_r1cs_std;
+extern crate ark_relations;
+extern crate ark_crypto_primitives;
+extern crate ark_std;
+extern crate rand;
+extern crate ark_bls12_381;
+extern crate ark_groth16;
+extern crate ark_ff;
+
+pub mod bot;
+pub mod ceremony;
+pub mod common;
+pub mod commitment;
+pub mod constraints;
+pub mod envelope;
+pub mod error;
+pub mod game;
+pub mod keys;
+pub mod net;
+pub mod plonk;
+pub mod proof_system;
+#[cfg(feature = "recursion")]
+pub mod recursion;
+pub mod save;
+pub mod simulate;
+pub mod transcript;
+
+pub use bot::{Bot, Difficulty};
+pub use commitment::CommitmentBackend;
+pub use envelope::Envelope;
+pub use error::GameError;
+pub use game::{Game, PlayerState, Reveal};
+pub use keys::GameKeys;
+pub use save::GameState;
+pub use transcript::{Outcome, Transcript};
@@ -1,253 +1,1163 @@
-extern crate ark_r1cs_std;
-extern crate ark_relations;
-extern crate ark_crypto_primitives;
-extern crate ark_std;
-extern crate rand;
-extern crate ark_bls12_381;
-extern crate ark_groth16;
-extern crate ark_ff;
+use ark_bls12_377::Bls12_377;
+use ark_bls12_381::Bls12_381;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::net::{TcpListener, TcpStream};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zk_battleships::bot::{random_fleet, Bot, Difficulty};
+use zk_battleships::ceremony::{self, Ceremony};
+use zk_battleships::envelope::{Circuit, Payload};
+use zk_battleships::common::*;
+use zk_battleships::commitment::CircuitField;
+use zk_battleships::keys::{self, KEY_DIR};
+use zk_battleships::net::{read_message, write_message, Message};
+use zk_battleships::simulate::simulate;
+use zk_battleships::game::Sunk;
+use zk_battleships::plonk::{Plonk, Srs};
+use zk_battleships::proof_system::{Groth16, ProofSystem};
+use zk_battleships::save::{saved_system, SAVE_DIR};
+use zk_battleships::transcript::{self, TRANSCRIPT_DIR};
+use zk_battleships::{CommitmentBackend, Envelope, Game, GameError, GameKeys, GameState, Outcome, PlayerState, Transcript};
 
-use ark_crypto_primitives::commitment::blake2s::Commitment;
-use rand::{rngs::OsRng, Rng};
+const DEFAULT_PORT: &str = "7878";
 
-pub mod common;
-use ark_crypto_primitives::CommitmentScheme;
-use constraints::BoardVerifier;
-use ark_bls12_381::{Bls12_381, Fr};
-use ark_groth16::{generate_random_parameters, prepare_verifying_key, create_random_proof, verify_proof, Proof, PreparedVerifyingKey};
-use ark_relations::r1cs::ToConstraintField;
-
-mod constraints;
+/**
+*   calls the function with the pairing engine of the curve, which is only known once the
+*   settings are chosen or read back from a file
+*/
+macro_rules! on_curve {
+    ($curve:expr, $f:ident($($arg:expr),*)) => {
+        match $curve {
+            Curve::Bls12_381 => $f::<Bls12_381>($($arg),*),
+            Curve::Bls12_377 => $f::<Bls12_377>($($arg),*),
+        }
+    };
+}
 
+/**
+*   calls the function with the proof system of the (curve, system) pair, which like the
+*   curve is only known once the settings are chosen or read back from a file
+*/
+macro_rules! on_system {
+    ($system:expr, $f:ident($($arg:expr),*)) => {
+        match $system {
+            (Curve::Bls12_381, System::Groth16) => $f::<Groth16<Bls12_381>>($($arg),*),
+            (Curve::Bls12_377, System::Groth16) => $f::<Groth16<Bls12_377>>($($arg),*),
+            (Curve::Bls12_381, System::Plonk) => $f::<Plonk<Bls12_381>>($($arg),*),
+            (Curve::Bls12_377, System::Plonk) => $f::<Plonk<Bls12_377>>($($arg),*),
+        }
+    };
+}
 
+/**
+*   zk-battleships setup        generate the proving/verifying keys for a game shape
+*   zk-battleships srs curve gates
+*                               make the plonk parameters for circuits of up to this many
+*                               gates on the curve, which setup indexes every plonk game's keys from
+*   zk-battleships              both players take turns at this console
+*   zk-battleships bot [level]  play against the computer, level is easy, medium or hard
+*   zk-battleships resume file  carry on with a game at this console from where it was saved
+*   zk-battleships host [port]  wait for an opponent to connect and pick the game settings
+*   zk-battleships join addr    connect to a host at addr (host:port)
+*   zk-battleships simulate games [--board WxH] [--fleet 5,4,3,3,2] [--commitment scheme]
+*                  [--rule anything-goes] [--salvo off] [--curve bls12-381] [--system groth16] [--bots hard,easy] [--proofs]
+*                               play bot against bot with no console input and report the results
+*   zk-battleships ceremony new|contribute|seal|verify|finish file
+*                               make every key for a game shape in a ceremony, so nobody
+*                               who took part alone can forge proofs
+*   zk-battleships verify-transcript file
+*                               replay a recorded game and check both players played honestly
+*   zk-battleships envelope export-keys|check-key circuit text|export-board file A|B|verify-board commitment proof [--base64]
+*                               print keys, commitments and board proofs as envelopes to paste
+*                               elsewhere, or check ones pasted from elsewhere against the local keys
+*   zk-battleships fold-setup turns
+*                               generate the bw6-761 keys for folding games of a shape on
+*                               bls12-377 that are won in this many shots
+*   zk-battleships fold-transcript file
+*                               fold a won game on bls12-377 into one bw6-761 proof, written
+*                               beside the transcript
+*   zk-battleships verify-fold file
+*                               check a folded game against the local fold keys
+*
+*   any of them but verify-transcript also takes --seed n, which draws every commitment's
+*   randomness, proof and bot decision from a rng seeded with n so a game can be replayed
+*   exactly. it is for testing only
+*
+*   every game played writes its transcript to TRANSCRIPT_DIR, and games at this console
+*   are saved to SAVE_DIR after every turn until they are over
+*/
 fn main() {
-    let (board_size, num_ships)  = setup();
-    println!("the board size is {} and the number of ships is {}", board_size, num_ships);
+    let mut args: Vec<String> = std::env::args().collect();
+    let result = take_seed(&mut args).and_then(|seed| run(&args, seed));
 
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
 
-    // initialise playing boards.
+fn run(args: &[String], seed: Option<u64>) -> Result<(), GameError> {
+    match args.get(1).map(|a| a.as_str()) {
+        Some("setup") => trusted_setup(seed),
+        Some("srs") => match (args.get(2).and_then(|curve| Curve::from_name(curve)), args.get(3).map(|gates| gates.parse())) {
+            (Some(curve), Some(Ok(gates))) => on_curve!(curve, universal_setup(gates, seed)),
+            _ => Err(GameError::BadInput("usage: zk-battleships srs <bls12-381|bls12-377> <gates>".to_string())),
+        },
+        Some("host") => host_game(args.get(2).map(|p| p.as_str()).unwrap_or(DEFAULT_PORT), seed),
+        Some("join") => match args.get(2) {
+            Some(addr) => join_game(addr, seed),
+            None => Err(GameError::BadInput("usage: zk-battleships join <host:port>".to_string())),
+        },
+        Some("bot") => match Difficulty::from_name(args.get(2).map(|l| l.as_str()).unwrap_or("hard")) {
+            Some(difficulty) => local_game(Some(Bot::new(difficulty)), seed),
+            None => Err(GameError::BadInput("usage: zk-battleships bot [easy|medium|hard]".to_string())),
+        },
+        Some("resume") => match args.get(2) {
+            Some(path) => on_system!(saved_system(Path::new(path))?, resume_game(Path::new(path), seed)),
+            None => Err(GameError::BadInput("usage: zk-battleships resume <file>".to_string())),
+        },
+        Some("simulate") => run_simulation(&args[2..], seed),
+        Some("ceremony") => run_ceremony(&args[2..], seed),
+        Some("envelope") => run_envelope(&args[2..]),
+        Some("verify-transcript") => match args.get(2) {
+            Some(path) => on_system!(transcript::read_system(BufReader::new(File::open(path)?))?, verify_transcript(Path::new(path))),
+            None => Err(GameError::BadInput("usage: zk-battleships verify-transcript <file>".to_string())),
+        },
+        #[cfg(feature = "recursion")]
+        Some("fold-setup") => match args.get(2).map(|turns| turns.parse()) {
+            Some(Ok(turns)) => fold_setup(turns, seed),
+            _ => Err(GameError::BadInput("usage: zk-battleships fold-setup <turns>".to_string())),
+        },
+        #[cfg(feature = "recursion")]
+        Some("fold-transcript") => match args.get(2) {
+            Some(path) => fold_transcript(Path::new(path), seed),
+            None => Err(GameError::BadInput("usage: zk-battleships fold-transcript <file>".to_string())),
+        },
+        #[cfg(feature = "recursion")]
+        Some("verify-fold") => match args.get(2) {
+            Some(path) => verify_fold(Path::new(path)),
+            None => Err(GameError::BadInput("usage: zk-battleships verify-fold <file>".to_string())),
+        },
+        _ => local_game(None, seed),
+    }
+}
 
-    // player's own boards. 1 = battleship
-    let mut board_a: Vec<u8> =  vec![0; board_size as usize];
-    let mut board_b: Vec<u8> =  vec![0; board_size as usize];
+/**
+*   removes `--seed n` from the arguments, returning n
+*/
+fn take_seed(args: &mut Vec<String>) -> Result<Option<u64>, GameError> {
+    let i = match args.iter().position(|a| a == "--seed") {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    let seed = parse(args.get(i + 1).ok_or_else(|| GameError::BadInput("--seed needs a number".to_string()))?)?;
+    args.drain(i..i + 2);
+    Ok(Some(seed))
+}
 
+/**
+*   the rng every commitment, proof and bot decision in this process is drawn from. with a
+*   seed it replays exactly, but anyone who learns the seed can recompute the commitment
+*   randomness and brute force the board from the commitment, so it is for testing only
+*/
+fn game_rng(seed: Option<u64>) -> ChaCha20Rng {
+    match seed {
+        Some(seed) => {
+            eprintln!("WARNING: seeded with --seed {}. every commitment and proof can be recomputed from the seed,", seed);
+            eprintln!("WARNING: so anyone who knows it can learn your board. only use --seed for testing.");
+            ChaCha20Rng::seed_from_u64(seed)
+        }
+        None => ChaCha20Rng::from_entropy(),
+    }
+}
 
-    // views. 0 = unknown, 1 = hit, 2 = miss
-    // player a's view of b's board
-    let mut board_a_b: Vec<u8> = vec![0; board_size as usize];
-    // player b's view of a's board
-    let mut board_b_a: Vec<u8> = vec![0; board_size as usize];
+/**
+*   makes the keys for the chosen game shape. groth16 keys are generated here, and whoever
+*   runs this learns the setup randomness and could forge proofs, so both players should
+*   trust them. plonk keys are indexed from the srs in KEY_DIR with nothing secret, so
+*   either player can make them and only the srs needs trusting
+*/
+fn trusted_setup(seed: Option<u64>) -> Result<(), GameError> {
+    let game = setup()?;
+    let mut rng = game_rng(seed);
+    println!("Generating keys, this may take a while..");
+    let written = match (game.curve(), game.system()) {
+        (Curve::Bls12_381, System::Groth16) => keys::setup_keys::<Groth16<Bls12_381>, _>(Path::new(KEY_DIR), &(), game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut rng)?,
+        (Curve::Bls12_377, System::Groth16) => keys::setup_keys::<Groth16<Bls12_377>, _>(Path::new(KEY_DIR), &(), game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut rng)?,
+        (Curve::Bls12_381, System::Plonk) => keys::setup_keys::<Plonk<Bls12_381>, _>(Path::new(KEY_DIR), &load_srs()?, game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut rng)?,
+        (Curve::Bls12_377, System::Plonk) => keys::setup_keys::<Plonk<Bls12_377>, _>(Path::new(KEY_DIR), &load_srs()?, game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut rng)?,
+    };
+    for path in written {
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
 
-    let (randomness_a, commitments_a, randomness_b, commitments_b) = initialise(board_size, num_ships, &mut board_a, &mut board_b);
+/// where `srs` writes the plonk parameters for the curve
+fn srs_path(curve: Curve) -> std::path::PathBuf {
+    Path::new(KEY_DIR).join(format!("srs_{}.srs", curve.name()))
+}
 
+/**
+*   makes the plonk parameters for circuits of up to `gates` gates on the curve and writes
+*   them to KEY_DIR. whoever runs this could forge proofs for every plonk game on the curve,
+*   so it should be someone both players trust, but it only has to be done once
+*/
+fn universal_setup<E: GameCurve>(gates: usize, seed: Option<u64>) -> Result<(), GameError> {
+    println!("Generating the parameters for circuits of up to {} gates, this may take a while..", gates);
+    let srs = Plonk::<E>::universal_setup(gates, &mut game_rng(seed))?;
+    std::fs::create_dir_all(KEY_DIR)?;
+    let path = srs_path(E::Fr::CURVE);
+    // like proving keys the parameters are large, so they're stored uncompressed
+    srs.serialize_uncompressed(std::io::BufWriter::new(File::create(&path)?))?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
 
-    println!("Generating proof for player a");
-    let (proof_a, pvk_a) = generate_proof(&board_a, &randomness_a, &commitments_a, num_ships, board_size);
-    println!("Verifying proof..");
-    let res = verify_initial_proof(&commitments_a, num_ships, board_size, proof_a, pvk_a);
-    if res {
-        println!("The proof was valid!");
-    } else {
-        println!("The proof was not valid.");
+fn load_srs<E: GameCurve>() -> Result<Srs<E>, GameError> {
+    let path = srs_path(E::Fr::CURVE);
+    let file = File::open(&path).inspect_err(|_| {
+        println!("Couldn't load the plonk parameters from {}. Run `zk-battleships srs {} <gates>` first.", path.display(), E::Fr::CURVE.name());
+    })?;
+    Ok(Srs::deserialize_uncompressed(BufReader::new(file))?)
+}
+
+/**
+*   loads the keys for the game shape, they must have been generated with `setup`
+*/
+fn load_keys<P: GameSystem>(game: &Game) -> Result<GameKeys<P>, GameError> {
+    GameKeys::load(Path::new(KEY_DIR), game.geometry(), game.fleet(), game.backend(), game.salvo()).inspect_err(|_| {
+        println!("Couldn't load the keys for this game from {}/. Run `zk-battleships setup` first.", KEY_DIR);
+    })
+}
+
+/**
+*   the opponent's proof failing to verify ends the game in our favour, anything
+*   else that goes wrong is passed on. returns true if the opponent was caught
+*/
+fn caught_cheating(result: Result<(), GameError>) -> Result<bool, GameError> {
+    match result {
+        Ok(()) => Ok(false),
+        Err(GameError::ProofFailure(e) | GameError::AuditFailure(e)) => {
+            println!("The opposing player tried to cheat ({})! You win.", e);
+            Ok(true)
+        }
+        Err(e) => Err(e),
     }
+}
 
-    println!("Generating proof for player b");
-    let (proof_b, pvk_b) = generate_proof(&board_b, &randomness_b, &commitments_b, num_ships, board_size);
-    println!("Verifying proof..");
-    let res2 = verify_initial_proof(&commitments_b, num_ships, board_size, proof_b, pvk_b);
-    if res2 {
-        println!("The proof was valid!");
-    } else {
-        println!("The proof was not valid.");
+/**
+*   writes the transcript of the game just played to TRANSCRIPT_DIR
+*/
+fn save_transcript<P: GameSystem>(transcript: &Transcript<P>) -> Result<(), GameError> {
+    std::fs::create_dir_all(TRANSCRIPT_DIR)?;
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let path = Path::new(TRANSCRIPT_DIR).join(format!("game-{}-{}.zkbt", secs, std::process::id()));
+    transcript.save(&path)?;
+    println!("The game transcript was saved to {}", path.display());
+    Ok(())
+}
+
+/**
+*   replays a saved transcript against the local keys for its game
+*/
+fn verify_transcript<P: GameSystem>(path: &Path) -> Result<(), GameError> {
+    let transcript = Transcript::<P>::load(path)?;
+    let game = transcript.game()?;
+    print_settings(&game);
+    let keys = load_keys(&game)?;
+
+    println!("Replaying {} shots..", transcript.turns.len());
+    match transcript.verify(&keys)? {
+        Outcome::Won(seat) => println!("Every proof is valid. Player {} won.", seat_name(seat)),
+        Outcome::Cheated(seat) => println!("Player {} cheated: one of their proofs is invalid.", seat_name(seat)),
+        Outcome::Unfinished => println!("Every proof is valid, but the game ended before anyone won."),
     }
+    Ok(())
+}
 
-    loop {
-        // player a's turn
-        println!("Player A's turn!");
-        perform_turn(&mut board_b, &mut board_a_b, &randomness_b, &commitments_b);
-        if check_winner(&mut board_b) {
-            println!("Player One wins!");
-            std::process::exit(0);
+/**
+*   asks for the game settings and generates the bw6-761 keys for folding games of that
+*   shape won in `turns` shots, from the inner keys in KEY_DIR. like `setup`, whoever runs
+*   this could forge folded proofs, so it should be someone both players trust
+*/
+#[cfg(feature = "recursion")]
+fn fold_setup(turns: usize, seed: Option<u64>) -> Result<(), GameError> {
+    use zk_battleships::recursion::FoldKeys;
+
+    let game = setup()?;
+    if game.curve() != Curve::Bls12_377 || game.system() != System::Groth16 {
+        return Err(GameError::BadInput("only groth16 games on bls12-377 can be folded".to_string()));
+    }
+    let keys = load_keys::<Groth16<Bls12_377>>(&game)?;
+    println!("Generating the keys for games of {} shots, this may take a while..", turns);
+    let fold = FoldKeys::generate(&game, &keys, turns, &mut game_rng(seed))?;
+    std::fs::create_dir_all(KEY_DIR)?;
+    for path in fold.save(Path::new(KEY_DIR), &game)? {
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/**
+*   replays a won game on bls12-377 and folds it into one proof over bw6-761, written to
+*   the transcript's path with `.fold` added. the outer keys come from KEY_DIR, written by
+*   `fold-setup` for the game's shape and length
+*/
+#[cfg(feature = "recursion")]
+fn fold_transcript(path: &Path, seed: Option<u64>) -> Result<(), GameError> {
+    use zk_battleships::recursion::{self, FoldKeys, FoldedGame};
+
+    let transcript = Transcript::<Groth16<Bls12_377>>::load(path)?;
+    let game = transcript.game()?;
+    print_settings(&game);
+    let keys = load_keys(&game)?;
+    let fold = FoldKeys::load(Path::new(KEY_DIR), &game, transcript.turns.len())?;
+
+    println!("Folding the game of {} shots, this may take a while..", transcript.turns.len());
+    let proof = recursion::fold_transcript(&fold, &keys, &transcript, &mut game_rng(seed))?;
+    let commitments = [transcript.commitments[0].as_deref().unwrap_or_default(), transcript.commitments[1].as_deref().unwrap_or_default()];
+    let folded = FoldedGame::new(&game, transcript.turns.len(), commitments, proof);
+    if !folded.verify(&fold.vk)? {
+        return Err(GameError::Proving("the folded proof doesn't verify, were the fold keys made from these inner keys?".to_string()));
+    }
+
+    let mut out = path.as_os_str().to_owned();
+    out.push(".fold");
+    folded.save(Path::new(&out))?;
+    println!("Player {} won. Wrote the folded proof to {}", seat_name(folded.proof.winner), Path::new(&out).display());
+    Ok(())
+}
+
+/**
+*   checks a folded game against the outer verifying key in KEY_DIR for its shape and
+*   length, never against a key that came with the proof
+*/
+#[cfg(feature = "recursion")]
+fn verify_fold(path: &Path) -> Result<(), GameError> {
+    use zk_battleships::recursion::{self, FoldedGame};
+
+    let folded = FoldedGame::load(path)?;
+    let game = folded.game()?;
+    print_settings(&game);
+    let vk = recursion::load_fold_vk(Path::new(KEY_DIR), &game, folded.turns)?;
+    if !folded.verify(&vk)? {
+        return Err(GameError::ProofFailure("the folded proof is invalid".to_string()));
+    }
+    println!("The folded proof is valid. Player {} won the game of {} shots.", seat_name(folded.proof.winner), folded.turns);
+    Ok(())
+}
+
+/**
+*   moves keys, commitments and board proofs in and out of the process as envelopes, printed
+*   as hex or, with --base64, as base64. `export-keys` prints the local verifying keys for a
+*   game shape and `check-key` compares one pasted by someone else with the local key, so two
+*   players can confirm they hold the same keys. `export-board` prints a seat's commitment
+*   and board proof from a transcript, and `verify-board` checks a pasted pair against the
+*   local keys without replaying the rest of the game
+*/
+fn run_envelope(args: &[String]) -> Result<(), GameError> {
+    let usage = || GameError::BadInput("usage: zk-battleships envelope <export-keys | check-key <circuit> <text> | export-board <file> <A|B> | verify-board <commitment> <proof>> [--base64]".to_string());
+    let base64 = args.iter().any(|a| a == "--base64");
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).filter(|a| *a != "--base64").collect();
+    match args.as_slice() {
+        ["export-keys"] => {
+            let game = setup()?;
+            on_system!((game.curve(), game.system()), export_keys(&game, base64))
+        }
+        ["check-key", circuit, text] => {
+            let circuit = Circuit::from_name(circuit).ok_or_else(usage)?;
+            let game = setup()?;
+            on_system!((game.curve(), game.system()), check_key(&game, circuit, text))
+        }
+        ["export-board", path, seat] => {
+            let seat = match seat.to_ascii_uppercase().as_str() {
+                "A" => 0,
+                "B" => 1,
+                _ => return Err(usage()),
+            };
+            let path = Path::new(path);
+            on_system!(transcript::read_system(BufReader::new(File::open(path)?))?, export_board(path, seat, base64))
+        }
+        ["verify-board", commitment, proof] => {
+            let game = setup()?;
+            on_system!((game.curve(), game.system()), verify_pasted_board(&game, commitment, proof))
+        }
+        _ => Err(usage()),
+    }
+}
+
+fn print_envelope<P: GameSystem>(label: &str, envelope: &Envelope<P>, base64: bool) -> Result<(), GameError> {
+    let text = if base64 { envelope.to_base64()? } else { envelope.to_hex()? };
+    println!("{}: {}", label, text);
+    Ok(())
+}
+
+fn export_keys<P: GameSystem>(game: &Game, base64: bool) -> Result<(), GameError> {
+    let keys = load_keys::<P>(game)?;
+    for circuit in [Circuit::Board, Circuit::Shot, Circuit::Sunk, Circuit::Remaining, Circuit::Salvo] {
+        // the salvo key only exists for salvo games
+        if let Ok(vk) = keys.verifying_key(circuit) {
+            print_envelope(circuit.name(), &Envelope::new(game, circuit, Payload::<P>::VerifyingKey(vk.clone())), base64)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_key<P: GameSystem>(game: &Game, circuit: Circuit, text: &str) -> Result<(), GameError> {
+    let keys = load_keys::<P>(game)?;
+    let vk = Envelope::<P>::from_text(text)?.into_verifying_key(game, circuit)?;
+    if &vk != keys.verifying_key(circuit)? {
+        return Err(GameError::KeyMismatch);
+    }
+    println!("The {} verifying key matches the local one.", circuit.name());
+    Ok(())
+}
+
+fn export_board<P: GameSystem>(path: &Path, seat: u8, base64: bool) -> Result<(), GameError> {
+    let transcript = Transcript::<P>::load(path)?;
+    let game = transcript.game()?;
+    let missing = || GameError::BadInput(format!("player {} never sent their board", seat_name(seat)));
+    let commitment = transcript.commitments[seat as usize].clone().ok_or_else(missing)?;
+    let proof = transcript.board_proofs[seat as usize].clone().ok_or_else(missing)?;
+    print_envelope("commitment", &Envelope::new(&game, Circuit::Board, Payload::<P>::Commitment(commitment)), base64)?;
+    print_envelope("board proof", &Envelope::new(&game, Circuit::Board, Payload::<P>::Proof(proof)), base64)
+}
+
+/**
+*   checks a pasted commitment and board proof against the local keys, the same check a
+*   player makes of their opponent's board before the first shot
+*/
+fn verify_pasted_board<P: GameSystem>(game: &Game, commitment: &str, proof: &str) -> Result<(), GameError> {
+    let keys = load_keys::<P>(game)?;
+    let commitment = Envelope::<P>::from_text(commitment)?.into_commitment(game)?;
+    let proof = Envelope::<P>::from_text(proof)?.into_proof(game, Circuit::Board)?;
+    let mut verifier = game.new_player();
+    verifier.receive_commitment(commitment)?;
+    verifier.verify_board(&keys, proof)?;
+    println!("The board proof is valid: the commitment is of a legal board for these settings.");
+    Ok(())
+}
+
+/**
+*   runs one step of a key ceremony kept in a file. `new` asks for the game settings and
+*   starts the ceremony, each participant then runs `contribute` in turn, someone runs
+*   `seal` to end phase one, everyone contributes again, and `finish` checks the whole
+*   ceremony and writes every key for the game shape to KEY_DIR, in place of `setup`
+*/
+fn run_ceremony(args: &[String], seed: Option<u64>) -> Result<(), GameError> {
+    let usage = || GameError::BadInput("usage: zk-battleships ceremony <new|contribute|seal|verify|finish> <file>".to_string());
+    let (step, path) = match (args.first(), args.get(1)) {
+        (Some(step), Some(path)) => (step.as_str(), Path::new(path)),
+        _ => return Err(usage()),
+    };
+    match step {
+        "new" => {
+            let game = setup()?;
+            on_curve!(game.curve(), start_ceremony(&game, path))
+        }
+        "contribute" | "seal" | "verify" | "finish" => on_curve!(ceremony::read_curve(BufReader::new(File::open(path)?))?, ceremony_step(step, path, seed)),
+        _ => Err(usage()),
+    }
+}
+
+fn start_ceremony<E: GameCurve>(game: &Game, path: &Path) -> Result<(), GameError> {
+    let ceremony = Ceremony::<E>::new(game)?;
+    ceremony.save(path)?;
+    println!("Started a ceremony over a domain of {} elements in {}", ceremony.phases.powers.domain_size(), path.display());
+    Ok(())
+}
+
+fn ceremony_step<E: GameCurve>(step: &str, path: &Path, seed: Option<u64>) -> Result<(), GameError> {
+    let mut ceremony = Ceremony::<E>::load(path)?;
+    print_settings(&ceremony.game()?);
+    println!("Checking the {} contributions so far, this may take a while..", ceremony.phases.phase_one.len() + ceremony.phases.phase_two.len());
+    let receipts = ceremony.verify(&mut OsRng)?;
+
+    match step {
+        "contribute" => {
+            let phase = if ceremony.phases.keys.is_none() { "one" } else { "two" };
+            let receipt = ceremony.contribute(&mut game_rng(seed))?;
+            ceremony.save(path)?;
+            println!("Contributed to phase {}. Your receipt is {}", phase, hex::encode(receipt));
         }
+        "seal" => {
+            ceremony.seal()?;
+            ceremony.save(path)?;
+            println!("Sealed phase one after {} contributions, phase two can start.", ceremony.phases.phase_one.len());
+        }
+        "verify" => {
+            for (phase, receipts) in [("one", &receipts.phase_one), ("two", &receipts.phase_two)] {
+                for (i, receipt) in receipts.iter().enumerate() {
+                    println!("phase {} contribution {}: {}", phase, i + 1, hex::encode(receipt));
+                }
+            }
+            println!("Every contribution is valid.");
+        }
+        _ => {
+            let keys = ceremony.game_keys()?;
+            for path in keys::write_game_keys(Path::new(KEY_DIR), ceremony.geometry, &ceremony.fleet, ceremony.backend, ceremony.salvo, &keys)? {
+                println!("wrote {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/**
+*   plays headless bot-vs-bot games and prints the win rates, shots per game and, with
+*   --proofs, how long proving and verifying took. without --seed a random seed is picked
+*   and printed, so any run can be replayed
+*/
+fn run_simulation(args: &[String], seed: Option<u64>) -> Result<(), GameError> {
+    let usage = || GameError::BadInput("usage: zk-battleships simulate <games> [--board WxH] [--fleet 5,4,3,3,2] [--commitment scheme] [--rule anything-goes] [--salvo off] [--curve bls12-381] [--system groth16] [--bots hard,easy] [--seed n] [--proofs]".to_string());
+    let games: usize = parse(args.first().ok_or_else(usage)?)?;
+
+    let geometry = option(args, "--board").unwrap_or("10x10").parse()?;
+    let fleet = option(args, "--fleet").unwrap_or("5,4,3,3,2").split(',').map(parse).collect::<Result<Vec<u8>, GameError>>()?;
+    let backend_name = option(args, "--commitment").unwrap_or("poseidon");
+    let backend = CommitmentBackend::from_name(backend_name)
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a commitment scheme", backend_name)))?;
+    let rule_name = option(args, "--rule").unwrap_or("anything-goes");
+    let rule = AdjacencyRule::from_name(rule_name)
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a rule", rule_name)))?;
+    let salvo_name = option(args, "--salvo").unwrap_or("off");
+    let salvo = Salvo::from_name(salvo_name)
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a salvo", salvo_name)))?;
+    let curve_name = option(args, "--curve").unwrap_or("bls12-381");
+    let curve = Curve::from_name(curve_name)
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a curve", curve_name)))?;
+    let system_name = option(args, "--system").unwrap_or("groth16");
+    let system = System::from_name(system_name)
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a proof system", system_name)))?;
+    let game = Game::new(geometry, fleet, backend)?.with_rule(rule).with_salvo(salvo)?.with_curve(curve)?.with_system(system);
 
-        // player b's turn
-        println!("Player B's turn!");
-        perform_turn(&mut board_a, &mut board_b_a, &randomness_a, &commitments_a);
-        if check_winner(&mut board_a) {
-            println!("Player Two wins!");
-            std::process::exit(0);
+    let mut bots = [Bot::new(Difficulty::Hard); 2];
+    if let Some(names) = option(args, "--bots") {
+        let levels: Vec<&str> = names.split(',').collect();
+        if levels.len() != 2 {
+            return Err(usage());
+        }
+        for (bot, level) in bots.iter_mut().zip(levels) {
+            let difficulty = Difficulty::from_name(level)
+                .ok_or_else(|| GameError::BadInput(format!("'{}' is not a difficulty level", level)))?;
+            *bot = Bot::new(difficulty);
         }
     }
+    // simulated games are never real ones, so a seed carries no risk here
+    let seed = seed.unwrap_or_else(|| OsRng.next_u64());
+    let proofs = args.iter().any(|a| a == "--proofs");
+    on_system!((game.curve(), game.system()), simulate_on(&game, bots, games, proofs, seed))
 }
 
-fn setup() -> (u8, u8) {
-    println!("Please choose the size of the board. It must be a square number");
-    let line = get_input();
-    let board_size = line.trim().parse::<u8>().unwrap();
+/// runs the simulation with the keys for the game's curve and system, if it is run with proofs
+fn simulate_on<P: GameSystem>(game: &Game, bots: [Bot; 2], games: usize, proofs: bool, seed: u64) -> Result<(), GameError> {
+    let keys = if proofs { Some(load_keys::<P>(game)?) } else { None };
+    print_settings(game);
+    println!("Playing {} games, {} bot against {} bot, {} proofs, seed {}..", games, bots[0].difficulty.name(), bots[1].difficulty.name(),
+        if keys.is_some() { "with" } else { "without" }, seed);
 
-    println!("Please choose the the number of battleships. It must be less than the board size");
-    let line = get_input();
-    let num_ships = line.trim().parse::<u8>().unwrap();
+    let report = simulate(game, keys.as_ref(), bots, games, &mut ChaCha20Rng::seed_from_u64(seed))?;
+    println!("{}", report);
+    Ok(())
+}
 
-    return (board_size, num_ships);
+fn print_settings(game: &Game) {
+    println!("the board is {}, the fleet is {:?}, boards are committed with {:?}, the rule is {}, the salvo is {} and proofs are {} on {}", game.geometry(), game.fleet(), game.backend(),
+        game.rule().name(), game.salvo().name(), game.system().name(), game.curve().name());
 }
 
-fn initialise(board_size: u8, num_ships: u8, board_a: &mut [u8], board_b: &mut [u8])
-    -> (Vec<Vec<u8>>,Vec<Vec<u8>>, Vec<Vec<u8>>, Vec<Vec<u8>>) {
+/// the value after a --name option on the command line
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).map(|v| v.as_str())
+}
 
-    println!("The current game board size is {}!", &board_size);
-    println!("Player 1 please place your battleships! You can place {} battleships.", &num_ships);
-    place_battleships(board_a, num_ships);
+/// players are A (seat 0, the local first player or the host) and B
+fn seat_name(seat: u8) -> &'static str {
+    if seat == 0 { "A" } else { "B" }
+}
 
-    let randomness_a = generate_randomness(board_size);
-    let commitments_a = generate_commitments(board_a, &randomness_a);
+/**
+*   plays a game at this console, either between two people or with player B played by
+*   the bot
+*/
+fn local_game(bot: Option<Bot>, seed: Option<u64>) -> Result<(), GameError> {
+    let mut rng = game_rng(seed);
+    let game = setup()?;
+    on_system!((game.curve(), game.system()), start_local(&game, bot, &mut rng))
+}
 
+fn start_local<P: GameSystem>(game: &Game, bot: Option<Bot>, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    print_settings(game);
+    let keys = load_keys::<P>(game)?;
 
-    println!("Player 2 please place your battleships! You can place {} battleships.", &num_ships);
-    place_battleships(board_b, num_ships);
-    let randomness_b = generate_randomness(board_size);
-    let commitments_b = generate_commitments(board_b, &randomness_b);
+    std::fs::create_dir_all(SAVE_DIR)?;
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let save = SaveFile { path: Path::new(SAVE_DIR).join(format!("game-{}-{}.zkbs", secs, std::process::id())), passphrase: choose_passphrase()? };
 
-    return (randomness_a, commitments_a, randomness_b, commitments_b)
+    let mut state = GameState::new(game, keys.fingerprint()?, bot.map(|bot| bot.difficulty));
+    let result = initialise(&mut state, bot.as_ref(), rng).and_then(|_| play_local(&keys, &mut state, &save, rng));
+    finish_local(&state, &save, result)
 }
 
 /**
-*   gets player input on where they want to place their battleships,
-*   modifies the input board based on input
-*   1 for battleship
+*   loads a game saved at this console and plays it on from the next turn
 */
-fn place_battleships(board: &mut [u8], num_ships: u8){
-    for _n in 0..num_ships {
-        board_to_string(board);
-        println!("Type the corresponding number to position your battleship.");
+fn resume_game<P: GameSystem>(path: &Path, seed: Option<u64>) -> Result<(), GameError> {
+    let mut rng = game_rng(seed);
+    let mut passphrase = None;
+    let mut state = GameState::<P>::load(path, || {
+        println!("Please enter the passphrase the game was saved with");
+        let line = read_passphrase()?;
+        passphrase = Some(line.clone());
+        Ok(line)
+    })?;
+    let game = state.transcript.game()?;
+    print_settings(&game);
+    let keys = load_keys(&game)?;
+    if keys.fingerprint()? != state.transcript.keys {
+        return Err(GameError::KeyMismatch);
+    }
 
-        let line = get_input();
-        let target = line.trim().parse::<usize>().unwrap();
-        if target > board.len() {
-            println!("Target not on board.")
-        } else if board[target] == 0 {
-            board[target] = 1;
+    println!("Resuming the game after {} shots.", state.turn());
+    let save = SaveFile { path: path.to_path_buf(), passphrase };
+    let result = play_local(&keys, &mut state, &save, &mut rng);
+    finish_local(&state, &save, result)
+}
+
+/**
+*   writes the transcript of a game played at this console. once it is over, cheating
+*   included, the saved game is removed, otherwise it is left to be resumed
+*/
+fn finish_local<P: GameSystem>(state: &GameState<P>, save: &SaveFile, result: Result<(), GameError>) -> Result<(), GameError> {
+    save_transcript(&state.transcript)?;
+    match result {
+        Ok(()) => {
+            if save.path.exists() {
+                std::fs::remove_file(&save.path)?;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            if save.path.exists() {
+                println!("The game was saved, carry on with `zk-battleships resume {}`", save.path.display());
+            }
+            Err(e)
         }
     }
-    println!("----------------------------------------------------------------")
+}
+
+/// where a game at this console is saved and the passphrase its secrets are encrypted with
+struct SaveFile {
+    path: std::path::PathBuf,
+    passphrase: Option<String>,
+}
+
+impl SaveFile {
+    fn write<P: GameSystem>(&self, state: &GameState<P>) -> Result<(), GameError> {
+        state.save(&self.path, self.passphrase.as_deref())
+    }
 }
 
 /**
-*   generates 32 bytes of randomess board_size times and returns as Vec<Vec<u8>>
+*   asks for the passphrase to encrypt the saved game's ships and secrets with, None to save
+*   them in the clear
 */
-fn generate_randomness(board_size: u8) -> Vec<Vec<u8>> {
-    let mut randomness = Vec::new();
-    for _ in 0..board_size {
-        let mut rng = OsRng::default();
-        let mut randomness_set = [0u8; 32];
-        rng.fill(&mut randomness_set);
-        randomness.push(randomness_set.to_vec());
+fn choose_passphrase() -> Result<Option<String>, GameError> {
+    println!("The game is saved after every turn. Type a passphrase to encrypt the saved boards with, or press enter to save them unencrypted");
+    let line = read_passphrase()?;
+    Ok(if line.is_empty() { None } else { Some(line) })
+}
+
+/// one line of input with the line ending removed, passphrases may have other whitespace
+fn read_passphrase() -> Result<String, GameError> {
+    Ok(get_input()?.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/**
+*   proves and checks the board proofs if the game hasn't got that far yet, then plays
+*   turns until someone wins. the game is saved after the board proofs and after every turn
+*/
+fn play_local<P: GameSystem>(keys: &GameKeys<P>, state: &mut GameState<P>, save: &SaveFile, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    let bot = state.bot.map(Bot::new);
+    let GameState { transcript, players, .. } = state;
+
+    for seat in 0..2u8 {
+        if transcript.board_proofs[seat as usize].is_some() {
+            continue;
+        }
+        let [player_a, player_b] = &mut *players;
+        let (prover, verifier) = if seat == 0 { (player_a, player_b) } else { (player_b, player_a) };
+        println!("Generating proof for player {}", seat_name(seat));
+        let proof = prover.prove_board(keys, rng)?;
+        transcript.record_board_proof(seat, &proof);
+        println!("Verifying proof..");
+        if caught_cheating(verifier.verify_board(keys, proof))? {
+            return Ok(());
+        }
+        println!("The proof was valid!");
+    }
+    save.write(state)?;
+
+    loop {
+        let seat = state.next_shooter();
+        let GameState { transcript, players, .. } = state;
+        let [player_a, player_b] = players;
+        let (attacker, defender) = if seat == 0 { (player_a, player_b) } else { (player_b, player_a) };
+
+        println!("Player {}'s turn!", seat_name(seat));
+        let shooter = if seat == 1 { bot.as_ref() } else { None };
+        let turn = match attacker.game().salvo() {
+            Salvo::Off => perform_turn(keys, transcript, seat, attacker, defender, shooter, rng),
+            _ => perform_salvo(keys, transcript, seat, attacker, defender, shooter, rng),
+        };
+        if caught_cheating(turn)? {
+            return Ok(());
+        }
+        if attacker.has_won()? {
+            if caught_cheating(confirm_victory(keys, transcript, attacker, defender, rng))? {
+                return Ok(());
+            }
+            println!("Player {} wins!", seat_name(seat));
+            let [player_a, player_b] = &state.players;
+            return reveal_boards(&mut state.transcript, player_a, player_b);
+        }
+        save.write(state)?;
+    }
+}
+
+fn host_game(port: &str, seed: Option<u64>) -> Result<(), GameError> {
+    let mut rng = game_rng(seed);
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port))?;
+    println!("Waiting for an opponent to join on port {}..", port);
+    let (stream, addr) = listener.accept()?;
+    println!("{} joined the game", addr);
+
+    let game = setup()?;
+    on_system!((game.curve(), game.system()), start_host(stream, &game, &mut rng))
+}
+
+fn start_host<P: GameSystem>(mut stream: TcpStream, game: &Game, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    let keys = load_keys::<P>(game)?;
+    write_message(&mut stream, &Message::<P>::Hello { geometry: game.geometry(), fleet: game.fleet().to_vec(), backend: game.backend(), rule: game.rule(), salvo: game.salvo(),
+        curve: game.curve(), system: game.system(), keys: keys.fingerprint()? })?;
+
+    play_networked(stream, game, &keys, true, rng)
+}
+
+fn join_game(addr: &str, seed: Option<u64>) -> Result<(), GameError> {
+    let mut rng = game_rng(seed);
+    let mut stream = TcpStream::connect(addr)?;
+    println!("Connected to {}, waiting for the game settings..", addr);
+
+    // the hello carries no proofs, so it reads the same whichever curve and system the game is on
+    let (game, fingerprint) = match read_message::<Groth16<Bls12_381>, _>(&mut stream)? {
+        Message::Hello { geometry, fleet, backend, rule, salvo, curve, system, keys } =>
+            (Game::new(geometry, fleet, backend)?.with_rule(rule).with_salvo(salvo)?.with_curve(curve)?.with_system(system), keys),
+        other => return Err(unexpected("the game settings", other)),
+    };
+    on_system!((game.curve(), game.system()), start_join(stream, &game, &fingerprint, &mut rng))
+}
+
+fn start_join<P: GameSystem>(stream: TcpStream, game: &Game, fingerprint: &[u8], rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    // proofs are only checked against local keys, so both sides must have the same ones
+    let keys = load_keys::<P>(game)?;
+    if keys.fingerprint()? != fingerprint {
+        return Err(GameError::KeyMismatch);
+    }
+
+    play_networked(stream, game, &keys, false, rng)
+}
+
+/// the opponent sent something other than the message the protocol expects next
+fn unexpected<P: GameSystem>(expected: &str, got: Message<P>) -> GameError {
+    GameError::BadInput(format!("expected {}, got {:?}", expected, got))
+}
+
+/**
+*   the loser proves none of their ships remain after the winner's hits, so the win
+*   rests on the loser's commitment rather than the winner's own count
+*/
+fn confirm_victory<P: GameSystem>(keys: &GameKeys<P>, transcript: &mut Transcript<P>, winner: &mut PlayerState, loser: &PlayerState, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    let (remaining, proof) = loser.prove_remaining(keys, rng)?;
+    transcript.record_victory(&proof);
+    println!("Verifying the proof that no ships remain..");
+    winner.confirm_victory(keys, remaining, proof)?;
+    println!("The proof was valid!");
+    Ok(())
+}
+
+/**
+*   once the game is over both boards are revealed and each player audits the other's
+*   against their commitment and every answer they gave
+*/
+fn reveal_boards<P: GameSystem>(transcript: &mut Transcript<P>, player_a: &PlayerState, player_b: &PlayerState) -> Result<(), GameError> {
+    let reveal_a = player_a.reveal()?;
+    let reveal_b = player_b.reveal()?;
+    transcript.record_reveal(0, &reveal_a);
+    transcript.record_reveal(1, &reveal_b);
+
+    println!("Auditing the revealed boards..");
+    if caught_cheating(player_b.audit(&reveal_a))? || caught_cheating(player_a.audit(&reveal_b))? {
+        return Ok(());
     }
-    return randomness;
+    println!("Both boards match their commitments and every answer given.");
+    Ok(())
 }
 
 /**
-*   generates blake2s commitments for each board space using associated randomness
+*   plays one side of a game against a remote opponent. only this player's board and
+*   randomness live in this process, the opponent is only ever seen through their
+*   commitment and proofs. the host fires first
 */
-fn generate_commitments(board: &[u8], randomness: &Vec<Vec<u8>>) -> Vec<Vec<u8>> {
-    let mut commitments :Vec<Vec<u8>> = Vec::new();
+fn play_networked<P: GameSystem>(stream: TcpStream, game: &Game, keys: &GameKeys<P>, first: bool, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    print_settings(game);
+
+    let mut transcript = Transcript::new(game, keys.fingerprint()?);
+    let result = play_remote(stream, game, keys, first, &mut transcript, rng);
+    save_transcript(&transcript)?;
+    result
+}
+
+fn play_remote<P: GameSystem>(mut stream: TcpStream, game: &Game, keys: &GameKeys<P>, first: bool, transcript: &mut Transcript<P>, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    let seat = if first { 0 } else { 1 };
+    let opponent = 1 - seat;
+
+    let mut player = game.new_player();
+    println!("Please place your battleships! Your fleet is {:?}.", game.fleet());
+    place_battleships(&mut player)?;
+    let commitment = player.commit(rng)?;
+    transcript.record_commitment(seat, &commitment);
+
+    write_message(&mut stream, &Message::<P>::Commitment { commitment })?;
+    match read_message::<P, _>(&mut stream)? {
+        Message::Commitment { commitment } => {
+            transcript.record_commitment(opponent, &commitment);
+            player.receive_commitment(commitment)?
+        }
+        other => return Err(unexpected("the opponent's commitment", other)),
+    };
+
+    println!("Generating proof for your board");
+    let proof = player.prove_board(keys, rng)?;
+    transcript.record_board_proof(seat, &proof);
+    write_message(&mut stream, &Message::<P>::BoardProof { proof })?;
 
-    let params = ();
-    for i in 0..board.len() {
-        let mut r = [0u8;32];
-        r.copy_from_slice(&randomness[i]);
-        let commitment = Commitment::commit(&params, &[board[i]], &r );
-        commitments.push(commitment.unwrap().to_vec());
+    println!("Verifying the opponent's board proof..");
+    let opponent_proof = match read_message::<P, _>(&mut stream)? {
+        Message::BoardProof { proof } => proof,
+        other => return Err(unexpected("the opponent's board proof", other)),
+    };
+    transcript.record_board_proof(opponent, &opponent_proof);
+    if caught_cheating(player.verify_board(keys, opponent_proof))? {
+        return Ok(());
+    }
+    println!("The proof was valid!");
+
+    let mut my_turn = first;
+    loop {
+        if my_turn {
+            println!("Your turn!");
+            let fired = match game.salvo() {
+                Salvo::Off => fire_remote_shot(&mut stream, keys, &mut player, transcript, seat),
+                _ => fire_remote_salvo(&mut stream, keys, &mut player, transcript, seat),
+            };
+            if caught_cheating(fired)? {
+                return Ok(());
+            }
+
+            if player.has_won()? {
+                let (remaining, proof) = match read_message::<P, _>(&mut stream)? {
+                    Message::Remaining { remaining, proof } => (remaining, proof),
+                    other => return Err(unexpected("the opponent's proof that no ships remain", other)),
+                };
+                transcript.record_victory(&proof);
+                println!("Verifying the proof that no ships remain..");
+                if caught_cheating(player.confirm_victory(keys, remaining, proof))? {
+                    return Ok(());
+                }
+                println!("The proof was valid!");
+                println!("You win!");
+                return exchange_reveals(&mut stream, &player, transcript, seat);
+            }
+        } else {
+            match game.salvo() {
+                Salvo::Off => answer_remote_shot(&mut stream, keys, &mut player, transcript, opponent, rng)?,
+                _ => answer_remote_salvo(&mut stream, keys, &mut player, transcript, opponent, rng)?,
+            }
+
+            if player.has_lost() {
+                println!("The opponent wins!");
+                let (remaining, proof) = player.prove_remaining(keys, rng)?;
+                transcript.record_victory(&proof);
+                write_message(&mut stream, &Message::<P>::Remaining { remaining, proof })?;
+                return exchange_reveals(&mut stream, &player, transcript, seat);
+            }
+        }
+        my_turn = !my_turn;
     }
-    return commitments;
 }
 
 /**
-*   generates groth16 proof and verifying key
+*   fires one shot at the remote opponent and checks their answer and any sunk ship
 */
-fn generate_proof(board: &Vec<u8>, randomness: &Vec<Vec<u8>>, commitments: &Vec<Vec<u8>>, ships: u8, b_size: u8)
--> (Proof<Bls12_381>, PreparedVerifyingKey<Bls12_381>) {
-    let circuit = BoardVerifier {
-        ships: ships,
-        b_size: b_size,
-        commitments: commitments.clone(),
+fn fire_remote_shot<P: GameSystem>(stream: &mut TcpStream, keys: &GameKeys<P>, player: &mut PlayerState, transcript: &mut Transcript<P>, seat: u8) -> Result<(), GameError> {
+    let t = pick_target(player)?;
+    write_message(stream, &Message::<P>::Shot { target: t })?;
 
-        rng_in: Some(randomness.clone()),
-        board: Some(board.clone()),
+    let (hit, proof, sunk) = match read_message::<P, _>(stream)? {
+        Message::ShotAnswer { hit, proof, sunk } => (hit, proof, sunk),
+        other => return Err(unexpected("an answer to the shot", other)),
     };
 
-    let mut rng = OsRng::default();
-    let params = generate_random_parameters::<Bls12_381, _, _>(circuit.clone(), &mut rng).unwrap();
-    let pvk = prepare_verifying_key(&params.vk);
+    transcript.record_turn(seat, t, hit, &proof);
+    println!("Verifying..");
+    player.verify_answer(keys, t, hit, proof)?;
+    println!("The shot proof is valid");
+    println!("{}", if hit { "Hit!" } else { "Miss!" });
 
+    if let Some(sunk) = sunk {
+        transcript.record_sunk(t, &sunk);
+        check_sunk(keys, player, t, sunk)?;
+    }
+    Ok(())
+}
 
-    let proof = {
-        // Create a proof with our parameters.
-        create_random_proof(circuit, &params, &mut rng).unwrap()
+/**
+*   fires a whole salvo at the remote opponent and checks their one proof answering it and
+*   any ships it sank
+*/
+fn fire_remote_salvo<P: GameSystem>(stream: &mut TcpStream, keys: &GameKeys<P>, player: &mut PlayerState, transcript: &mut Transcript<P>, seat: u8) -> Result<(), GameError> {
+    let targets = pick_salvo(player)?;
+    write_message(stream, &Message::<P>::Salvo { targets: targets.clone() })?;
+
+    let (hits, proof, sunk) = match read_message::<P, _>(stream)? {
+        Message::SalvoAnswer { hits, proof, sunk } => (hits, proof, sunk),
+        other => return Err(unexpected("an answer to the salvo", other)),
     };
 
-    return (proof, pvk)
+    transcript.record_salvo(seat, &targets, &hits, &proof);
+    println!("Verifying..");
+    player.verify_salvo(keys, &targets, &hits, proof)?;
+    println!("The salvo proof is valid");
+    print_salvo(&targets, &hits);
+
+    for (target, sunk) in sunk {
+        transcript.record_sunk(target, &sunk);
+        check_sunk(keys, player, target, sunk)?;
+    }
+    Ok(())
+}
+
+/**
+*   answers the remote opponent's next shot with a proof, and a sunk ship proof if it sank one
+*/
+fn answer_remote_shot<P: GameSystem>(stream: &mut TcpStream, keys: &GameKeys<P>, player: &mut PlayerState, transcript: &mut Transcript<P>, opponent: u8, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    println!("Waiting for the opponent's shot..");
+    let t = match read_message::<P, _>(stream)? {
+        Message::Shot { target } => target,
+        other => return Err(unexpected("a shot at the board", other)),
+    };
+
+    let (hit, proof) = player.answer(keys, t, rng)?;
+    transcript.record_turn(opponent, t, hit, &proof);
+    let sunk = player.announce_sunk(keys, t, rng)?;
+    if let Some(sunk) = &sunk {
+        transcript.record_sunk(t, sunk);
+    }
+    write_message(stream, &Message::<P>::ShotAnswer { hit, proof, sunk: sunk.clone() })?;
+
+    println!("The opponent fired at {} and {}", t, if hit { "hit!" } else { "missed." });
+    if let Some(sunk) = sunk {
+        println!("They sank your ship of length {}.", player.game().fleet()[sunk.ship as usize]);
+    }
+    Ok(())
 }
 
 /**
-* verifies proof using public information, the proof and the verifying key
+*   answers the remote opponent's next salvo with one proof, and a sunk ship proof for every
+*   ship it sank
 */
-fn verify_initial_proof(commitments: &Vec<Vec<u8>>, ships: u8, b_size: u8, proof: Proof<Bls12_381>, pvk: PreparedVerifyingKey<Bls12_381>) -> bool {
-    let  mut inputs: Vec<_> = Vec::new();
-    inputs.push(Fr::from(ships));
-    inputs.push(Fr::from(b_size));
+fn answer_remote_salvo<P: GameSystem>(stream: &mut TcpStream, keys: &GameKeys<P>, player: &mut PlayerState, transcript: &mut Transcript<P>, opponent: u8, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    println!("Waiting for the opponent's salvo..");
+    let targets = match read_message::<P, _>(stream)? {
+        Message::Salvo { targets } => targets,
+        other => return Err(unexpected("a salvo at the board", other)),
+    };
 
-    for i in commitments.clone() {
-        let mut field_elements: Vec<Fr> = ToConstraintField::<Fr>::to_field_elements(&i).unwrap();
-        inputs.append(&mut field_elements);
+    let (hits, proof) = player.answer_salvo(keys, &targets, rng)?;
+    transcript.record_salvo(opponent, &targets, &hits, &proof);
+    let sunk = player.announce_salvo_sunk(keys, &targets, rng)?;
+    for (target, sunk) in &sunk {
+        transcript.record_sunk(*target, sunk);
     }
+    write_message(stream, &Message::<P>::SalvoAnswer { hits: hits.clone(), proof, sunk: sunk.clone() })?;
 
-    let r = verify_proof(&pvk, &proof, &inputs);
-    return r.unwrap();
+    println!("The opponent fired a salvo:");
+    print_salvo(&targets, &hits);
+    for (_, sunk) in sunk {
+        println!("They sank your ship of length {}.", player.game().fleet()[sunk.ship as usize]);
+    }
+    Ok(())
 }
 
 /**
-* verifies tiles state by recalculating the commitment
+*   once the game is over each side reveals its board and audits the opponent's
 */
-fn verify_move(ship: u8, randomness: &Vec<u8>, commitment: &Vec<u8>) {
-    let mut rand = [0u8;32];
-    rand.copy_from_slice(&randomness);
-    let result = Commitment::commit(&(), &[ship], &rand);
+fn exchange_reveals<P: GameSystem>(stream: &mut TcpStream, player: &PlayerState, transcript: &mut Transcript<P>, seat: u8) -> Result<(), GameError> {
+    let reveal = player.reveal()?;
+    transcript.record_reveal(seat, &reveal);
+    write_message(stream, &Message::<P>::Reveal { reveal })?;
 
-    let mut comm = [0u8;32];
-    comm.copy_from_slice(&commitment);
+    let opponent_reveal = match read_message::<P, _>(stream)? {
+        Message::Reveal { reveal } => reveal,
+        other => return Err(unexpected("the opponent's revealed board", other)),
+    };
+    transcript.record_reveal(1 - seat, &opponent_reveal);
 
-    let compare = result.unwrap() == comm;
+    println!("Auditing the opponent's revealed board..");
+    if caught_cheating(player.audit(&opponent_reveal))? {
+        return Ok(());
+    }
+    println!("The opponent's board matches their commitment and every answer they gave.");
+    Ok(())
+}
 
-    if compare {
-        println!("The commitment is valid");
-    } else {
-        println!("The opposing player tried to cheat! You win.");
-        std::process::exit(0);
+/**
+*   asks for the game settings until they describe a valid game
+*/
+fn setup() -> Result<Game, GameError> {
+    loop {
+        match read_settings() {
+            Ok(game) => return Ok(game),
+            Err(GameError::BadInput(e)) => println!("{}", e),
+            Err(e) => return Err(e),
+        }
     }
 }
 
+fn read_settings() -> Result<Game, GameError> {
+    println!("Please choose the size of the board as width x height, e.g. 10x10");
+    let geometry = get_input()?.parse()?;
+
+    println!("Please choose the fleet as a comma separated list of ship lengths, e.g. 5,4,3,3,2");
+    let fleet = get_input()?.split(',').map(parse).collect::<Result<Vec<u8>, GameError>>()?;
+
+    println!("Please choose how boards are committed to: blake2s, pedersen or poseidon");
+    let line = get_input()?;
+    let backend = CommitmentBackend::from_name(line.trim())
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a commitment scheme", line.trim())))?;
+
+    println!("Please choose how close ships may be: anything-goes, no-touch (not side by side) or no-diagonal-touch (not even at the corners)");
+    let line = get_input()?;
+    let rule = AdjacencyRule::from_name(line.trim())
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a rule", line.trim())))?;
+
+    println!("Please choose how many shots a turn has: off (one), a number of shots or ships (one per ship still afloat)");
+    let line = get_input()?;
+    let salvo = Salvo::from_name(line.trim())
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a salvo", line.trim())))?;
+
+    println!("Please choose the curve proofs are made on: bls12-381, or bls12-377 (its proofs can later be verified inside a bw6-761 proof)");
+    let line = get_input()?;
+    let curve = Curve::from_name(line.trim())
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a curve", line.trim())))?;
+
+    println!("Please choose the proof system: groth16 (keys made for each game shape), or plonk (keys indexed from one srs, see `zk-battleships srs`)");
+    let line = get_input()?;
+    let system = System::from_name(line.trim())
+        .ok_or_else(|| GameError::BadInput(format!("'{}' is not a proof system", line.trim())))?;
+
+    let game = Game::new(geometry, fleet, backend)?.with_rule(rule).with_salvo(salvo)?.with_curve(curve)?.with_system(system);
+    // placement only checks each ship as it goes, so make sure the fleet can be placed at all
+    if random_fleet(game.geometry(), rule, game.fleet(), &vec![0; game.geometry().tiles()], &mut ChaCha20Rng::seed_from_u64(0)).is_none() {
+        return Err(GameError::BadInput(format!("the fleet doesn't fit on the board under the {} rule", rule.name())));
+    }
+    Ok(game)
+}
+
+fn initialise<P: GameSystem>(state: &mut GameState<P>, bot: Option<&Bot>, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    let GameState { transcript, players: [player_a, player_b], .. } = state;
+    let game = player_a.game().clone();
+    println!("The current game board is {}!", game.geometry());
+    println!("Player 1 please place your battleships! Your fleet is {:?}.", game.fleet());
+    place_battleships(player_a)?;
+    let commitment_a = player_a.commit(rng)?;
+
+    match bot {
+        Some(bot) => {
+            println!("The {} bot is placing its battleships..", bot.difficulty.name());
+            bot.place_fleet(player_b, rng)?;
+        }
+        None => {
+            println!("Player 2 please place your battleships! Your fleet is {:?}.", game.fleet());
+            place_battleships(player_b)?;
+        }
+    }
+    let commitment_b = player_b.commit(rng)?;
+
+    transcript.record_commitment(0, &commitment_a);
+    transcript.record_commitment(1, &commitment_b);
+    player_a.receive_commitment(commitment_b)?;
+    player_b.receive_commitment(commitment_a)?;
+    Ok(())
+}
+
+/**
+*   gets player input on where they want to place each ship of the fleet
+*/
+fn place_battleships(player: &mut PlayerState) -> Result<(), GameError> {
+    while let Some(length) = player.next_ship_length() {
+        let occupied: Vec<u8> = player.board().iter().map(|tile| (*tile != 0) as u8).collect();
+        board_to_string(&occupied, player.game().geometry());
+        println!("Type the corresponding number to position the top/left end of your ship of length {}.", length);
+        let origin = match parse(&get_input()?) {
+            Ok(origin) => origin,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
+        };
+
+        println!("Type h to place it horizontally or v to place it vertically.");
+        let orientation = match get_input()?.trim() {
+            "v" => Orientation::Vertical,
+            _ => Orientation::Horizontal,
+        };
+
+        if let Err(e) = player.place_ship(Ship { origin, orientation }) {
+            println!("{}", e);
+        }
+    }
+    println!("----------------------------------------------------------------");
+    Ok(())
+}
 
 /**
 *   prints out the board. Fills the board with 0, 1, ..., len-1
 */
-fn board_to_string(board: &[u8]) {
+fn board_to_string(board: &[u8], geometry: BoardGeometry) {
     let mut output = "\n".to_string();
-    let row_len = (board.len() as f64).sqrt() as usize;
+    let row_len = geometry.width as usize;
 
     let mut rc = 0;
-    let mut tc = 0;
-    for tile in board {
+    for (tc, tile) in board.iter().enumerate() {
 
         if rc == 0 {
             output += "[";
         }
 
-        if tile == &(0 as u8) {
+        if tile == &0 {
             output += &tc.to_string();
-        } else if tile == &(1 as u8) {
+        } else if tile == &1 {
             output += "o";
-        } else if tile == &(2 as u8) {
+        } else if tile == &2 {
             output += "x";
         }
 
@@ -259,7 +1169,6 @@ fn board_to_string(board: &[u8]) {
             output += ", ";
             rc += 1;
         }
-        tc += 1;
     }
 
     print!("{}", output);
@@ -268,45 +1177,132 @@ fn board_to_string(board: &[u8]) {
 /**
 *   Get one line of user input, return as a string
 */
-fn get_input() -> String {
+fn get_input() -> Result<String, GameError> {
     let mut line = String::new();
-    let _bytes = std::io::stdin().read_line(&mut line).unwrap();
-    return line;
+    if std::io::stdin().read_line(&mut line)? == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "no more input").into());
+    }
+    Ok(line)
 }
 
-fn perform_turn(p_board: &mut [u8], view_board: &mut [u8], target_randomness: &Vec<Vec<u8>>, target_commitment: &Vec<Vec<u8>>) {
-    println!("This is your view of the opponent's board. Pick a tile to attack");
-    board_to_string(view_board);
-    let t = get_input().trim().parse::<usize>().unwrap();
+/**
+*   parses a number typed by the player
+*/
+fn parse<T: FromStr>(input: &str) -> Result<T, GameError> {
+    input.trim().parse::<T>().map_err(|_| GameError::BadInput(format!("'{}' is not a valid number", input.trim())))
+}
+
+/**
+*   the attacker picks a tile, the defender answers with a shot proof over their board
+*   and the attacker checks the answer against the defender's commitment only. the bot,
+*   if it is the attacker, picks the tile instead of the console
+*/
+fn perform_turn<P: GameSystem>(keys: &GameKeys<P>, transcript: &mut Transcript<P>, seat: u8, attacker: &mut PlayerState, defender: &mut PlayerState, bot: Option<&Bot>, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    let t = match bot {
+        Some(bot) => {
+            let t = bot.pick_target(attacker, rng)?;
+            println!("The bot fires at {}", t);
+            t
+        }
+        None => pick_target(attacker)?,
+    };
+
+    // defender's side
+    let (hit, proof) = defender.answer(keys, t, rng)?;
+    transcript.record_turn(seat, t, hit, &proof);
+    let sunk = defender.announce_sunk(keys, t, rng)?;
 
-    if view_board[t] != 0 {
-        println!("You have already attacked this area.");
-        return
+    // attacker's side
+    println!("Verifying..");
+    attacker.verify_answer(keys, t, hit, proof)?;
+    println!("The shot proof is valid");
+    println!("{}", if hit { "Hit!" } else { "Miss!" });
+
+    if let Some(sunk) = sunk {
+        transcript.record_sunk(t, &sunk);
+        check_sunk(keys, attacker, t, sunk)?;
     }
+    Ok(())
+}
 
-    if (view_board[t] == 0) && (p_board[t] == 1) {
-        println!("Hit!");
-        println!("Verifying..");
+/**
+*   the attacker picks a salvo of tiles, the defender answers all of them with one salvo
+*   proof over their board and the attacker checks it against the defender's commitment
+*   only. the bot, if it is the attacker, picks the tiles instead of the console
+*/
+fn perform_salvo<P: GameSystem>(keys: &GameKeys<P>, transcript: &mut Transcript<P>, seat: u8, attacker: &mut PlayerState, defender: &mut PlayerState, bot: Option<&Bot>, rng: &mut ChaCha20Rng) -> Result<(), GameError> {
+    let targets = match bot {
+        Some(bot) => {
+            let targets = bot.pick_salvo(attacker, rng)?;
+            println!("The bot fires at {:?}", targets);
+            targets
+        }
+        None => pick_salvo(attacker)?,
+    };
+
+    // defender's side
+    let (hits, proof) = defender.answer_salvo(keys, &targets, rng)?;
+    transcript.record_salvo(seat, &targets, &hits, &proof);
+    let sunk = defender.announce_salvo_sunk(keys, &targets, rng)?;
 
-        verify_move(1 ,&target_randomness[t] , &target_commitment[t]);
+    // attacker's side
+    println!("Verifying..");
+    attacker.verify_salvo(keys, &targets, &hits, proof)?;
+    println!("The salvo proof is valid");
+    print_salvo(&targets, &hits);
 
-        view_board[t] = 2;
-        p_board[t] = 0;
-    }   else {
-        println!("Miss!");
-        verify_move(0 ,&target_randomness[t] , &target_commitment[t]);
-        view_board[t] = 1;
+    for (target, sunk) in sunk {
+        transcript.record_sunk(target, &sunk);
+        check_sunk(keys, attacker, target, sunk)?;
     }
+    Ok(())
+}
+
+/// prints whether each shot of a salvo hit
+fn print_salvo(targets: &[u16], hits: &[bool]) {
+    for (target, hit) in targets.iter().zip(hits) {
+        println!("{}: {}", target, if *hit { "Hit!" } else { "Miss!" });
+    }
+}
+
+/**
+*   checks the defender's proof that the attacker's hit sank a ship and says which one
+*/
+fn check_sunk<P: GameSystem>(keys: &GameKeys<P>, attacker: &mut PlayerState, target: u16, sunk: Sunk<P>) -> Result<(), GameError> {
+    let length = attacker.verify_sunk(keys, target, sunk)?;
+    println!("You sank a ship of length {}! ({} of {} ships sunk)", length, attacker.sunk_ships().len(), attacker.game().fleet().len());
+    Ok(())
 }
 
 /**
-*   Winner when board sum is zero.
+*   asks the attacker for their whole salvo, as many tiles as they have shots
 */
-fn check_winner(board: &mut [u8]) -> bool {
-    let mut sum = 0;
-    for i in 0..board.len() {
-        sum += board[i];
+fn pick_salvo(player: &PlayerState) -> Result<Vec<u16>, GameError> {
+    loop {
+        println!("This is your view of the opponent's board. Pick {} tiles to attack, separated by commas", player.salvo_size());
+        board_to_string(player.view(), player.game().geometry());
+
+        let targets = get_input()?.split(',').map(parse).collect::<Result<Vec<u16>, GameError>>();
+        match targets.and_then(|targets| player.fire_salvo(&targets).map(|_| targets)) {
+            Ok(targets) => return Ok(targets),
+            Err(e @ (GameError::BadInput(_) | GameError::OutOfBounds { .. } | GameError::RepeatedShot(_))) => println!("{}", e),
+            Err(e) => return Err(e),
+        }
     }
-    println!("Number of ships left is {}", sum);
-    return sum == 0;
-}
\ No newline at end of file
+}
+
+/**
+*   asks the attacker for a tile on the board they haven't attacked yet
+*/
+fn pick_target(player: &PlayerState) -> Result<u16, GameError> {
+    loop {
+        println!("This is your view of the opponent's board. Pick a tile to attack");
+        board_to_string(player.view(), player.game().geometry());
+
+        match parse(&get_input()?).and_then(|t| player.fire(t)) {
+            Ok(t) => return Ok(t),
+            Err(e @ (GameError::BadInput(_) | GameError::OutOfBounds { .. } | GameError::RepeatedShot(_))) => println!("{}", e),
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use crate::common::*;
+use crate::commitment::CommitmentBackend;
+use crate::error::GameError;
+use crate::game::{Reveal, Sunk};
+use crate::proof_system::{Groth16, ProofSystem};
+use ark_bls12_381::Bls12_381;
+#[cfg(test)]
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use std::io::{Error, ErrorKind, Read, Write};
+
+/**
+*   wire protocol for two-process games.
+*
+*   every message is sent as one frame:
+*
+*   ```text
+*   u32 (big endian)  length of the rest of the frame
+*   u8                protocol version, PROTOCOL_VERSION
+*   u8                message tag, see Message::tag
+*   ...               the message fields in order, ark-serialize compressed encoding
+*   ```
+*
+*   a frame with a different version, an unknown tag or trailing bytes is rejected. the proofs
+*   in a message are made with the curve and proof system of the game, so the joining player
+*   reads the hello (which carries none) before knowing which to read the rest with.
+*/
+pub const PROTOCOL_VERSION: u8 = 11;
+
+/// frames larger than this are rejected before anything is allocated for them
+pub const MAX_FRAME_LEN: u32 = 1 << 20;
+
+// answers carry several proofs, messages are short lived so it isn't boxed
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message<P: ProofSystem = Groth16<Bls12_381>> {
+    /// sent by the host once the opponent connects, the game settings both players use
+    /// and the fingerprint of the host's keys (see GameKeys::fingerprint)
+    Hello { geometry: BoardGeometry, fleet: Vec<u8>, backend: CommitmentBackend, rule: AdjacencyRule, salvo: Salvo, curve: Curve, system: System, keys: Vec<u8> },
+    /// the sender's board commitment
+    Commitment { commitment: Vec<u8> },
+    /// proof that the sender's committed board holds the fleet. it is checked against
+    /// the receiver's own verifying key, keys are never sent over the wire
+    BoardProof { proof: P::Proof },
+    /// the sender fires at the target tile
+    Shot { target: u16 },
+    /// answer to the last shot with a proof over the sender's committed board, and a proof
+    /// of which ship it sank if it sank one
+    ShotAnswer { hit: bool, proof: P::Proof, sunk: Option<Sunk<P>> },
+    /// the sender's ships and commitment randomness, sent by both players once the game is over
+    Reveal { reveal: Reveal },
+    /// proof of how many of the sender's ships are still afloat, sent by the loser with
+    /// remaining 0 before the boards are revealed
+    Remaining { remaining: u8, proof: P::Proof },
+    /// the sender fires a salvo at the target tiles
+    Salvo { targets: Vec<u16> },
+    /// answer to every shot of the last salvo with one proof over the sender's committed
+    /// board, and a proof for each ship it sank along with the target that sank it
+    SalvoAnswer { hits: Vec<bool>, proof: P::Proof, sunk: Vec<(u16, Sunk<P>)> },
+}
+
+impl<P: ProofSystem> Message<P> {
+    fn tag(&self) -> u8 {
+        match self {
+            Message::Hello { .. } => 0,
+            Message::Commitment { .. } => 1,
+            Message::BoardProof { .. } => 2,
+            Message::Shot { .. } => 3,
+            Message::ShotAnswer { .. } => 4,
+            Message::Reveal { .. } => 5,
+            Message::Remaining { .. } => 6,
+            Message::Salvo { .. } => 7,
+            Message::SalvoAnswer { .. } => 8,
+        }
+    }
+
+    fn write_body<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        match self {
+            Message::Hello { geometry, fleet, backend, rule, salvo, curve, system, keys } => {
+                geometry.width.serialize(&mut writer)?;
+                geometry.height.serialize(&mut writer)?;
+                fleet.serialize(&mut writer)?;
+                backend.to_u8().serialize(&mut writer)?;
+                rule.to_u8().serialize(&mut writer)?;
+                salvo.to_u8().serialize(&mut writer)?;
+                curve.to_u8().serialize(&mut writer)?;
+                system.to_u8().serialize(&mut writer)?;
+                keys.serialize(&mut writer)
+            }
+            Message::Commitment { commitment } => commitment.serialize(&mut writer),
+            Message::BoardProof { proof } => proof.serialize(&mut writer),
+            Message::Shot { target } => target.serialize(&mut writer),
+            Message::ShotAnswer { hit, proof, sunk } => {
+                hit.serialize(&mut writer)?;
+                proof.serialize(&mut writer)?;
+                sunk.serialize(&mut writer)
+            }
+            Message::Reveal { reveal } => reveal.serialize(&mut writer),
+            Message::Remaining { remaining, proof } => {
+                remaining.serialize(&mut writer)?;
+                proof.serialize(&mut writer)
+            }
+            Message::Salvo { targets } => targets.serialize(&mut writer),
+            Message::SalvoAnswer { hits, proof, sunk } => {
+                hits.serialize(&mut writer)?;
+                proof.serialize(&mut writer)?;
+                (sunk.len() as u8).serialize(&mut writer)?;
+                for (target, sunk) in sunk {
+                    target.serialize(&mut writer)?;
+                    sunk.serialize(&mut writer)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn read_body<R: Read>(tag: u8, mut reader: R) -> Result<Message<P>, SerializationError> {
+        let message = match tag {
+            0 => Message::Hello {
+                geometry: BoardGeometry::new(u8::deserialize(&mut reader)?, u8::deserialize(&mut reader)?),
+                fleet: Vec::<u8>::deserialize(&mut reader)?,
+                backend: CommitmentBackend::from_u8(u8::deserialize(&mut reader)?)
+                    .ok_or(SerializationError::InvalidData)?,
+                rule: AdjacencyRule::from_u8(u8::deserialize(&mut reader)?)
+                    .ok_or(SerializationError::InvalidData)?,
+                salvo: Salvo::from_u8(u8::deserialize(&mut reader)?)
+                    .ok_or(SerializationError::InvalidData)?,
+                curve: Curve::from_u8(u8::deserialize(&mut reader)?)
+                    .ok_or(SerializationError::InvalidData)?,
+                system: System::from_u8(u8::deserialize(&mut reader)?)
+                    .ok_or(SerializationError::InvalidData)?,
+                keys: Vec::<u8>::deserialize(&mut reader)?,
+            },
+            1 => Message::Commitment { commitment: Vec::<u8>::deserialize(&mut reader)? },
+            2 => Message::BoardProof { proof: P::Proof::deserialize(&mut reader)? },
+            3 => Message::Shot { target: u16::deserialize(&mut reader)? },
+            4 => Message::ShotAnswer {
+                hit: bool::deserialize(&mut reader)?,
+                proof: P::Proof::deserialize(&mut reader)?,
+                sunk: Option::<Sunk<P>>::deserialize(&mut reader)?,
+            },
+            5 => Message::Reveal { reveal: Reveal::deserialize(&mut reader)? },
+            6 => Message::Remaining {
+                remaining: u8::deserialize(&mut reader)?,
+                proof: P::Proof::deserialize(&mut reader)?,
+            },
+            7 => Message::Salvo { targets: Vec::<u16>::deserialize(&mut reader)? },
+            8 => {
+                let hits = Vec::<bool>::deserialize(&mut reader)?;
+                let proof = P::Proof::deserialize(&mut reader)?;
+                let mut sunk = vec![];
+                for _ in 0..u8::deserialize(&mut reader)? {
+                    sunk.push((u16::deserialize(&mut reader)?, Sunk::deserialize(&mut reader)?));
+                }
+                Message::SalvoAnswer { hits, proof, sunk }
+            }
+            _ => return Err(SerializationError::InvalidData),
+        };
+        Ok(message)
+    }
+}
+
+fn invalid_data(e: String) -> Error {
+    Error::new(ErrorKind::InvalidData, e)
+}
+
+/**
+*   encodes the message as a single frame and writes it out
+*/
+pub fn write_message<P: ProofSystem, W: Write>(writer: &mut W, message: &Message<P>) -> Result<(), GameError> {
+    let mut payload = vec![PROTOCOL_VERSION, message.tag()];
+    message.write_body(&mut payload)?;
+
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(&payload)?;
+    Ok(writer.flush()?)
+}
+
+/**
+*   reads one frame and decodes the message in it
+*/
+pub fn read_message<P: ProofSystem, R: Read>(reader: &mut R) -> Result<Message<P>, GameError> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if !(2..=MAX_FRAME_LEN).contains(&len) {
+        return Err(invalid_data(format!("bad frame length {}", len)).into());
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+
+    if payload[0] != PROTOCOL_VERSION {
+        return Err(invalid_data(format!("unsupported protocol version {}", payload[0])).into());
+    }
+
+    let mut body = &payload[2..];
+    let message = Message::read_body(payload[1], &mut body)?;
+    if !body.is_empty() {
+        return Err(invalid_data("trailing bytes in frame".to_string()).into());
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+fn all_messages() -> Vec<Message> {
+    vec![
+        Message::Hello { geometry: BoardGeometry::new(16, 12), fleet: vec![5, 4, 3, 3, 2], backend: CommitmentBackend::Poseidon, rule: AdjacencyRule::NoDiagonalTouch, salvo: Salvo::Fixed(3), curve: Curve::Bls12_377, system: System::Plonk, keys: vec![9u8; 32] },
+        Message::Commitment { commitment: vec![7u8; 32] },
+        Message::BoardProof { proof: Proof::default() },
+        Message::Shot { target: 300 },
+        Message::ShotAnswer { hit: false, proof: Proof::default(), sunk: None },
+        Message::ShotAnswer { hit: true, proof: Proof::default(), sunk: Some(Sunk { ship: 2, proof: Proof::default() }) },
+        Message::Reveal { reveal: Reveal { ships: vec![Ship { origin: 300, orientation: Orientation::Vertical }], randomness: vec![3u8; 32] } },
+        Message::Remaining { remaining: 0, proof: Proof::default() },
+        Message::Salvo { targets: vec![0, 17, 300] },
+        Message::SalvoAnswer { hits: vec![false, true, true], proof: Proof::default(), sunk: vec![(17, Sunk { ship: 4, proof: Proof::default() })] },
+    ]
+}
+
+#[test]
+fn test_message_round_trip() {
+    for message in all_messages() {
+        let mut bytes = vec![];
+        write_message(&mut bytes, &message).unwrap();
+
+        // length prefix covers the rest of the frame
+        assert_eq!(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize, bytes.len() - 4);
+        assert_eq!(bytes[4], PROTOCOL_VERSION);
+
+        let decoded = read_message(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    // proofs on bls12-377 go over the wire the same way
+    let message = Message::<Groth16<ark_bls12_377::Bls12_377>>::Remaining { remaining: 0, proof: Proof::default() };
+    let mut bytes = vec![];
+    write_message(&mut bytes, &message).unwrap();
+    assert_eq!(read_message::<Groth16<ark_bls12_377::Bls12_377>, _>(&mut bytes.as_slice()).unwrap(), message);
+}
+
+#[test]
+fn test_rejects_bad_frames() {
+    let mut bytes = vec![];
+    write_message(&mut bytes, &Message::<Groth16<Bls12_381>>::Shot { target: 3 }).unwrap();
+
+    // wrong version
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] = PROTOCOL_VERSION + 1;
+    assert!(read_message::<Groth16<Bls12_381>, _>(&mut wrong_version.as_slice()).is_err());
+
+    // unknown tag
+    let mut unknown_tag = bytes.clone();
+    unknown_tag[5] = 200;
+    assert!(read_message::<Groth16<Bls12_381>, _>(&mut unknown_tag.as_slice()).is_err());
+
+    // truncated
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(read_message::<Groth16<Bls12_381>, _>(&mut &truncated[..]).is_err());
+
+    // trailing bytes inside the frame
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    trailing[3] += 1;
+    assert!(read_message::<Groth16<Bls12_381>, _>(&mut trailing.as_slice()).is_err());
+
+    // oversized
+    let oversized = (MAX_FRAME_LEN + 1).to_be_bytes();
+    assert!(read_message::<Groth16<Bls12_381>, _>(&mut &oversized[..]).is_err());
+}
+
+#[test]
+fn test_messages_over_tcp() {
+    use std::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let sender = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        for message in all_messages() {
+            write_message(&mut stream, &message).unwrap();
+        }
+    });
+
+    let (mut stream, _) = listener.accept().unwrap();
+    for message in all_messages() {
+        assert_eq!(read_message::<Groth16<Bls12_381>, _>(&mut stream).unwrap(), message);
+    }
+    sender.join().unwrap();
+}
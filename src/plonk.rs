@@ -0,0 +1,565 @@
+use crate::common::System;
+use crate::error::GameError;
+use crate::proof_system::ProofSystem;
+use ark_ec::msm::{FixedBaseMSM, VariableBaseMSM};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{batch_inversion, FftField, Field, One, PrimeField, UniformRand, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use blake2::{Blake2s, Digest};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+
+/**
+*   plonk with kzg commitments on the pairing engine, a snark with a universal setup: one
+*   structured reference string, the powers of a secret τ, serves every circuit up to its
+*   size, and a circuit's keys are computed from it without any randomness. so once the
+*   string is made, anyone can index the keys for any board geometry, fleet and commitment
+*   and get the same keys as everyone else.
+*
+*   the game's circuits are r1cs, written for groth16. indexing turns each constraint
+*   ⟨a, z⟩·⟨b, z⟩ = ⟨c, z⟩ into plonk gates q_m·a·b + q_l·a + q_r·b + q_o·c + q_c = 0:
+*   every linear combination of more than one variable is summed two terms at a time by
+*   addition gates into a new variable, and one multiplication gate then relates the three
+*   sums. the wiring between gates, a variable used in several places, is checked by the
+*   permutation argument. the public inputs take the first rows, followed by a row that
+*   pins r1cs's constant variable to 1.
+*
+*   the prover follows the plonk paper without its linearisation: it opens every committed
+*   polynomial at the challenge ζ (and the grand product at ζω), and the verifier checks
+*   the gate, copy and grand product identities on the opened values directly, then checks
+*   all the openings with two batched kzg proofs in one pairing equation. the wire, grand
+*   product and quotient polynomials are blinded, so the openings reveal nothing about
+*   the witness.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Plonk<E: PairingEngine>(PhantomData<E>);
+
+/// the fewest gates a circuit is padded to, so the quotient's degree stays below 4n
+const MIN_GATES: usize = 8;
+/// polynomials opened at ζ: the three wires, five selectors, three permutations, the grand product and three quotient parts
+const OPENINGS: usize = 15;
+
+/**
+*   the universal parameters: τ^i·g for i up to the largest circuit's gates plus two, and
+*   τ·h. whoever makes them knows τ and can forge proofs for every circuit, so they are
+*   made once by someone trusted
+*/
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Srs<E: PairingEngine> {
+    pub powers: Vec<E::G1Affine>,
+    pub h: E::G2Affine,
+    pub tau_h: E::G2Affine,
+}
+
+impl<E: PairingEngine> Srs<E> {
+    /// the most gates a circuit can have to be indexed from these parameters
+    pub fn max_gates(&self) -> usize {
+        let n = self.powers.len().saturating_sub(3);
+        if n.is_power_of_two() { n } else { n.checked_next_power_of_two().map_or(0, |n| n / 2) }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct VerifyingKey<E: PairingEngine> {
+    /// the number of gates, a power of two
+    pub gates: u64,
+    pub inputs: u64,
+    /// commitments to q_m, q_l, q_r, q_o and q_c
+    pub selectors: Vec<E::G1Affine>,
+    /// commitments to the three permutation polynomials
+    pub sigmas: Vec<E::G1Affine>,
+    pub g: E::G1Affine,
+    pub h: E::G2Affine,
+    pub tau_h: E::G2Affine,
+}
+
+/// the verifying key with the digest every proof's challenges start from
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreparedVerifyingKey<E: PairingEngine> {
+    pub vk: VerifyingKey<E>,
+    digest: Vec<u8>,
+}
+
+/// the verifying key, the powers a proof needs and the selector and permutation polynomials
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProvingKey<E: PairingEngine> {
+    pub vk: VerifyingKey<E>,
+    pub powers: Vec<E::G1Affine>,
+    pub selectors: Vec<Vec<E::Fr>>,
+    pub sigmas: Vec<Vec<E::Fr>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Proof<E: PairingEngine> {
+    /// the wires, the grand product and the quotient's three parts
+    pub commitments: Vec<E::G1Affine>,
+    /// every polynomial at ζ, in the order they are opened
+    pub evaluations: Vec<E::Fr>,
+    /// the grand product at ζω
+    pub shifted: E::Fr,
+    /// the batched opening proofs at ζ and ζω
+    pub opening: E::G1Affine,
+    pub shifted_opening: E::G1Affine,
+}
+
+/// a gate's five selectors and its three wires, None for a wire the gate doesn't use
+struct Gate<F> {
+    selectors: [F; 5],
+    wires: [Option<usize>; 3],
+}
+
+/**
+*   an r1cs circuit as plonk gates. its variables are the r1cs ones followed by the partial
+*   sums of linear combinations, and their values are worked out alongside when proving
+*/
+struct Layout<F> {
+    inputs: usize,
+    gates: Vec<Gate<F>>,
+    variables: usize,
+    values: Option<Vec<F>>,
+}
+
+impl<F: PrimeField> Layout<F> {
+    fn new(matrices: &ConstraintMatrices<F>, assignment: Option<Vec<F>>) -> Layout<F> {
+        let mut layout = Layout {
+            inputs: matrices.num_instance_variables - 1,
+            gates: vec![],
+            variables: matrices.num_instance_variables + matrices.num_witness_variables,
+            values: assignment,
+        };
+        let (zero, one) = (F::zero(), F::one());
+        for input in 1..matrices.num_instance_variables {
+            layout.gates.push(Gate { selectors: [zero, one, zero, zero, zero], wires: [Some(input), None, None] });
+        }
+        layout.gates.push(Gate { selectors: [zero, one, zero, zero, -one], wires: [Some(0), None, None] });
+
+        for ((a, b), c) in matrices.a.iter().zip(&matrices.b).zip(&matrices.c) {
+            let (a_coeff, a) = layout.reduce(a);
+            let (b_coeff, b) = layout.reduce(b);
+            let (c_coeff, c) = layout.reduce(c);
+            layout.gates.push(Gate { selectors: [a_coeff * b_coeff, zero, zero, -c_coeff, zero], wires: [Some(a), Some(b), Some(c)] });
+        }
+        layout
+    }
+
+    /// a linear combination as a multiple of one variable, adding gates to sum its terms
+    fn reduce(&mut self, combination: &[(F, usize)]) -> (F, usize) {
+        let mut terms = combination.iter();
+        let mut sum = match terms.next() {
+            Some(term) => *term,
+            None => return (F::zero(), 0),
+        };
+        for (coeff, variable) in terms {
+            let total = self.variables;
+            self.variables += 1;
+            if let Some(values) = &mut self.values {
+                let value = sum.0 * values[sum.1] + *coeff * values[*variable];
+                values.push(value);
+            }
+            self.gates.push(Gate { selectors: [F::zero(), sum.0, *coeff, -F::one(), F::zero()], wires: [Some(sum.1), Some(*variable), Some(total)] });
+            sum = (F::one(), total);
+        }
+        sum
+    }
+
+    /// the number of gates once padded, a power of two
+    fn size(&self) -> usize {
+        self.gates.len().max(MIN_GATES).next_power_of_two()
+    }
+
+    /// each wire's position in the next place its variable is used, a cycle per variable
+    fn permutation(&self, n: usize) -> Vec<usize> {
+        let mut uses = vec![vec![]; self.variables];
+        for (row, gate) in self.gates.iter().enumerate() {
+            for (column, wire) in gate.wires.iter().enumerate() {
+                if let Some(variable) = wire {
+                    uses[*variable].push(column * n + row);
+                }
+            }
+        }
+        let mut sigma: Vec<usize> = (0..3 * n).collect();
+        for cycle in uses {
+            for (i, position) in cycle.iter().enumerate() {
+                sigma[*position] = cycle[(i + 1) % cycle.len()];
+            }
+        }
+        sigma
+    }
+}
+
+/// the circuit's r1cs matrices and, when proving, the assignment to its variables
+fn synthesize<F: PrimeField, C: ConstraintSynthesizer<F>>(circuit: C, proving: bool) -> Result<(ConstraintMatrices<F>, Option<Vec<F>>), GameError> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    if !proving {
+        cs.set_mode(SynthesisMode::Setup);
+    }
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    let matrices = cs.to_matrices().ok_or_else(|| GameError::Proving("the circuit has no matrices".to_string()))?;
+    let assignment = match proving {
+        true => cs.borrow().map(|cs| [cs.instance_assignment.as_slice(), cs.witness_assignment.as_slice()].concat()),
+        false => None,
+    };
+    Ok((matrices, assignment))
+}
+
+fn evaluation_domain<F: FftField>(n: usize) -> Result<Radix2EvaluationDomain<F>, GameError> {
+    Radix2EvaluationDomain::new(n).ok_or_else(|| GameError::Proving(format!("no evaluation domain of {} elements", n)))
+}
+
+/// the cosets the three wire columns' positions are labelled with: 1, k and k² times the domain
+fn cosets<F: FftField>() -> [F; 3] {
+    let k = F::multiplicative_generator();
+    [F::one(), k, k.square()]
+}
+
+fn commit<E: PairingEngine>(powers: &[E::G1Affine], coeffs: &[E::Fr]) -> Result<E::G1Affine, GameError> {
+    if coeffs.len() > powers.len() {
+        return Err(GameError::Proving("a polynomial is too large for the parameters".to_string()));
+    }
+    let scalars: Vec<_> = coeffs.iter().map(|coeff| coeff.into_repr()).collect();
+    Ok(VariableBaseMSM::multi_scalar_mul(&powers[..scalars.len()], &scalars).into_affine())
+}
+
+fn evaluate<F: Field>(coeffs: &[F], point: F) -> F {
+    coeffs.iter().rev().fold(F::zero(), |acc, coeff| acc * point + coeff)
+}
+
+/// (p(X) - p(point)) / (X - point)
+fn divide_by_linear<F: Field>(coeffs: &[F], point: F) -> Vec<F> {
+    let mut quotient = vec![F::zero(); coeffs.len().saturating_sub(1)];
+    let mut carry = F::zero();
+    for i in (1..coeffs.len()).rev() {
+        carry = coeffs[i] + carry * point;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+/// adds (b_0 + b_1·X + ..)·(X^n - 1), which vanishes on the domain, with random b_i
+fn blind<F: Field, R: RngCore + CryptoRng>(mut coeffs: Vec<F>, n: usize, count: usize, rng: &mut R) -> Vec<F> {
+    coeffs.resize(n + count, F::zero());
+    for i in 0..count {
+        let b = F::rand(rng);
+        coeffs[i] -= b;
+        coeffs[n + i] += b;
+    }
+    coeffs
+}
+
+/**
+*   the fiat-shamir challenges, a running blake2s hash of the verifying key, the public
+*   inputs and everything the prover has sent so far
+*/
+struct Challenges {
+    state: Vec<u8>,
+}
+
+impl Challenges {
+    fn new<F: PrimeField>(digest: &[u8], inputs: &[F]) -> Result<Challenges, GameError> {
+        let mut challenges = Challenges { state: digest.to_vec() };
+        challenges.absorb(&inputs.to_vec())?;
+        Ok(challenges)
+    }
+
+    fn absorb<T: CanonicalSerialize>(&mut self, item: &T) -> Result<(), GameError> {
+        let mut bytes = self.state.clone();
+        item.serialize(&mut bytes)?;
+        self.state = Blake2s::digest(&bytes).to_vec();
+        Ok(())
+    }
+
+    /// a field element from 64 bytes of hash, so reducing it is as good as uniform
+    fn challenge<F: PrimeField>(&mut self) -> F {
+        let hash = |tag: u8| Blake2s::digest(&[self.state.as_slice(), &[tag]].concat()).to_vec();
+        let challenge = F::from_le_bytes_mod_order(&[hash(0), hash(1)].concat());
+        self.state = hash(2);
+        challenge
+    }
+}
+
+fn digest<E: PairingEngine>(vk: &VerifyingKey<E>) -> Result<Vec<u8>, GameError> {
+    let mut bytes = vec![];
+    vk.serialize(&mut bytes)?;
+    Ok(Blake2s::digest(&bytes).to_vec())
+}
+
+impl<E: PairingEngine> ProofSystem for Plonk<E> {
+    type Field = E::Fr;
+    type Parameters = Srs<E>;
+    type ProvingKey = ProvingKey<E>;
+    type VerifyingKey = VerifyingKey<E>;
+    type PreparedVerifyingKey = PreparedVerifyingKey<E>;
+    type Proof = Proof<E>;
+
+    const SYSTEM: System = System::Plonk;
+    const UNIVERSAL: bool = true;
+
+    /// the powers of a fresh τ, for circuits of up to `max_constraints` plonk gates
+    fn universal_setup<R: RngCore + CryptoRng>(max_constraints: usize, rng: &mut R) -> Result<Srs<E>, GameError> {
+        let n = max_constraints.max(MIN_GATES).next_power_of_two();
+        let tau = E::Fr::rand(rng);
+        let mut powers = Vec::with_capacity(n + 3);
+        let mut power = E::Fr::one();
+        for _ in 0..n + 3 {
+            powers.push(power);
+            power *= tau;
+        }
+
+        let scalar_bits = E::Fr::size_in_bits();
+        let window = FixedBaseMSM::get_mul_window_size(powers.len());
+        let table = FixedBaseMSM::get_window_table(scalar_bits, window, E::G1Projective::prime_subgroup_generator());
+        let powers = FixedBaseMSM::multi_scalar_mul::<E::G1Projective>(scalar_bits, window, &table, &powers);
+        let h = E::G2Affine::prime_subgroup_generator();
+        Ok(Srs { powers: E::G1Projective::batch_normalization_into_affine(&powers), h, tau_h: h.mul(tau.into_repr()).into_affine() })
+    }
+
+    /// the circuit's keys, computed from the parameters alone, so the rng isn't used
+    fn index<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(srs: &Srs<E>, circuit: C, _: &mut R)
+        -> Result<(ProvingKey<E>, VerifyingKey<E>), GameError> {
+        let (matrices, _) = synthesize(circuit, false)?;
+        let layout = Layout::new(&matrices, None);
+        let n = layout.size();
+        if n + 3 > srs.powers.len() {
+            return Err(GameError::BadInput(format!("the universal parameters cover circuits of up to {} gates, this one needs {}", srs.max_gates(), n)));
+        }
+        let domain = evaluation_domain::<E::Fr>(n)?;
+
+        let mut selectors = vec![vec![E::Fr::zero(); n]; 5];
+        for (row, gate) in layout.gates.iter().enumerate() {
+            for (selector, value) in selectors.iter_mut().zip(gate.selectors) {
+                selector[row] = value;
+            }
+        }
+        let elements: Vec<E::Fr> = domain.elements().collect();
+        let cosets = cosets::<E::Fr>();
+        let sigma = layout.permutation(n);
+        let sigmas: Vec<Vec<E::Fr>> = (0..3).map(|column| (0..n).map(|row| {
+            let position = sigma[column * n + row];
+            cosets[position / n] * elements[position % n]
+        }).collect()).collect();
+
+        let powers = srs.powers[..n + 3].to_vec();
+        let selectors: Vec<Vec<E::Fr>> = selectors.iter().map(|evals| domain.ifft(evals)).collect();
+        let sigmas: Vec<Vec<E::Fr>> = sigmas.iter().map(|evals| domain.ifft(evals)).collect();
+        let vk = VerifyingKey {
+            gates: n as u64,
+            inputs: layout.inputs as u64,
+            selectors: selectors.iter().map(|coeffs| commit::<E>(&powers, coeffs)).collect::<Result<_, _>>()?,
+            sigmas: sigmas.iter().map(|coeffs| commit::<E>(&powers, coeffs)).collect::<Result<_, _>>()?,
+            g: srs.powers[0],
+            h: srs.h,
+            tau_h: srs.tau_h,
+        };
+        Ok((ProvingKey { vk: vk.clone(), powers, selectors, sigmas }, vk))
+    }
+
+    fn prepare(vk: &VerifyingKey<E>) -> PreparedVerifyingKey<E> {
+        // the key was read or made by us, so it serializes
+        PreparedVerifyingKey { vk: vk.clone(), digest: digest(vk).expect("writing to a vec can't fail") }
+    }
+
+    fn verifying_key(pk: &ProvingKey<E>) -> &VerifyingKey<E> {
+        &pk.vk
+    }
+
+    fn unprepared(pvk: &PreparedVerifyingKey<E>) -> &VerifyingKey<E> {
+        &pvk.vk
+    }
+
+    fn prove<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(pk: &ProvingKey<E>, circuit: C, rng: &mut R) -> Result<Proof<E>, GameError> {
+        let (matrices, assignment) = synthesize(circuit, true)?;
+        let layout = Layout::new(&matrices, assignment);
+        let n = pk.vk.gates as usize;
+        let values = match layout.values {
+            Some(ref values) if layout.size() == n && layout.inputs as u64 == pk.vk.inputs => values,
+            _ => return Err(GameError::Proving("the circuit doesn't match the proving key".to_string())),
+        };
+        let domain = evaluation_domain::<E::Fr>(n)?;
+        let elements: Vec<E::Fr> = domain.elements().collect();
+        let cosets = cosets::<E::Fr>();
+        let mut challenges = Challenges::new(&digest(&pk.vk)?, &values[1..=layout.inputs])?;
+
+        // the wires, blinded with two random terms each
+        let wire_evals: Vec<Vec<E::Fr>> = (0..3).map(|column| (0..n).map(|row| {
+            layout.gates.get(row).and_then(|gate| gate.wires[column]).map_or(E::Fr::zero(), |variable| values[variable])
+        }).collect()).collect();
+        let wires: Vec<Vec<E::Fr>> = wire_evals.iter().map(|evals| blind(domain.ifft(evals), n, 2, rng)).collect();
+        let mut commitments = wires.iter().map(|wire| commit::<E>(&pk.powers, wire)).collect::<Result<Vec<_>, _>>()?;
+        challenges.absorb(&commitments)?;
+        let beta: E::Fr = challenges.challenge();
+        let gamma: E::Fr = challenges.challenge();
+
+        // the grand product of the copy constraints, blinded with three terms
+        let sigma_evals: Vec<Vec<E::Fr>> = pk.sigmas.iter().map(|coeffs| domain.fft(coeffs)).collect();
+        let mut denominators: Vec<E::Fr> = (0..n).map(|row| {
+            (0..3).map(|column| wire_evals[column][row] + beta * sigma_evals[column][row] + gamma).product()
+        }).collect();
+        batch_inversion(&mut denominators);
+        let mut product = vec![E::Fr::one()];
+        for row in 0..n - 1 {
+            let numerator: E::Fr = (0..3).map(|column| wire_evals[column][row] + beta * cosets[column] * elements[row] + gamma).product();
+            product.push(product[row] * numerator * denominators[row]);
+        }
+        let z = blind(domain.ifft(&product), n, 3, rng);
+        commitments.push(commit::<E>(&pk.powers, &z)?);
+        challenges.absorb(&commitments[3])?;
+        let alpha: E::Fr = challenges.challenge();
+
+        // the quotient, worked out on a coset four times the size of the domain
+        let big = evaluation_domain::<E::Fr>(4 * n)?;
+        let coset = |coeffs: &[E::Fr]| big.coset_fft(coeffs);
+        let omega = domain.group_gen;
+        let shifted_z: Vec<E::Fr> = z.iter().scan(E::Fr::one(), |power, coeff| {
+            let shifted = *coeff * *power;
+            *power *= omega;
+            Some(shifted)
+        }).collect();
+        let mut inputs = vec![E::Fr::zero(); n];
+        for (row, input) in values[1..=layout.inputs].iter().enumerate() {
+            inputs[row] = -*input;
+        }
+        let mut first = vec![E::Fr::zero(); n];
+        first[0] = E::Fr::one();
+
+        let [a, b, c] = [coset(&wires[0]), coset(&wires[1]), coset(&wires[2])];
+        let (z_big, shifted_big) = (coset(&z), coset(&shifted_z));
+        let selectors: Vec<Vec<E::Fr>> = pk.selectors.iter().map(|coeffs| coset(coeffs)).collect();
+        let sigmas: Vec<Vec<E::Fr>> = pk.sigmas.iter().map(|coeffs| coset(coeffs)).collect();
+        let (inputs, first) = (coset(&domain.ifft(&inputs)), coset(&domain.ifft(&first)));
+
+        // X^n - 1 on the coset only takes four values, the offset^n times each fourth root of unity
+        let offset = E::Fr::multiplicative_generator();
+        let root = big.group_gen.pow([n as u64]);
+        let mut vanishing: Vec<E::Fr> = (0..4).map(|i| offset.pow([n as u64]) * root.pow([i]) - E::Fr::one()).collect();
+        batch_inversion(&mut vanishing);
+
+        let mut x = offset;
+        let mut quotient = Vec::with_capacity(4 * n);
+        for i in 0..4 * n {
+            let gate = selectors[0][i] * a[i] * b[i] + selectors[1][i] * a[i] + selectors[2][i] * b[i] + selectors[3][i] * c[i] + selectors[4][i] + inputs[i];
+            let identity = (a[i] + beta * x + gamma) * (b[i] + beta * cosets[1] * x + gamma) * (c[i] + beta * cosets[2] * x + gamma) * z_big[i];
+            let copied = (a[i] + beta * sigmas[0][i] + gamma) * (b[i] + beta * sigmas[1][i] + gamma) * (c[i] + beta * sigmas[2][i] + gamma) * shifted_big[i];
+            let start = (z_big[i] - E::Fr::one()) * first[i];
+            quotient.push((gate + alpha * (identity - copied) + alpha.square() * start) * vanishing[i % 4]);
+            x *= big.group_gen;
+        }
+        let quotient = big.coset_ifft(&quotient);
+
+        // split into three parts of n + 2 coefficients, blinded so only their sum is fixed
+        let mut parts: Vec<Vec<E::Fr>> = quotient[..3 * n + 6].chunks(n + 2).map(|part| part.to_vec()).collect();
+        for i in 0..2 {
+            let b = E::Fr::rand(rng);
+            parts[i].push(b);
+            parts[i + 1][0] -= b;
+        }
+        for part in &parts {
+            commitments.push(commit::<E>(&pk.powers, part)?);
+        }
+        challenges.absorb(&commitments[4..].to_vec())?;
+        let zeta: E::Fr = challenges.challenge();
+
+        // open everything at ζ and the grand product at ζω
+        let mut polynomials: Vec<&[E::Fr]> = wires.iter().map(|wire| wire.as_slice()).collect();
+        polynomials.extend(pk.selectors.iter().map(|selector| selector.as_slice()));
+        polynomials.extend(pk.sigmas.iter().map(|sigma| sigma.as_slice()));
+        polynomials.push(&z);
+        polynomials.extend(parts.iter().map(|part| part.as_slice()));
+        let evaluations: Vec<E::Fr> = polynomials.iter().map(|polynomial| evaluate(polynomial, zeta)).collect();
+        let shifted = evaluate(&z, zeta * omega);
+        challenges.absorb(&evaluations)?;
+        challenges.absorb(&shifted)?;
+        let v: E::Fr = challenges.challenge();
+
+        let mut combined = vec![E::Fr::zero(); n + 3];
+        let mut power = E::Fr::one();
+        for polynomial in polynomials {
+            for (sum, coeff) in combined.iter_mut().zip(polynomial) {
+                *sum += power * coeff;
+            }
+            power *= v;
+        }
+        Ok(Proof {
+            commitments,
+            evaluations,
+            shifted,
+            opening: commit::<E>(&pk.powers, &divide_by_linear(&combined, zeta))?,
+            shifted_opening: commit::<E>(&pk.powers, &divide_by_linear(&z, zeta * omega))?,
+        })
+    }
+
+    fn verify(pvk: &PreparedVerifyingKey<E>, inputs: &[E::Fr], proof: &Proof<E>) -> Result<bool, GameError> {
+        let vk = &pvk.vk;
+        if inputs.len() as u64 != vk.inputs || vk.selectors.len() != 5 || vk.sigmas.len() != 3 {
+            return Err(SynthesisError::MalformedVerifyingKey.into());
+        }
+        if proof.commitments.len() != 7 || proof.evaluations.len() != OPENINGS {
+            return Ok(false);
+        }
+        let n = vk.gates as usize;
+        let domain = evaluation_domain::<E::Fr>(n)?;
+        if domain.size() != n {
+            return Err(SynthesisError::MalformedVerifyingKey.into());
+        }
+
+        let mut challenges = Challenges::new(&pvk.digest, inputs)?;
+        challenges.absorb(&proof.commitments[..3].to_vec())?;
+        let beta: E::Fr = challenges.challenge();
+        let gamma: E::Fr = challenges.challenge();
+        challenges.absorb(&proof.commitments[3])?;
+        let alpha: E::Fr = challenges.challenge();
+        challenges.absorb(&proof.commitments[4..].to_vec())?;
+        let zeta: E::Fr = challenges.challenge();
+        challenges.absorb(&proof.evaluations)?;
+        challenges.absorb(&proof.shifted)?;
+        let v: E::Fr = challenges.challenge();
+        let u: E::Fr = challenges.challenge();
+
+        // the lagrange polynomials of the input rows at ζ, ω^i·(ζ^n - 1) / (n·(ζ - ω^i))
+        let vanishing = domain.evaluate_vanishing_polynomial(zeta);
+        let mut differences: Vec<E::Fr> = domain.elements().take(inputs.len().max(1)).map(|element| domain.size_as_field_element * (zeta - element)).collect();
+        if vanishing.is_zero() || differences.iter().any(|difference| difference.is_zero()) {
+            return Ok(false);
+        }
+        batch_inversion(&mut differences);
+        let lagrange: Vec<E::Fr> = domain.elements().zip(differences).map(|(element, inverse)| element * vanishing * inverse).collect();
+        let public: E::Fr = inputs.iter().zip(&lagrange).map(|(input, basis)| -*input * basis).sum();
+
+        let e = &proof.evaluations;
+        let (a, b, c, z) = (e[0], e[1], e[2], e[11]);
+        let cosets = cosets::<E::Fr>();
+        let gate = e[3] * a * b + e[4] * a + e[5] * b + e[6] * c + e[7] + public;
+        let identity = (a + beta * zeta + gamma) * (b + beta * cosets[1] * zeta + gamma) * (c + beta * cosets[2] * zeta + gamma) * z;
+        let copied = (a + beta * e[8] + gamma) * (b + beta * e[9] + gamma) * (c + beta * e[10] + gamma) * proof.shifted;
+        let start = (z - E::Fr::one()) * lagrange[0];
+        let shift = zeta.pow([n as u64 + 2]);
+        let quotient = e[12] + shift * e[13] + shift.square() * e[14];
+        if gate + alpha * (identity - copied) + alpha.square() * start != quotient * vanishing {
+            return Ok(false);
+        }
+
+        // both batched openings at once: e(W + u·W', τ·h) = e(ζ·W + uζω·W' + F - E·g, h)
+        let mut committed: Vec<E::G1Affine> = proof.commitments[..3].to_vec();
+        committed.extend(&vk.selectors);
+        committed.extend(&vk.sigmas);
+        committed.extend(&proof.commitments[3..]);
+        let mut weights = vec![];
+        let mut power = E::Fr::one();
+        for _ in 0..OPENINGS {
+            weights.push(power);
+            power *= v;
+        }
+        let claimed: E::Fr = weights.iter().zip(e).map(|(weight, evaluation)| *weight * evaluation).sum::<E::Fr>() + u * proof.shifted;
+        let shifted_zeta = zeta * domain.group_gen;
+        let mut points = committed;
+        points.extend([proof.commitments[3], vk.g, proof.opening, proof.shifted_opening]);
+        weights.extend([u, -claimed, zeta, u * shifted_zeta]);
+        let weights: Vec<_> = weights.iter().map(|weight| weight.into_repr()).collect();
+        let right = VariableBaseMSM::multi_scalar_mul(&points, &weights);
+        let left = proof.opening.into_projective() + proof.shifted_opening.mul(u.into_repr());
+        Ok(E::pairing(left, vk.tau_h) == E::pairing(right, vk.h))
+    }
+}
@@ -0,0 +1,182 @@
+use crate::common::System;
+use crate::error::GameError;
+use ark_ec::PairingEngine;
+use ark_ff::PrimeField;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{CryptoRng, RngCore};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+/**
+*   a zk-snark the game's circuits are proven with.
+*
+*   making keys for a circuit is split in two. `universal_setup` makes the parameters
+*   shared by every circuit up to a number of constraints, and `index` turns them into the
+*   proving and verifying keys of one circuit. groth16 shares nothing between circuits, so
+*   its parameters are `()` and all of its trusted randomness goes into `index`, which is
+*   why `setup` has to run again for every board geometry, fleet and commitment. a
+*   universal-setup system such as `Plonk` does its trusted work once in `universal_setup`
+*   and `index` is a deterministic function of the parameters and the circuit, so one set
+*   of parameters serves every game shape.
+*
+*   the game, its keys, transcripts, saves and messages are generic over the system, see
+*   GameSystem, so a game is played the same way whichever one it was set up with. the
+*   systems themselves are marker types, comparable so the types generic over them can derive
+*   Clone, Debug and PartialEq.
+*/
+pub trait ProofSystem: Clone + Debug + PartialEq {
+    /// the field the circuits are written over
+    type Field: PrimeField;
+    /// shared by every circuit, `()` for a system with a setup per circuit
+    type Parameters;
+    type ProvingKey: CanonicalSerialize + CanonicalDeserialize + Clone;
+    type VerifyingKey: CanonicalSerialize + CanonicalDeserialize + Clone + Debug + PartialEq;
+    /// the verifying key with whatever can be computed once before verifying many proofs
+    type PreparedVerifyingKey;
+    type Proof: CanonicalSerialize + CanonicalDeserialize + Clone + Debug + PartialEq;
+
+    /// which system it is, written into the game's settings
+    const SYSTEM: System;
+    /// whether `universal_setup` makes parameters that serve more than one circuit
+    const UNIVERSAL: bool;
+
+    /**
+    *   the parameters for circuits of up to this many constraints. whoever runs it could
+    *   forge proofs for every circuit indexed from them
+    */
+    fn universal_setup<R: RngCore + CryptoRng>(max_constraints: usize, rng: &mut R) -> Result<Self::Parameters, GameError>;
+
+    /**
+    *   the proving and verifying keys of the circuit, which only needs its shape filled in.
+    *   a system with a setup per circuit draws its trusted randomness from the rng here
+    */
+    fn index<C: ConstraintSynthesizer<Self::Field>, R: RngCore + CryptoRng>(parameters: &Self::Parameters, circuit: C, rng: &mut R)
+        -> Result<(Self::ProvingKey, Self::VerifyingKey), GameError>;
+
+    fn prepare(vk: &Self::VerifyingKey) -> Self::PreparedVerifyingKey;
+
+    /// the verifying key that goes with a proving key
+    fn verifying_key(pk: &Self::ProvingKey) -> &Self::VerifyingKey;
+
+    /// the verifying key a prepared key was made from
+    fn unprepared(pvk: &Self::PreparedVerifyingKey) -> &Self::VerifyingKey;
+
+    fn prove<C: ConstraintSynthesizer<Self::Field>, R: RngCore + CryptoRng>(pk: &Self::ProvingKey, circuit: C, rng: &mut R) -> Result<Self::Proof, GameError>;
+
+    /// whether the proof verifies for these public inputs, an error if the key can't take them
+    fn verify(pvk: &Self::PreparedVerifyingKey, inputs: &[Self::Field], proof: &Self::Proof) -> Result<bool, GameError>;
+}
+
+/**
+*   groth16 from `ark-groth16` on the pairing engine. its proofs are the smallest and
+*   quickest to verify, at the price of a trusted setup for every circuit
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Groth16<E: PairingEngine>(PhantomData<E>);
+
+impl<E: PairingEngine> ProofSystem for Groth16<E> {
+    type Field = E::Fr;
+    type Parameters = ();
+    type ProvingKey = ark_groth16::ProvingKey<E>;
+    type VerifyingKey = ark_groth16::VerifyingKey<E>;
+    type PreparedVerifyingKey = ark_groth16::PreparedVerifyingKey<E>;
+    type Proof = ark_groth16::Proof<E>;
+
+    const SYSTEM: System = System::Groth16;
+    const UNIVERSAL: bool = false;
+
+    fn universal_setup<R: RngCore + CryptoRng>(_: usize, _: &mut R) -> Result<(), GameError> {
+        Ok(())
+    }
+
+    fn index<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(_: &(), circuit: C, rng: &mut R)
+        -> Result<(Self::ProvingKey, Self::VerifyingKey), GameError> {
+        let pk = ark_groth16::generate_random_parameters::<E, _, _>(circuit, rng)?;
+        let vk = pk.vk.clone();
+        Ok((pk, vk))
+    }
+
+    fn prepare(vk: &Self::VerifyingKey) -> Self::PreparedVerifyingKey {
+        ark_groth16::prepare_verifying_key(vk)
+    }
+
+    fn verifying_key(pk: &Self::ProvingKey) -> &Self::VerifyingKey {
+        &pk.vk
+    }
+
+    fn unprepared(pvk: &Self::PreparedVerifyingKey) -> &Self::VerifyingKey {
+        &pvk.vk
+    }
+
+    fn prove<C: ConstraintSynthesizer<E::Fr>, R: RngCore + CryptoRng>(pk: &Self::ProvingKey, circuit: C, rng: &mut R) -> Result<Self::Proof, GameError> {
+        Ok(ark_groth16::create_random_proof(circuit, pk, rng)?)
+    }
+
+    fn verify(pvk: &Self::PreparedVerifyingKey, inputs: &[E::Fr], proof: &Self::Proof) -> Result<bool, GameError> {
+        Ok(ark_groth16::verify_proof(pvk, proof, inputs)?)
+    }
+}
+
+/**
+*   runs a shot circuit through every step of the proof system, so a new one can be tried
+*   on the game's circuits by calling this with it
+*/
+#[cfg(test)]
+fn check_shot_proofs<S: ProofSystem<Field = ark_bls12_381::Fr>>() {
+    use crate::commitment::CommitmentBackend;
+    use crate::common::*;
+    use crate::constraints::ShotVerifier;
+    use crate::game::generate_commitment;
+    use ark_bls12_381::Fr;
+    use rand::rngs::OsRng;
+
+    let geometry = BoardGeometry::square(3);
+    let board = vec![0, 1, 0, 0, 1, 0, 2, 0, 0];
+    let randomness = vec![7u8; 32];
+    let backend = CommitmentBackend::Poseidon;
+    let commitment = generate_commitment(Curve::Bls12_381, backend, &board, &randomness).unwrap();
+    let circuit = |target: u16| ShotVerifier {
+        geometry,
+        target,
+        hit: (board[target as usize] != 0) as u8,
+        commitment: commitment.clone(),
+        backend,
+
+        rng_in: Some(randomness.clone()),
+        board: Some(board.clone()),
+    };
+
+    let parameters = S::universal_setup(1 << 16, &mut OsRng).unwrap();
+    let (pk, vk) = S::index(&parameters, circuit(0), &mut OsRng).unwrap();
+    let pvk = S::prepare(&vk);
+
+    let proof = S::prove(&pk, circuit(1), &mut OsRng).unwrap();
+    let mut inputs = vec![Fr::from(3u8), Fr::from(3u8), Fr::from(1u8), Fr::from(1u8)];
+    inputs.append(&mut backend.to_inputs(&commitment).unwrap());
+    assert!(S::verify(&pvk, &inputs, &proof).unwrap());
+
+    // the answer is bound to the proof
+    inputs[3] = Fr::from(0u8);
+    assert!(!S::verify(&pvk, &inputs, &proof).unwrap());
+
+    // and keys and proofs survive serialization
+    let mut bytes = vec![];
+    vk.serialize(&mut bytes).unwrap();
+    proof.serialize(&mut bytes).unwrap();
+    let mut reader = bytes.as_slice();
+    let vk = S::VerifyingKey::deserialize(&mut reader).unwrap();
+    let proof = S::Proof::deserialize(&mut reader).unwrap();
+    inputs[3] = Fr::from(1u8);
+    assert!(S::verify(&S::prepare(&vk), &inputs, &proof).unwrap());
+}
+
+#[test]
+fn test_groth16() {
+    check_shot_proofs::<Groth16<ark_bls12_381::Bls12_381>>();
+}
+
+#[test]
+fn test_plonk() {
+    check_shot_proofs::<crate::plonk::Plonk<ark_bls12_381::Bls12_381>>();
+}
@@ -0,0 +1,611 @@
+use crate::commitment::CommitmentBackend;
+use crate::common::*;
+use crate::error::GameError;
+use crate::game::Game;
+use crate::keys::GameKeys;
+use crate::transcript::{Outcome, Transcript};
+use ark_bls12_377::Bls12_377;
+use ark_bls12_377_04::constraints::PairingVar;
+use ark_bw6_761::BW6_761;
+use ark_crypto_primitives_04::snark::{BooleanInputVar, SNARKGadget};
+use ark_ec::short_weierstrass_jacobian::GroupAffine;
+use ark_ec::SWModelParameters;
+use ark_ec_04::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::PrimeField;
+use ark_ff_04::PrimeField as _;
+use ark_groth16_04::constraints::{Groth16VerifierGadget, PreparedVerifyingKeyVar, ProofVar};
+use ark_groth16_04::Groth16;
+use ark_r1cs_std_04::fields::fp::FpVar;
+use ark_r1cs_std_04::prelude::*;
+use ark_relations_04::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_serialize_04::{CanonicalDeserialize as _, CanonicalSerialize as _, Valid};
+use ark_snark_04::{CircuitSpecificSetupSNARK, SNARK};
+use rand::{CryptoRng, RngCore};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/**
+*   folds a whole game on bls12-377 into one proof over bw6-761 that "the two committed
+*   boards hold the fleet, and this many turns of shots answered against them end with this
+*   seat winning".
+*
+*   the outer circuit verifies both board proofs and every shot proof of the game with the
+*   groth16 verifier gadget, which is cheap there because bw6-761's scalar field is
+*   bls12-377's base field. on top of them it checks what the transcript replay would: seats
+*   take turns, nobody fires at the same tile twice, and the last shot is the hit that takes
+*   the winner to every tile of the fleet while the loser never got there. the only public
+*   inputs are the winner and the two commitments, so anyone holding the outer verifying key
+*   can check a game with one pairing check, without its turns.
+*
+*   the inner verifying keys are constants of the outer circuit, so the outer keys only
+*   accept games proven with the inner keys they were made from. the number of turns is part
+*   of the circuit's shape, so there is one set of outer keys per game shape and length. only
+*   games of single shots are folded, the sunk ship and remaining ships proofs aren't needed
+*   to show who won.
+*
+*   bw6-761 and the verifier gadget come from arkworks 0.4 while the game runs on 0.3, so the
+*   inner proofs and keys are carried across coordinate by coordinate.
+*/
+/// the game's own proof system, which the folded proofs are made with
+type InnerSystem = crate::proof_system::Groth16<Bls12_377>;
+type InnerEngine = ark_bls12_377_04::Bls12_377;
+type InnerField = ark_bls12_377_04::Fr;
+type InnerProof = ark_groth16_04::Proof<InnerEngine>;
+type InnerKey = ark_groth16_04::VerifyingKey<InnerEngine>;
+/// the outer circuit's field, bw6-761's scalar field and bls12-377's base field
+pub type OuterField = ark_bw6_761::Fr;
+type Verifier = Groth16VerifierGadget<InnerEngine, PairingVar>;
+
+/// bits of an inner public input, the size of bls12-377's scalar field
+const INNER_INPUT_BITS: usize = 253;
+
+/**
+*   the outer circuit for a game of `turns.len()` single shots. the seat in the last turn
+*   fired it, so it is the winner
+*/
+#[derive(Clone)]
+pub struct GameFoldVerifier {
+    pub geometry: BoardGeometry,
+    pub fleet: Vec<u8>,
+    pub rule: AdjacencyRule,
+    pub board_vk: InnerKey,
+    pub shot_vk: InnerKey,
+
+    // public inputs, each seat's commitment as the inner public inputs it is checked as
+    pub winner: u8,
+    pub commitments: [Vec<OuterField>; 2],
+
+    // witnesses
+    pub board_proofs: [InnerProof; 2],
+    /// the target, whether it hit and the shot proof of every turn in order
+    pub turns: Vec<(u16, bool, InnerProof)>,
+}
+
+impl ConstraintSynthesizer<OuterField> for GameFoldVerifier {
+    fn generate_constraints(self, cs: ConstraintSystemRef<OuterField>) -> Result<(), SynthesisError> {
+        let last = self.turns.len().checked_sub(1).ok_or(SynthesisError::Unsatisfiable)?;
+
+        // winner then both commitments, each input checked to be an inner field element
+        let winner = FpVar::new_input(ark_relations_04::ns!(cs, "winner"), || Ok(OuterField::from(self.winner)))?;
+        winner.enforce_equal(&FpVar::constant(OuterField::from((last % 2) as u8)))?;
+        let mut commitments = vec![];
+        for inputs in &self.commitments {
+            let mut bits = vec![];
+            for input in inputs {
+                let input = FpVar::new_input(ark_relations_04::ns!(cs, "commitment"), || Ok(*input))?;
+                bits.push(inner_input_bits(&input)?);
+            }
+            commitments.push(bits);
+        }
+
+        let board_vk = PreparedVerifyingKeyVar::<InnerEngine, PairingVar>::new_constant(cs.clone(), ark_groth16_04::prepare_verifying_key(&self.board_vk))?;
+        let shot_vk = PreparedVerifyingKeyVar::<InnerEngine, PairingVar>::new_constant(cs.clone(), ark_groth16_04::prepare_verifying_key(&self.shot_vk))?;
+        let width = constant_bits(self.geometry.width as u64, 8);
+        let height = constant_bits(self.geometry.height as u64, 8);
+
+        // both boards hold the fleet under the rule
+        for (seat, proof) in self.board_proofs.iter().enumerate() {
+            let mut inputs: Vec<Vec<Boolean<OuterField>>> = self.fleet.iter().map(|length| constant_bits(*length as u64, 8)).collect();
+            inputs.push(width.clone());
+            inputs.push(height.clone());
+            inputs.push(constant_bits(self.rule.to_u8() as u64, 8));
+            inputs.extend(commitments[seat].iter().cloned());
+            let proof = ProofVar::new_witness(ark_relations_04::ns!(cs, "board proof"), || Ok(proof.clone()))?;
+            Verifier::verify_with_processed_vk(&board_vk, &BooleanInputVar::new(inputs), &proof)?.enforce_equal(&Boolean::TRUE)?;
+        }
+
+        // every answer is proven against the defender's commitment, and the hits of each
+        // seat are counted over distinct targets
+        let mut targets: [Vec<FpVar<OuterField>>; 2] = [vec![], vec![]];
+        let mut hits = [FpVar::<OuterField>::zero(), FpVar::<OuterField>::zero()];
+        for (i, (target, hit, proof)) in self.turns.iter().enumerate() {
+            let shooter = i % 2;
+            let target_bits = (0..16).map(|bit| Boolean::new_witness(ark_relations_04::ns!(cs, "target"), || Ok((target >> bit) & 1 == 1)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let hit = Boolean::new_witness(ark_relations_04::ns!(cs, "hit"), || Ok(*hit))?;
+
+            let mut inputs = vec![width.clone(), height.clone(), target_bits.clone(), vec![hit.clone()]];
+            inputs.extend(commitments[1 - shooter].iter().cloned());
+            let proof = ProofVar::new_witness(ark_relations_04::ns!(cs, "shot proof"), || Ok(proof.clone()))?;
+            Verifier::verify_with_processed_vk(&shot_vk, &BooleanInputVar::new(inputs), &proof)?.enforce_equal(&Boolean::TRUE)?;
+
+            let target = Boolean::le_bits_to_fp_var(&target_bits)?;
+            for earlier in &targets[shooter] {
+                target.enforce_not_equal(earlier)?;
+            }
+            targets[shooter].push(target);
+            hits[shooter] += FpVar::from(hit.clone());
+            if i == last {
+                hit.enforce_equal(&Boolean::TRUE)?;
+            }
+        }
+
+        // the last shot sank the last ship, and the loser never sank them all
+        let tiles = FpVar::constant(OuterField::from(self.fleet.iter().map(|length| *length as u64).sum::<u64>()));
+        hits[last % 2].enforce_equal(&tiles)?;
+        hits[1 - last % 2].enforce_not_equal(&tiles)
+    }
+}
+
+/**
+*   the bits of an inner public input, which must be below bls12-377's scalar modulus. the
+*   verifier gadget multiplies by the bits, so an input of x plus the modulus would pass
+*   as x and one commitment would have two sets of public inputs
+*/
+fn inner_input_bits(input: &FpVar<OuterField>) -> Result<Vec<Boolean<OuterField>>, SynthesisError> {
+    let mut bits = input.to_bits_le()?;
+    Boolean::enforce_smaller_or_equal_than_le(&bits, (-InnerField::from(1u8)).into_bigint())?;
+    bits.truncate(INNER_INPUT_BITS);
+    Ok(bits)
+}
+
+fn constant_bits(value: u64, bits: usize) -> Vec<Boolean<OuterField>> {
+    (0..bits).map(|bit| Boolean::constant((value >> bit) & 1 == 1)).collect()
+}
+
+fn proving<E: Display>(e: E) -> GameError {
+    GameError::Proving(e.to_string())
+}
+
+/// a bls12-377 point from arkworks 0.3 as the same point in 0.4, checked to be on the curve
+/// and in the right subgroup
+fn point<P: SWModelParameters, Q: SWCurveConfig>(point: &GroupAffine<P>) -> Result<Affine<Q>, GameError> {
+    if point.infinity {
+        return Ok(Affine::identity());
+    }
+    let mut x = vec![];
+    let mut y = vec![];
+    point.x.serialize(&mut x)?;
+    point.y.serialize(&mut y)?;
+    let point = Affine::<Q>::new_unchecked(Q::BaseField::deserialize_compressed(x.as_slice()).map_err(proving)?,
+        Q::BaseField::deserialize_compressed(y.as_slice()).map_err(proving)?);
+    point.check().map_err(proving)?;
+    Ok(point)
+}
+
+fn inner_proof(proof: &ark_groth16::Proof<Bls12_377>) -> Result<InnerProof, GameError> {
+    Ok(InnerProof { a: point(&proof.a)?, b: point(&proof.b)?, c: point(&proof.c)? })
+}
+
+fn inner_key(vk: &ark_groth16::VerifyingKey<Bls12_377>) -> Result<InnerKey, GameError> {
+    Ok(InnerKey {
+        alpha_g1: point(&vk.alpha_g1)?,
+        beta_g2: point(&vk.beta_g2)?,
+        gamma_g2: point(&vk.gamma_g2)?,
+        delta_g2: point(&vk.delta_g2)?,
+        gamma_abc_g1: vk.gamma_abc_g1.iter().map(point).collect::<Result<_, _>>()?,
+    })
+}
+
+/// a bls12-377 scalar as an element of the outer field, which is larger
+fn outer_input(input: ark_bls12_377::Fr) -> OuterField {
+    let mut bytes = vec![];
+    input.into_repr().serialize(&mut bytes).expect("writing to a vec can't fail");
+    OuterField::from_le_bytes_mod_order(&bytes)
+}
+
+/// each seat's commitment as the inner public inputs of the board and shot proofs
+fn commitment_inputs(game: &Game, commitments: [&[u8]; 2]) -> Result<[Vec<OuterField>; 2], GameError> {
+    let inputs = |commitment| -> Result<Vec<OuterField>, GameError> {
+        Ok(game.backend().to_inputs::<ark_bls12_377::Fr>(commitment)?.into_iter().map(outer_input).collect())
+    };
+    Ok([inputs(commitments[0])?, inputs(commitments[1])?])
+}
+
+/// only games of single shots proven with groth16 on bls12-377 are folded
+fn check_foldable(game: &Game) -> Result<(), GameError> {
+    if game.curve() != Curve::Bls12_377 {
+        return Err(GameError::BadInput(format!("only games on bls12-377 can be folded, not {}", game.curve().name())));
+    }
+    if game.system() != System::Groth16 {
+        return Err(GameError::BadInput(format!("only groth16 games can be folded, not {}", game.system().name())));
+    }
+    if game.salvo() != Salvo::Off {
+        return Err(GameError::BadInput("only games of single shots can be folded".to_string()));
+    }
+    Ok(())
+}
+
+/**
+*   the outer circuit's shape: the game's settings and inner verifying keys, with
+*   placeholder proofs and commitments. none of the placeholders end up in the keys
+*/
+fn placeholder_circuit(game: &Game, keys: &GameKeys<InnerSystem>, turns: usize) -> Result<GameFoldVerifier, GameError> {
+    let board_vk = inner_key(&keys.board_vk.vk)?;
+    let shot_vk = inner_key(&keys.shot_vk.vk)?;
+    let commitment = vec![OuterField::from(0u8); shot_vk.gamma_abc_g1.len().saturating_sub(5)];
+    Ok(GameFoldVerifier {
+        geometry: game.geometry(),
+        fleet: game.fleet().to_vec(),
+        rule: game.rule(),
+        board_vk,
+        shot_vk,
+
+        winner: (turns.saturating_sub(1) % 2) as u8,
+        commitments: [commitment.clone(), commitment],
+
+        board_proofs: [InnerProof::default(), InnerProof::default()],
+        turns: vec![(0, true, InnerProof::default()); turns],
+    })
+}
+
+/**
+*   the verifier gadget assumes the key takes as many inputs as it is given, so a key made
+*   for another shape is turned away before the circuit is built
+*/
+fn check_shape(circuit: &GameFoldVerifier) -> Result<(), GameError> {
+    let commitment = circuit.commitments[0].len();
+    if circuit.board_vk.gamma_abc_g1.len() != circuit.fleet.len() + 4 + commitment || circuit.shot_vk.gamma_abc_g1.len() != 5 + commitment
+        || circuit.commitments[1].len() != commitment {
+        return Err(GameError::BadInput("the inner keys weren't made for this game".to_string()));
+    }
+    if circuit.turns.is_empty() {
+        return Err(GameError::BadInput("a game with no turns has no winner".to_string()));
+    }
+    Ok(())
+}
+
+/**
+*   groth16 keys over bw6-761 for folding games of one shape and number of turns, made
+*   from the inner keys the games were played with. whoever generates them could forge
+*   folded proofs, just as with the inner keys, so like the inner keys they are made once
+*   ahead of time and written to KEY_DIR, and the players fold and check games against the
+*   copies there
+*/
+pub struct FoldKeys {
+    pub turns: usize,
+    pub pk: ark_groth16_04::ProvingKey<BW6_761>,
+    pub vk: ark_groth16_04::PreparedVerifyingKey<BW6_761>,
+}
+
+impl FoldKeys {
+    pub fn generate<R: RngCore + CryptoRng>(game: &Game, keys: &GameKeys<InnerSystem>, turns: usize, rng: &mut R) -> Result<FoldKeys, GameError> {
+        check_foldable(game)?;
+        let circuit = placeholder_circuit(game, keys, turns)?;
+        check_shape(&circuit)?;
+        let (pk, vk) = Groth16::<BW6_761>::setup(circuit, rng).map_err(proving)?;
+        Ok(FoldKeys { turns, pk, vk: ark_groth16_04::prepare_verifying_key(&vk) })
+    }
+
+    /**
+    *   writes the keys to dir, named for the game's shape and number of turns, and returns
+    *   the paths written
+    */
+    pub fn save(&self, dir: &Path, game: &Game) -> Result<Vec<PathBuf>, GameError> {
+        let name = fold_key_name(game, self.turns);
+        let pk_path = dir.join(format!("{}.pk", name));
+        let vk_path = dir.join(format!("{}.vk", name));
+
+        // as with the inner keys the proving key is stored uncompressed to load quickly
+        self.pk.serialize_uncompressed(BufWriter::new(File::create(&pk_path)?)).map_err(proving)?;
+        self.vk.vk.serialize_compressed(BufWriter::new(File::create(&vk_path)?)).map_err(proving)?;
+        Ok(vec![pk_path, vk_path])
+    }
+
+    /**
+    *   loads the keys written by `save` for this game shape and number of turns. the
+    *   proving key is only used for our own proofs, so its points aren't checked
+    */
+    pub fn load(dir: &Path, game: &Game, turns: usize) -> Result<FoldKeys, GameError> {
+        let name = fold_key_name(game, turns);
+        let pk = ark_groth16_04::ProvingKey::deserialize_uncompressed_unchecked(BufReader::new(File::open(dir.join(format!("{}.pk", name)))?))
+            .map_err(proving)?;
+        Ok(FoldKeys { turns, pk, vk: load_fold_vk(dir, game, turns)? })
+    }
+}
+
+/// loads the outer verifying key for this game shape and number of turns, checking every point
+pub fn load_fold_vk(dir: &Path, game: &Game, turns: usize) -> Result<ark_groth16_04::PreparedVerifyingKey<BW6_761>, GameError> {
+    let path = dir.join(format!("{}.vk", fold_key_name(game, turns)));
+    let vk = ark_groth16_04::VerifyingKey::deserialize_compressed(BufReader::new(File::open(path)?)).map_err(proving)?;
+    Ok(ark_groth16_04::prepare_verifying_key(&vk))
+}
+
+/**
+*   file name (without extension) of the fold keys, which depend on the rule as well as the
+*   shape since it is a constant of the outer circuit, e.g. fold_3x3_2-1_poseidon_no-touch_5
+*/
+fn fold_key_name(game: &Game, turns: usize) -> String {
+    let fleet: Vec<String> = game.fleet().iter().map(|length| length.to_string()).collect();
+    format!("fold_{}_{}_{}_{}_{}", game.geometry(), fleet.join("-"), game.backend().name(), game.rule().name(), turns)
+}
+
+/// a folded game: the seat that won and the bw6-761 proof of it
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameProof {
+    pub winner: u8,
+    pub proof: ark_groth16_04::Proof<BW6_761>,
+}
+
+/**
+*   replays the transcript and builds the outer circuit for the game it records. only a
+*   game that replays as won can be folded, and its turns must all be single shots
+*/
+fn transcript_circuit(keys: &GameKeys<InnerSystem>, transcript: &Transcript<InnerSystem>) -> Result<GameFoldVerifier, GameError> {
+    let game = transcript.game()?;
+    check_foldable(&game)?;
+    let winner = match transcript.verify(keys)? {
+        Outcome::Won(seat) => seat,
+        outcome => return Err(GameError::BadInput(format!("only a won game can be folded, the transcript replays as {:?}", outcome))),
+    };
+
+    // a won game has both commitments and board proofs
+    let commitment = |seat: usize| transcript.commitments[seat].as_deref().ok_or_else(|| GameError::BadInput("a commitment is missing".to_string()));
+    let board_proof = |seat: usize| transcript.board_proofs[seat].as_ref().ok_or_else(|| GameError::BadInput("a board proof is missing".to_string()));
+    let mut circuit = placeholder_circuit(&game, keys, transcript.turns.len())?;
+    circuit.winner = winner;
+    circuit.commitments = commitment_inputs(&game, [commitment(0)?, commitment(1)?])?;
+    circuit.board_proofs = [inner_proof(board_proof(0)?)?, inner_proof(board_proof(1)?)?];
+    circuit.turns = transcript.turns.iter().map(|turn| Ok((turn.shots[0].target, turn.shots[0].hit, inner_proof(&turn.proof)?)))
+        .collect::<Result<_, GameError>>()?;
+    check_shape(&circuit)?;
+    Ok(circuit)
+}
+
+/**
+*   folds the won game a transcript records into one proof. the fold keys must be for the
+*   game's number of turns
+*/
+pub fn fold_transcript<R: RngCore + CryptoRng>(fold: &FoldKeys, keys: &GameKeys<InnerSystem>, transcript: &Transcript<InnerSystem>, rng: &mut R) -> Result<GameProof, GameError> {
+    if transcript.turns.len() != fold.turns {
+        return Err(GameError::BadInput(format!("the fold keys are for games of {} turns, not {}", fold.turns, transcript.turns.len())));
+    }
+    let circuit = transcript_circuit(keys, transcript)?;
+    let winner = circuit.winner;
+    let proof = Groth16::<BW6_761>::prove(&fold.pk, circuit, rng).map_err(proving)?;
+    Ok(GameProof { winner, proof })
+}
+
+/**
+*   checks a folded proof that the game with these commitments was won by the proof's
+*   winner, without any of its turns
+*/
+pub fn verify_game_proof(vk: &ark_groth16_04::PreparedVerifyingKey<BW6_761>, game: &Game, commitments: [&[u8]; 2], proof: &GameProof) -> Result<bool, GameError> {
+    check_foldable(game)?;
+    let mut inputs = vec![OuterField::from(proof.winner)];
+    for seat in commitment_inputs(game, commitments)? {
+        inputs.extend(seat);
+    }
+    if vk.vk.gamma_abc_g1.len() != inputs.len() + 1 {
+        return Err(GameError::BadInput("the fold keys weren't made for this game".to_string()));
+    }
+    Groth16::<BW6_761>::verify_with_processed_vk(vk, &inputs, &proof.proof).map_err(proving)
+}
+
+/**
+*   a folded game as it is handed to someone who wants to check it: the settings, the
+*   number of turns (which picks the outer keys), both commitments and the proof. the outer
+*   verifying key isn't in it, since a key that came with the proof could have been made by
+*   whoever proved it; the proof is checked against the local copy in KEY_DIR.
+*
+*   a file is the magic bytes, FOLD_VERSION and then the fields below in order
+*/
+pub const FOLD_MAGIC: &[u8; 4] = b"ZKBF";
+pub const FOLD_VERSION: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoldedGame {
+    pub geometry: BoardGeometry,
+    pub fleet: Vec<u8>,
+    pub backend: CommitmentBackend,
+    pub rule: AdjacencyRule,
+    pub turns: usize,
+    pub commitments: [Vec<u8>; 2],
+    pub proof: GameProof,
+}
+
+impl FoldedGame {
+    pub fn new(game: &Game, turns: usize, commitments: [&[u8]; 2], proof: GameProof) -> FoldedGame {
+        FoldedGame {
+            geometry: game.geometry(),
+            fleet: game.fleet().to_vec(),
+            backend: game.backend(),
+            rule: game.rule(),
+            turns,
+            commitments: [commitments[0].to_vec(), commitments[1].to_vec()],
+            proof,
+        }
+    }
+
+    /// the settings of the folded game, always single shots on bls12-377
+    pub fn game(&self) -> Result<Game, GameError> {
+        Game::new(self.geometry, self.fleet.clone(), self.backend)?.with_rule(self.rule).with_curve(Curve::Bls12_377)
+    }
+
+    /// checks the proof against an outer verifying key for the game's shape and length
+    pub fn verify(&self, vk: &ark_groth16_04::PreparedVerifyingKey<BW6_761>) -> Result<bool, GameError> {
+        verify_game_proof(vk, &self.game()?, [&self.commitments[0], &self.commitments[1]], &self.proof)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), GameError> {
+        writer.write_all(FOLD_MAGIC)?;
+        FOLD_VERSION.serialize(&mut writer)?;
+        self.geometry.width.serialize(&mut writer)?;
+        self.geometry.height.serialize(&mut writer)?;
+        self.fleet.serialize(&mut writer)?;
+        self.backend.to_u8().serialize(&mut writer)?;
+        self.rule.to_u8().serialize(&mut writer)?;
+        (self.turns as u32).serialize(&mut writer)?;
+        for commitment in &self.commitments {
+            commitment.serialize(&mut writer)?;
+        }
+        self.proof.winner.serialize(&mut writer)?;
+        self.proof.proof.serialize_compressed(&mut writer).map_err(proving)?;
+        Ok(writer.flush()?)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<FoldedGame, GameError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != FOLD_MAGIC {
+            return Err(GameError::BadInput("not a folded zk-battleships game".to_string()));
+        }
+        let version = u8::deserialize(&mut reader)?;
+        if version != FOLD_VERSION {
+            return Err(GameError::BadInput(format!("unsupported folded game version {}", version)));
+        }
+
+        let geometry = BoardGeometry::new(u8::deserialize(&mut reader)?, u8::deserialize(&mut reader)?);
+        let fleet = Vec::<u8>::deserialize(&mut reader)?;
+        let backend = CommitmentBackend::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        let rule = AdjacencyRule::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        let turns = u32::deserialize(&mut reader)? as usize;
+        let commitments = [Vec::<u8>::deserialize(&mut reader)?, Vec::<u8>::deserialize(&mut reader)?];
+        let winner = u8::deserialize(&mut reader)?;
+        let proof = ark_groth16_04::Proof::deserialize_compressed(&mut reader).map_err(proving)?;
+        Ok(FoldedGame { geometry, fleet, backend, rule, turns, commitments, proof: GameProof { winner, proof } })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GameError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: &Path) -> Result<FoldedGame, GameError> {
+        FoldedGame::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+fn folded_game() -> (Game, GameKeys<InnerSystem>, Transcript<InnerSystem>) {
+    use rand::rngs::OsRng;
+
+    let game = Game::new(BoardGeometry::square(3), vec![2, 1], CommitmentBackend::Poseidon).unwrap().with_curve(Curve::Bls12_377).unwrap();
+    let keys = GameKeys::<InnerSystem>::generate(game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut OsRng).unwrap();
+    let mut transcript = Transcript::new(&game, keys.fingerprint().unwrap());
+    let mut players = [game.new_player(), game.new_player()];
+    players[0].place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }]).unwrap();
+    players[1].place_fleet(&[Ship { origin: 1, orientation: Orientation::Vertical }, Ship { origin: 6, orientation: Orientation::Horizontal }]).unwrap();
+    let commitments = [players[0].commit(&mut OsRng).unwrap(), players[1].commit(&mut OsRng).unwrap()];
+    for seat in 0..2 {
+        transcript.record_commitment(seat as u8, &commitments[seat]);
+        transcript.record_board_proof(seat as u8, &players[seat].prove_board(&keys, &mut OsRng).unwrap());
+    }
+
+    // a sinks b's fleet while b misses twice
+    for (i, target) in [1, 2, 4, 3, 6].into_iter().enumerate() {
+        let (hit, proof) = players[1 - i % 2].answer(&keys, target, &mut OsRng).unwrap();
+        transcript.record_turn((i % 2) as u8, target, hit, &proof);
+    }
+    (game, keys, transcript)
+}
+
+#[test]
+fn test_fold_circuit() {
+    use ark_ff_04::BigInteger;
+    use ark_relations_04::r1cs::ConstraintSystem;
+
+    let (_, keys, transcript) = folded_game();
+    let satisfied = |circuit: GameFoldVerifier| {
+        let cs = ConstraintSystem::<OuterField>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    };
+    let circuit = transcript_circuit(&keys, &transcript).unwrap();
+    assert_eq!(circuit.winner, 0);
+    assert!(satisfied(circuit.clone()));
+
+    // claiming the other seat won isn't
+    let mut winner = circuit.clone();
+    winner.winner = 1;
+    assert!(!satisfied(winner));
+
+    // nor is a miss passed off as a hit
+    let mut lie = circuit.clone();
+    lie.turns[1].1 = true;
+    assert!(!satisfied(lie));
+
+    // nor a game that stops before the fleet is sunk
+    let mut short = circuit.clone();
+    short.turns.truncate(3);
+    assert!(!satisfied(short));
+
+    // nor one with the shot proofs for another board
+    let mut swapped = circuit.clone();
+    swapped.commitments.swap(0, 1);
+    assert!(!satisfied(swapped));
+
+    // nor a commitment input pushed past the inner modulus, which the verifier gadget
+    // would otherwise take as the same input
+    let mut shifted = circuit;
+    shifted.commitments[0][0] += OuterField::from_le_bytes_mod_order(&InnerField::MODULUS.to_bytes_le());
+    assert!(!satisfied(shifted));
+
+    // and an unfinished game isn't folded at all
+    let mut unfinished = transcript;
+    unfinished.turns.pop();
+    assert!(matches!(transcript_circuit(&keys, &unfinished), Err(GameError::BadInput(_))));
+}
+
+#[test]
+fn test_fold_game() {
+    use rand::rngs::OsRng;
+
+    // the outer keys take minutes to make for a game of any length, so this one is over
+    // in a single shot
+    let game = Game::new(BoardGeometry::new(2, 1), vec![1], CommitmentBackend::Poseidon).unwrap().with_curve(Curve::Bls12_377).unwrap();
+    let keys = GameKeys::<InnerSystem>::generate(game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut OsRng).unwrap();
+    let mut transcript = Transcript::new(&game, keys.fingerprint().unwrap());
+    let mut players = [game.new_player(), game.new_player()];
+    players[0].place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }]).unwrap();
+    players[1].place_fleet(&[Ship { origin: 1, orientation: Orientation::Horizontal }]).unwrap();
+    let commitments = [players[0].commit(&mut OsRng).unwrap(), players[1].commit(&mut OsRng).unwrap()];
+    for seat in 0..2 {
+        transcript.record_commitment(seat as u8, &commitments[seat]);
+        transcript.record_board_proof(seat as u8, &players[seat].prove_board(&keys, &mut OsRng).unwrap());
+    }
+    let (hit, proof) = players[1].answer(&keys, 1, &mut OsRng).unwrap();
+    transcript.record_turn(0, 1, hit, &proof);
+
+    // the keys are made once and folded and checked with from where they were saved
+    let dir = std::env::temp_dir().join(format!("zk-battleships-fold-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    FoldKeys::generate(&game, &keys, 1, &mut OsRng).unwrap().save(&dir, &game).unwrap();
+    let fold = FoldKeys::load(&dir, &game, 1).unwrap();
+    let proof = fold_transcript(&fold, &keys, &transcript, &mut OsRng).unwrap();
+    assert_eq!(proof.winner, 0);
+    assert!(verify_game_proof(&fold.vk, &game, [&commitments[0], &commitments[1]], &proof).unwrap());
+
+    // and comes back from its file format, which holds no key
+    let folded = FoldedGame::new(&game, 1, [&commitments[0], &commitments[1]], proof.clone());
+    let mut bytes = vec![];
+    folded.write(&mut bytes).unwrap();
+    let read = FoldedGame::read(bytes.as_slice()).unwrap();
+    assert_eq!(read, folded);
+    assert!(read.verify(&load_fold_vk(&dir, &read.game().unwrap(), read.turns).unwrap()).unwrap());
+    assert!(load_fold_vk(&dir, &read.game().unwrap(), 2).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // the proof is only for its winner and the boards it was made with
+    assert!(!verify_game_proof(&fold.vk, &game, [&commitments[1], &commitments[0]], &proof).unwrap());
+    assert!(!verify_game_proof(&fold.vk, &game, [&commitments[0], &commitments[1]], &GameProof { winner: 1, proof: proof.proof.clone() }).unwrap());
+
+    // and the keys only fold games of the length they were made for
+    let mut longer = transcript.clone();
+    longer.turns.push(transcript.turns[0].clone());
+    assert!(matches!(fold_transcript(&fold, &keys, &longer, &mut OsRng), Err(GameError::BadInput(_))));
+}
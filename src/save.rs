@@ -0,0 +1,305 @@
+use crate::bot::Difficulty;
+use crate::common::{Curve, GameSystem, System};
+use crate::error::GameError;
+use crate::game::{Game, PlayerSecrets, PlayerState};
+use crate::transcript::{self, Transcript};
+use argon2::Argon2;
+use crate::proof_system::{Groth16, ProofSystem};
+use ark_bls12_381::Bls12_381;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/**
+*   a game played at one console, saved after every turn so it can be resumed.
+*
+*   the transcript holds the settings, the commitments, the proofs and every turn so far,
+*   and each player's public state (commitments, views and the ships they have sunk) is
+*   saved as it is. their ships and secrets are written separately and, with a passphrase,
+*   encrypted with chacha20poly1305 under a key stretched from it with argon2. the
+*   encryption authenticates the public part too, so it can't be edited either.
+*
+*   a save file is the magic bytes, SAVE_VERSION, the transcript, the bot's difficulty,
+*   both players' public state and then the secrets, ark-serialize compressed encoding.
+*/
+pub const SAVE_DIR: &str = "saves";
+
+pub const SAVE_MAGIC: &[u8; 4] = b"ZKBS";
+pub const SAVE_VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+pub struct GameState<P: ProofSystem = Groth16<Bls12_381>> {
+    pub transcript: Transcript<P>,
+    /// players by seat, seat 0 fires first
+    pub players: [PlayerState; 2],
+    /// the difficulty of the bot playing seat 1, None if a person is
+    pub bot: Option<Difficulty>,
+}
+
+impl<P: GameSystem> GameState<P> {
+    /// a game that hasn't started yet, with both boards still empty
+    pub fn new(game: &Game, keys: Vec<u8>, bot: Option<Difficulty>) -> GameState<P> {
+        GameState {
+            transcript: Transcript::new(game, keys),
+            players: [game.new_player(), game.new_player()],
+            bot,
+        }
+    }
+
+    /// the number of turns played so far
+    pub fn turn(&self) -> usize {
+        self.transcript.turns.len()
+    }
+
+    /// the seat that fires next
+    pub fn next_shooter(&self) -> u8 {
+        (self.turn() % 2) as u8
+    }
+
+    /**
+    *   writes the game, encrypting the players' secrets if there is a passphrase. the salt
+    *   and nonce are drawn from the operating system every time, even in a seeded game,
+    *   so saving the same state twice never reuses a nonce under the same key
+    */
+    pub fn write<W: Write>(&self, mut writer: W, passphrase: Option<&str>) -> Result<(), GameError> {
+        let mut public = Vec::new();
+        public.write_all(SAVE_MAGIC)?;
+        SAVE_VERSION.serialize(&mut public)?;
+        self.transcript.write(&mut public)?;
+        self.bot.map(Difficulty::to_u8).serialize(&mut public)?;
+        for player in &self.players {
+            let mut state = Vec::new();
+            player.write_public(&mut state)?;
+            state.serialize(&mut public)?;
+        }
+
+        let mut secrets = Vec::new();
+        for player in &self.players {
+            player.player_secrets().serialize(&mut secrets)?;
+        }
+
+        writer.write_all(&public)?;
+        passphrase.is_some().serialize(&mut writer)?;
+        match passphrase {
+            None => secrets.serialize(&mut writer)?,
+            Some(passphrase) => {
+                let mut salt = [0u8; SALT_LEN];
+                let mut nonce = [0u8; NONCE_LEN];
+                OsRng.fill_bytes(&mut salt);
+                OsRng.fill_bytes(&mut nonce);
+                let sealed = cipher(passphrase, &salt)?
+                    .encrypt(Nonce::from_slice(&nonce), Payload { msg: &secrets, aad: &public })
+                    .map_err(|_| GameError::BadInput("the saved game couldn't be encrypted".to_string()))?;
+                salt.to_vec().serialize(&mut writer)?;
+                nonce.to_vec().serialize(&mut writer)?;
+                sealed.serialize(&mut writer)?;
+            }
+        }
+        Ok(writer.flush()?)
+    }
+
+    /**
+    *   reads a saved game. passphrase is only asked for if the secrets are encrypted, and a
+    *   wrong passphrase or a file that has been tampered with is refused
+    */
+    pub fn read<R: Read, F: FnOnce() -> Result<String, GameError>>(mut reader: R, passphrase: F) -> Result<GameState<P>, GameError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut cursor = bytes.as_slice();
+
+        read_header(&mut cursor)?;
+        let transcript = Transcript::read(&mut cursor)?;
+        let bot = match Option::<u8>::deserialize(&mut cursor)? {
+            Some(id) => Some(Difficulty::from_u8(id).ok_or(SerializationError::InvalidData)?),
+            None => None,
+        };
+        let public = [Vec::<u8>::deserialize(&mut cursor)?, Vec::<u8>::deserialize(&mut cursor)?];
+        let aad = &bytes[..bytes.len() - cursor.len()];
+
+        let encrypted = bool::deserialize(&mut cursor)?;
+        let secrets = if !encrypted {
+            Vec::<u8>::deserialize(&mut cursor)?
+        } else {
+            let salt = Vec::<u8>::deserialize(&mut cursor)?;
+            let nonce = Vec::<u8>::deserialize(&mut cursor)?;
+            let sealed = Vec::<u8>::deserialize(&mut cursor)?;
+            if salt.len() != SALT_LEN || nonce.len() != NONCE_LEN {
+                return Err(SerializationError::InvalidData.into());
+            }
+            cipher(&passphrase()?, &salt)?
+                .decrypt(Nonce::from_slice(&nonce), Payload { msg: &sealed, aad })
+                .map_err(|_| GameError::BadInput("the passphrase is wrong or the saved game has been tampered with".to_string()))?
+        };
+
+        let game = transcript.game()?;
+        let mut secrets = secrets.as_slice();
+        let a = PlayerState::read(&game, public[0].as_slice(), PlayerSecrets::deserialize(&mut secrets)?)?;
+        let b = PlayerState::read(&game, public[1].as_slice(), PlayerSecrets::deserialize(&mut secrets)?)?;
+        Ok(GameState { transcript, players: [a, b], bot })
+    }
+
+    /// saves to a temporary file first and moves it over path, so a save cut short never
+    /// replaces the last good one
+    pub fn save(&self, path: &Path, passphrase: Option<&str>) -> Result<(), GameError> {
+        let partial = path.with_extension("partial");
+        self.write(BufWriter::new(File::create(&partial)?), passphrase)?;
+        Ok(std::fs::rename(partial, path)?)
+    }
+
+    pub fn load<F: FnOnce() -> Result<String, GameError>>(path: &Path, passphrase: F) -> Result<GameState<P>, GameError> {
+        GameState::read(File::open(path)?, passphrase)
+    }
+}
+
+fn read_header<R: Read>(mut reader: R) -> Result<(), GameError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != SAVE_MAGIC {
+        return Err(GameError::BadInput("not a zk-battleships saved game".to_string()));
+    }
+    let version = u8::deserialize(&mut reader)?;
+    if version != SAVE_VERSION {
+        return Err(GameError::BadInput(format!("unsupported saved game version {}", version)));
+    }
+    Ok(())
+}
+
+/// the curve and proof system a saved game is proven with, read before the rest of it so
+/// resume can load the right keys
+pub fn saved_system(path: &Path) -> Result<(Curve, System), GameError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_header(&mut reader)?;
+    transcript::read_system(&mut reader)
+}
+
+/// the cipher keyed with the passphrase stretched over the salt
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, GameError> {
+    let mut key = [0u8; 32];
+    Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| GameError::BadInput(format!("the passphrase couldn't be stretched: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/**
+*   the test game after both board proofs and a shot each: seat 0 missed, seat 1 hit
+*/
+#[cfg(test)]
+fn game_in_progress(rng: &mut rand_chacha::ChaCha20Rng) -> (GameState, crate::keys::GameKeys) {
+    use crate::common::{Orientation, Ship};
+
+    let (game, keys) = crate::game::test_game();
+    let mut state = GameState::new(&game, keys.fingerprint().unwrap(), Some(Difficulty::Medium));
+    let [a, b] = &mut state.players;
+    a.place_fleet(&[
+        Ship { origin: 0, orientation: Orientation::Horizontal },
+        Ship { origin: 8, orientation: Orientation::Horizontal },
+    ]).unwrap();
+    b.place_fleet(&[
+        Ship { origin: 1, orientation: Orientation::Vertical },
+        Ship { origin: 6, orientation: Orientation::Horizontal },
+    ]).unwrap();
+    let (commitment_a, commitment_b) = (a.commit(rng).unwrap(), b.commit(rng).unwrap());
+    state.transcript.record_commitment(0, &commitment_a);
+    state.transcript.record_commitment(1, &commitment_b);
+    a.receive_commitment(commitment_b).unwrap();
+    b.receive_commitment(commitment_a).unwrap();
+
+    let (proof_a, proof_b) = (a.prove_board(&keys, rng).unwrap(), b.prove_board(&keys, rng).unwrap());
+    state.transcript.record_board_proof(0, &proof_a);
+    state.transcript.record_board_proof(1, &proof_b);
+    b.verify_board(&keys, proof_a).unwrap();
+    a.verify_board(&keys, proof_b).unwrap();
+
+    for (shooter, target) in [(0u8, 0u16), (1, 0)] {
+        let [a, b] = &mut state.players;
+        let (attacker, defender) = if shooter == 0 { (a, b) } else { (b, a) };
+        let (hit, proof) = defender.answer(&keys, target, rng).unwrap();
+        state.transcript.record_turn(shooter, target, hit, &proof);
+        attacker.verify_answer(&keys, target, hit, proof).unwrap();
+    }
+    (state, keys)
+}
+
+#[test]
+fn test_save_and_resume() {
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(18);
+    let (state, keys) = game_in_progress(&mut rng);
+
+    for passphrase in [None, Some("correct horse")] {
+        let mut bytes = Vec::new();
+        state.write(&mut bytes, passphrase).unwrap();
+        let mut resumed = GameState::<Groth16<Bls12_381>>::read(bytes.as_slice(), || Ok(passphrase.unwrap().to_string())).unwrap();
+
+        assert_eq!(resumed.transcript, state.transcript);
+        assert_eq!(resumed.bot, Some(Difficulty::Medium));
+        assert_eq!(resumed.turn(), 2);
+        assert_eq!(resumed.next_shooter(), 0);
+        for (restored, player) in resumed.players.iter().zip(&state.players) {
+            assert_eq!(restored.board(), player.board());
+            assert_eq!(restored.view(), player.view());
+            assert_eq!(restored.opponent_view(), player.opponent_view());
+            assert_eq!(restored.commitment(), player.commitment());
+            assert_eq!(restored.player_secrets(), player.player_secrets());
+        }
+
+        // the resumed game carries on, the restored secret still proves against the commitment
+        let [a, b] = &mut resumed.players;
+        let (hit, proof) = b.answer(&keys, 1, &mut rng).unwrap();
+        assert!(hit);
+        a.verify_answer(&keys, 1, hit, proof).unwrap();
+        assert!(!a.has_won().unwrap());
+    }
+}
+
+#[test]
+fn test_encrypted_save() {
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(180);
+    let (state, _) = game_in_progress(&mut rng);
+
+    // the passphrase is only asked for when the secrets are encrypted
+    let mut plain = Vec::new();
+    state.write(&mut plain, None).unwrap();
+    assert!(GameState::<Groth16<Bls12_381>>::read(plain.as_slice(), || panic!("no passphrase is needed")).is_ok());
+
+    let mut bytes = Vec::new();
+    state.write(&mut bytes, Some("correct horse")).unwrap();
+    let secret = state.players[0].player_secrets().secret.unwrap();
+    assert!(!bytes.windows(secret.len()).any(|w| w == secret.as_slice()));
+
+    // saving the same seeded game again draws a new salt and nonce
+    let mut again = Vec::new();
+    state.write(&mut again, Some("correct horse")).unwrap();
+    assert_eq!(again.len(), bytes.len());
+    assert_ne!(again, bytes);
+
+    // a wrong passphrase, an edited view or edited secrets are all refused
+    assert!(matches!(GameState::<Groth16<Bls12_381>>::read(bytes.as_slice(), || Ok("battery staple".to_string())), Err(GameError::BadInput(_))));
+    let secrets: usize = state.players.iter().map(|player| player.player_secrets().serialized_size()).sum();
+    let sealed = 1 + (8 + SALT_LEN) + (8 + NONCE_LEN) + (8 + secrets + 16);
+    // the last tile of seat 1's view of its own board, still a valid view when flipped
+    let view = bytes.len() - sealed - 1;
+    for tampered in [view, bytes.len() - 1] {
+        let mut edited = bytes.clone();
+        edited[tampered] ^= 1;
+        assert!(matches!(GameState::<Groth16<Bls12_381>>::read(edited.as_slice(), || Ok("correct horse".to_string())), Err(GameError::BadInput(_))));
+    }
+
+    // and so is a plaintext save whose secret doesn't open its commitment
+    let mut mixed = state.players[1].player_secrets();
+    mixed.secret = state.players[0].player_secrets().secret;
+    let mut public = Vec::new();
+    state.players[1].write_public(&mut public).unwrap();
+    let game = state.transcript.game().unwrap();
+    assert!(matches!(PlayerState::read(&game, public.as_slice(), mixed), Err(GameError::BadInput(_))));
+}
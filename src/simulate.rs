@@ -0,0 +1,278 @@
+use crate::bot::{choose_salvo, Bot};
+use crate::error::GameError;
+use crate::common::{GameSystem, Salvo};
+use crate::game::{Game, PlayerState, Sunk};
+use crate::keys::GameKeys;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/**
+*   headless bot-vs-bot games for regression testing the protocol and timing it.
+*
+*   every game places and commits both fleets. with keys, every board, shot or salvo, sunk
+*   ship and remaining ships proof is generated and verified through the same PlayerState calls as
+*   a real game and timed. without them the shots are answered straight from the
+*   defender's board, which plays the bots much faster.
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub games: usize,
+    /// games won by each seat, seat 0 fires first
+    pub wins: [usize; 2],
+    /// shots fired over all games
+    pub turns: usize,
+    pub proofs: u32,
+    pub proving: Duration,
+    pub verifications: u32,
+    pub verifying: Duration,
+}
+
+impl Report {
+    pub fn win_rate(&self, seat: u8) -> f64 {
+        self.wins[seat as usize] as f64 / self.games.max(1) as f64
+    }
+
+    pub fn average_turns(&self) -> f64 {
+        self.turns as f64 / self.games.max(1) as f64
+    }
+
+    /// mean time to generate one proof, None if no proofs were made
+    pub fn average_proving(&self) -> Option<Duration> {
+        (self.proofs > 0).then(|| self.proving / self.proofs)
+    }
+
+    /// mean time to verify one proof, None if no proofs were checked
+    pub fn average_verifying(&self) -> Option<Duration> {
+        (self.verifications > 0).then(|| self.verifying / self.verifications)
+    }
+
+    /// counts a proof generated since start
+    fn proved(&mut self, start: Instant) {
+        self.proving += start.elapsed();
+        self.proofs += 1;
+    }
+
+    /// counts a proof verified since start
+    fn verified(&mut self, start: Instant) {
+        self.verifying += start.elapsed();
+        self.verifications += 1;
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "games played:     {}", self.games)?;
+        writeln!(f, "seat A wins:      {} ({:.1}%)", self.wins[0], 100.0 * self.win_rate(0))?;
+        writeln!(f, "seat B wins:      {} ({:.1}%)", self.wins[1], 100.0 * self.win_rate(1))?;
+        write!(f, "average shots:    {:.1}", self.average_turns())?;
+        if let Some(proving) = self.average_proving() {
+            write!(f, "\nproofs generated: {} ({:.1?} each)", self.proofs, proving)?;
+        }
+        if let Some(verifying) = self.average_verifying() {
+            write!(f, "\nproofs verified:  {} ({:.1?} each)", self.verifications, verifying)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+*   plays the given number of games between the two bots, bots[0] firing first, and adds
+*   up the results. with keys every proof is generated and checked, and one that doesn't
+*   verify is returned as the ProofFailure it raises. placements, shots, commitment
+*   randomness and proofs are all drawn from rng, so a seeded rng replays the same games
+*/
+pub fn simulate<P: GameSystem, R: RngCore + CryptoRng>(game: &Game, keys: Option<&GameKeys<P>>, bots: [Bot; 2], games: usize, rng: &mut R) -> Result<Report, GameError> {
+    let mut report = Report::default();
+    for _ in 0..games {
+        let (winner, turns) = play_one(game, keys, bots, rng, &mut report)?;
+        report.games += 1;
+        report.wins[winner as usize] += 1;
+        report.turns += turns;
+    }
+    Ok(report)
+}
+
+/**
+*   plays one game to the end, returning the winning seat and the number of shots fired
+*/
+fn play_one<P: GameSystem, R: RngCore + CryptoRng>(game: &Game, keys: Option<&GameKeys<P>>, bots: [Bot; 2], rng: &mut R, report: &mut Report) -> Result<(u8, usize), GameError> {
+    let mut players = [game.new_player(), game.new_player()];
+    for (player, bot) in players.iter_mut().zip(bots) {
+        bot.place_fleet(player, rng)?;
+    }
+    let commitments = [players[0].commit(rng)?, players[1].commit(rng)?];
+    let [a, b] = &mut players;
+    a.receive_commitment(commitments[1].clone())?;
+    b.receive_commitment(commitments[0].clone())?;
+
+    if let Some(keys) = keys {
+        let start = Instant::now();
+        let proofs = [a.prove_board(keys, rng)?, b.prove_board(keys, rng)?];
+        report.proving += start.elapsed();
+        report.proofs += 2;
+
+        let start = Instant::now();
+        let [proof_a, proof_b] = proofs;
+        b.verify_board(keys, proof_a)?;
+        a.verify_board(keys, proof_b)?;
+        report.verifying += start.elapsed();
+        report.verifications += 2;
+    }
+
+    // without proofs the views and sunk ships are kept here rather than in PlayerState
+    let tiles = game.geometry().tiles();
+    let mut views = [vec![0u8; tiles], vec![0u8; tiles]];
+    let mut sunk: [Vec<u8>; 2] = [vec![], vec![]];
+
+    let mut shots = 0;
+    for turn in 0.. {
+        let shooter = turn % 2;
+        let (attacker, defender) = if shooter == 0 { (&mut *a, &mut *b) } else { (&mut *b, &mut *a) };
+        let won = match keys {
+            Some(keys) => {
+                let targets = bots[shooter].pick_salvo(attacker, rng)?;
+                shots += targets.len();
+                proven_turn(keys, &targets, attacker, defender, rng, report)?
+            }
+            None => {
+                let unattacked = views[shooter].iter().filter(|seen| **seen == 0).count();
+                let salvo = game.salvo().shots(game.fleet().len() - sunk[1 - shooter].len(), unattacked);
+                let targets = choose_salvo(bots[shooter].difficulty, game.geometry(), game.fleet(), &views[shooter], &sunk[shooter], salvo, rng)
+                    .ok_or_else(|| GameError::BadInput("every tile has already been attacked".to_string()))?;
+                shots += targets.len();
+                let mut won = false;
+                for target in targets {
+                    won = unproven_turn(defender, target, &mut views[shooter], &mut sunk[shooter]);
+                }
+                won
+            }
+        };
+        if won {
+            return Ok((shooter as u8, shots));
+        }
+    }
+    unreachable!()
+}
+
+/**
+*   one shot, or one salvo in a salvo game, answered and checked with proofs, true if it
+*   won the game
+*/
+fn proven_turn<P: GameSystem, R: RngCore + CryptoRng>(keys: &GameKeys<P>, targets: &[u16], attacker: &mut PlayerState, defender: &mut PlayerState, rng: &mut R, report: &mut Report) -> Result<bool, GameError> {
+    let start = Instant::now();
+    let sunk = match attacker.game().salvo() {
+        Salvo::Off => {
+            let (hit, proof) = defender.answer(keys, targets[0], rng)?;
+            report.proved(start);
+            let start = Instant::now();
+            attacker.verify_answer(keys, targets[0], hit, proof)?;
+            report.verified(start);
+            let start = Instant::now();
+            let sunk: Vec<(u16, Sunk<P>)> = defender.announce_sunk(keys, targets[0], rng)?.map(|sunk| (targets[0], sunk)).into_iter().collect();
+            report.proving += start.elapsed();
+            sunk
+        }
+        _ => {
+            let (hits, proof) = defender.answer_salvo(keys, targets, rng)?;
+            report.proved(start);
+            let start = Instant::now();
+            attacker.verify_salvo(keys, targets, &hits, proof)?;
+            report.verified(start);
+            let start = Instant::now();
+            let sunk = defender.announce_salvo_sunk(keys, targets, rng)?;
+            report.proving += start.elapsed();
+            sunk
+        }
+    };
+
+    for (target, sunk) in sunk {
+        report.proofs += 1;
+        let start = Instant::now();
+        attacker.verify_sunk(keys, target, sunk)?;
+        report.verified(start);
+    }
+
+    if !attacker.has_won()? {
+        return Ok(false);
+    }
+    let start = Instant::now();
+    let (remaining, proof) = defender.prove_remaining(keys, rng)?;
+    report.proved(start);
+    let start = Instant::now();
+    attacker.confirm_victory(keys, remaining, proof)?;
+    report.verified(start);
+    Ok(true)
+}
+
+/**
+*   one shot answered straight from the defender's board, true if it won the game
+*/
+fn unproven_turn(defender: &PlayerState, target: u16, view: &mut [u8], sunk: &mut Vec<u8>) -> bool {
+    let board = defender.board();
+    let number = board[target as usize];
+    view[target as usize] = if number != 0 { 2 } else { 1 };
+    if number != 0 && board.iter().zip(view.iter()).all(|(tile, seen)| *tile != number || *seen == 2) {
+        sunk.push(number - 1);
+    }
+    sunk.len() == defender.game().fleet().len()
+}
+
+#[test]
+fn test_simulate_without_proofs() {
+    use crate::bot::Difficulty;
+    use crate::commitment::CommitmentBackend;
+    use crate::common::BoardGeometry;
+    use rand::SeedableRng;
+
+    let game = Game::new(BoardGeometry::square(10), vec![5, 4, 3, 3, 2], CommitmentBackend::Blake2s).unwrap();
+    let bots = [Bot::new(Difficulty::Hard), Bot::new(Difficulty::Easy)];
+    let report = simulate(&game, None::<&GameKeys>, bots, 20, &mut rand::rngs::StdRng::seed_from_u64(7)).unwrap();
+    assert_eq!(report.games, 20);
+    assert_eq!(report.wins[0] + report.wins[1], 20);
+    assert!(report.wins[0] > report.wins[1]);
+    assert!(report.average_turns() >= 2.0 * 17.0 - 1.0);
+    assert_eq!(report.average_proving(), None);
+
+    // the same seed plays the same games
+    let again = simulate(&game, None::<&GameKeys>, bots, 20, &mut rand::rngs::StdRng::seed_from_u64(7)).unwrap();
+    assert_eq!(again, report);
+}
+
+#[test]
+fn test_simulate_with_proofs() {
+    use crate::bot::Difficulty;
+    use rand::SeedableRng;
+
+    let (game, keys) = crate::game::test_game();
+    let bots = [Bot::new(Difficulty::Medium), Bot::new(Difficulty::Hard)];
+    let report = simulate(&game, Some(&keys), bots, 2, &mut rand::rngs::StdRng::seed_from_u64(3)).unwrap();
+    assert_eq!(report.games, 2);
+
+    // two board proofs a game, one proof per shot and one remaining ships proof at the end,
+    // every one of them verified
+    assert!(report.proofs as usize >= 2 * (2 + 1) + report.turns);
+    assert_eq!(report.proofs, report.verifications);
+    assert!(report.average_verifying().is_some());
+}
+
+#[test]
+fn test_simulate_salvos() {
+    use crate::bot::Difficulty;
+    use crate::commitment::CommitmentBackend;
+    use crate::common::BoardGeometry;
+    use rand::SeedableRng;
+
+    // every shot is still counted, however many a turn has
+    let game = Game::new(BoardGeometry::square(10), vec![5, 4, 3, 3, 2], CommitmentBackend::Blake2s).unwrap().with_salvo(Salvo::Ships).unwrap();
+    let bots = [Bot::new(Difficulty::Hard), Bot::new(Difficulty::Hard)];
+    let report = simulate(&game, None::<&GameKeys>, bots, 10, &mut rand::rngs::StdRng::seed_from_u64(7)).unwrap();
+    assert_eq!(report.wins[0] + report.wins[1], 10);
+    assert!(report.average_turns() >= 17.0);
+
+    let game = Game::new(BoardGeometry::square(3), vec![2, 1], CommitmentBackend::Poseidon).unwrap().with_salvo(Salvo::Fixed(2)).unwrap();
+    let keys: GameKeys = GameKeys::generate(game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut rand::rngs::OsRng).unwrap();
+    let report = simulate(&game, Some(&keys), bots, 1, &mut rand::rngs::StdRng::seed_from_u64(3)).unwrap();
+    assert_eq!(report.games, 1);
+    assert_eq!(report.proofs, report.verifications);
+}
@@ -0,0 +1,551 @@
+use crate::common::*;
+use crate::commitment::{CircuitField, CommitmentBackend};
+use crate::error::GameError;
+use crate::game::{audit_board, verify_initial_proof, verify_remaining, verify_salvo, verify_shot, verify_sunk, Game, Reveal, Sunk};
+use crate::keys::GameKeys;
+use ark_bls12_381::Bls12_381;
+use crate::proof_system::{Groth16, ProofSystem};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+#[cfg(test)]
+use rand::rngs::OsRng;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/**
+*   a record of everything public in one game: the settings, the fingerprint of the keys,
+*   both commitments and board proofs, every turn's shots with their answers, proof and any
+*   sunk ship announcements, the loser's proof that none of their ships remain, and the boards revealed
+*   once the game was over.
+*
+*   players are numbered by seat, seat 0 fires first (player A locally, the host over the
+*   network). until the end-of-game reveal the transcript holds nothing either player kept
+*   secret, and anyone with the same keys can replay it with `verify` and confirm who won
+*   without trusting either side.
+*
+*   the binary writes one to TRANSCRIPT_DIR after every game. a transcript file is the magic
+*   bytes, TRANSCRIPT_VERSION, the curve and proof system the proofs are made with and then the fields below in order,
+*   ark-serialize compressed encoding.
+*/
+pub const TRANSCRIPT_DIR: &str = "transcripts";
+
+pub const TRANSCRIPT_MAGIC: &[u8; 4] = b"ZKBT";
+pub const TRANSCRIPT_VERSION: u8 = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transcript<P: ProofSystem = Groth16<Bls12_381>> {
+    pub geometry: BoardGeometry,
+    pub fleet: Vec<u8>,
+    pub backend: CommitmentBackend,
+    pub rule: AdjacencyRule,
+    pub salvo: Salvo,
+    /// fingerprint of the keys both players used, see GameKeys::fingerprint
+    pub keys: Vec<u8>,
+    /// each seat's board commitment, None if the game ended before it was sent
+    pub commitments: [Option<Vec<u8>>; 2],
+    /// each seat's board proof, None if the game ended before it was sent
+    pub board_proofs: [Option<P::Proof>; 2],
+    pub turns: Vec<Turn<P>>,
+    /// the last defender's proof that none of their ships remain after the shooter's hits
+    pub victory: Option<P::Proof>,
+    /// each seat's board, if they revealed it after the game
+    pub reveals: [Option<Reveal>; 2],
+}
+
+/// the shots of one turn and the defender's answer to them
+#[derive(Clone, Debug, PartialEq)]
+pub struct Turn<P: ProofSystem = Groth16<Bls12_381>> {
+    /// seat of the player who fired
+    pub shooter: u8,
+    /// a single shot, or every shot of the salvo in the order they were fired
+    pub shots: Vec<Shot<P>>,
+    /// a shot proof, or one salvo proof answering every shot
+    pub proof: P::Proof,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shot<P: ProofSystem = Groth16<Bls12_381>> {
+    pub target: u16,
+    pub hit: bool,
+    /// the defender's announcement that this hit sank a ship
+    pub sunk: Option<Sunk<P>>,
+}
+
+/// what a replayed transcript shows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// the player in this seat hit every tile of the opponent's fleet
+    Won(u8),
+    /// a commitment or proof from the player in this seat doesn't verify, or the board
+    /// they revealed doesn't match them
+    Cheated(u8),
+    /// the transcript stops before anyone has won
+    Unfinished,
+}
+
+impl<P: GameSystem> Transcript<P> {
+    /// an empty transcript for a game played with these keys
+    pub fn new(game: &Game, keys: Vec<u8>) -> Transcript<P> {
+        Transcript {
+            geometry: game.geometry(),
+            fleet: game.fleet().to_vec(),
+            backend: game.backend(),
+            rule: game.rule(),
+            salvo: game.salvo(),
+            keys,
+            commitments: [None, None],
+            board_proofs: [None, None],
+            turns: Vec::new(),
+            victory: None,
+            reveals: [None, None],
+        }
+    }
+
+    pub fn record_commitment(&mut self, seat: u8, commitment: &[u8]) {
+        self.commitments[seat as usize] = Some(commitment.to_vec());
+    }
+
+    pub fn record_board_proof(&mut self, seat: u8, proof: &P::Proof) {
+        self.board_proofs[seat as usize] = Some(proof.clone());
+    }
+
+    pub fn record_turn(&mut self, shooter: u8, target: u16, hit: bool, proof: &P::Proof) {
+        self.record_salvo(shooter, &[target], &[hit], proof);
+    }
+
+    pub fn record_salvo(&mut self, shooter: u8, targets: &[u16], hits: &[bool], proof: &P::Proof) {
+        let shots = targets.iter().zip(hits).map(|(target, hit)| Shot { target: *target, hit: *hit, sunk: None }).collect();
+        self.turns.push(Turn { shooter, shots, proof: proof.clone() });
+    }
+
+    /// attaches a sunk ship announcement to the shot at the target in the last turn
+    pub fn record_sunk(&mut self, target: u16, sunk: &Sunk<P>) {
+        if let Some(shot) = self.turns.last_mut().and_then(|turn| turn.shots.iter_mut().find(|shot| shot.target == target)) {
+            shot.sunk = Some(sunk.clone());
+        }
+    }
+
+    /// records the loser's proof that none of their ships remain
+    pub fn record_victory(&mut self, proof: &P::Proof) {
+        self.victory = Some(proof.clone());
+    }
+
+    pub fn record_reveal(&mut self, seat: u8, reveal: &Reveal) {
+        self.reveals[seat as usize] = Some(reveal.clone());
+    }
+
+    /// the settings the transcript was recorded under
+    pub fn game(&self) -> Result<Game, GameError> {
+        Ok(Game::new(self.geometry, self.fleet.clone(), self.backend)?.with_rule(self.rule).with_salvo(self.salvo)?.with_curve(P::Field::CURVE)?.with_system(P::SYSTEM))
+    }
+
+    /**
+    *   replays the game against the keys, checking every commitment and proof in the order
+    *   they were sent, then the loser's proof that no ship remains, then auditing any
+    *   revealed boards. the first thing that doesn't check out is reported as cheating by
+    *   its sender. a transcript that couldn't have come from a game at all (keys that don't
+    *   match, turns out of order or with the wrong number of shots, shots after the game
+    *   was won) is an error
+    */
+    pub fn verify(&self, keys: &GameKeys<P>) -> Result<Outcome, GameError> {
+        if keys.fingerprint()? != self.keys {
+            return Err(GameError::KeyMismatch);
+        }
+        let game = self.game()?;
+
+        let mut commitments = vec![];
+        for seat in 0..2u8 {
+            match &self.commitments[seat as usize] {
+                None => return Ok(Outcome::Unfinished),
+                Some(commitment) if !game.backend().is_well_formed(game.curve(), commitment) => return Ok(Outcome::Cheated(seat)),
+                Some(commitment) => commitments.push(commitment.as_slice()),
+            }
+        }
+        for seat in 0..2u8 {
+            let proof = match &self.board_proofs[seat as usize] {
+                None => return Ok(Outcome::Unfinished),
+                Some(proof) => proof.clone(),
+            };
+            if !verify_initial_proof::<P>(game.backend(), &keys.board_vk, commitments[seat as usize], game.fleet(), game.geometry(), game.rule(), proof)? {
+                return Ok(Outcome::Cheated(seat));
+            }
+        }
+
+        // views[seat] is what the player in that seat knows of the opponent's board, and
+        // sunk[seat] the opponent's ships proven sunk by it
+        let mut views = [vec![0u8; game.geometry().tiles()], vec![0u8; game.geometry().tiles()]];
+        let mut sunk: [Vec<u8>; 2] = [vec![], vec![]];
+        let mut outcome = Outcome::Unfinished;
+        for (i, turn) in self.turns.iter().enumerate() {
+            if outcome != Outcome::Unfinished {
+                return Err(GameError::BadInput("the transcript goes on after the game was won".to_string()));
+            }
+            if turn.shooter != (i % 2) as u8 {
+                return Err(GameError::BadInput(format!("turn {} was fired from seat {}, out of order", i, turn.shooter)));
+            }
+            let defender = 1 - turn.shooter;
+            let view = &mut views[turn.shooter as usize];
+            let unattacked = view.iter().filter(|seen| **seen == 0).count();
+            let shots = game.salvo().shots(game.fleet().len() - sunk[defender as usize].len(), unattacked);
+            if turn.shots.len() != shots {
+                return Err(GameError::BadInput(format!("turn {} has {} shots, not {}", i, turn.shots.len(), shots)));
+            }
+            for (j, shot) in turn.shots.iter().enumerate() {
+                match view.get(shot.target as usize) {
+                    None => return Err(GameError::OutOfBounds { target: shot.target, board_size: view.len() }),
+                    Some(0) if turn.shots[..j].iter().all(|other| other.target != shot.target) => {}
+                    Some(_) => return Err(GameError::RepeatedShot(shot.target)),
+                }
+            }
+
+            let commitment = commitments[defender as usize];
+            let targets: Vec<u16> = turn.shots.iter().map(|shot| shot.target).collect();
+            let hits: Vec<bool> = turn.shots.iter().map(|shot| shot.hit).collect();
+            let valid = match game.salvo() {
+                Salvo::Off => verify_shot::<P>(game.backend(), &keys.shot_vk, commitment, game.geometry(), targets[0], hits[0], turn.proof.clone())?,
+                _ => verify_salvo::<P>(game.backend(), keys.salvo()?.1, commitment, game.geometry(), &targets, &hits, game.max_salvo(), turn.proof.clone())?,
+            };
+            if !valid {
+                return Ok(Outcome::Cheated(defender));
+            }
+            for shot in &turn.shots {
+                view[shot.target as usize] = if shot.hit { 2 } else { 1 };
+            }
+
+            // a salvo's sinkings are proven against the hits of the whole salvo
+            let hits: Vec<bool> = view.iter().map(|seen| *seen == 2).collect();
+            for shot in &turn.shots {
+                if let Some(announced) = &shot.sunk {
+                    if !shot.hit || sunk[turn.shooter as usize].contains(&announced.ship) {
+                        return Ok(Outcome::Cheated(defender));
+                    }
+                    if !verify_sunk::<P>(game.backend(), &keys.sunk_vk, commitment, game.geometry(), shot.target, announced.ship, &hits, announced.proof.clone())? {
+                        return Ok(Outcome::Cheated(defender));
+                    }
+                    sunk[turn.shooter as usize].push(announced.ship);
+                }
+            }
+
+            if view.iter().filter(|tile| **tile == 2).count() == game.fleet_tiles() {
+                outcome = Outcome::Won(turn.shooter);
+            }
+        }
+
+        if let Some(proof) = &self.victory {
+            let (winner, loser) = match self.turns.last() {
+                Some(turn) => (turn.shooter, 1 - turn.shooter),
+                None => return Err(GameError::BadInput("a victory proof with no turns played".to_string())),
+            };
+            let hits: Vec<bool> = views[winner as usize].iter().map(|seen| *seen == 2).collect();
+            if !verify_remaining::<P>(game.backend(), &keys.remaining_vk, commitments[loser as usize], game.geometry(), game.fleet().len() as u8, &hits, 0, proof.clone())? {
+                return Ok(Outcome::Cheated(loser));
+            }
+            outcome = Outcome::Won(winner);
+        }
+
+        for seat in 0..2u8 {
+            if let Some(reveal) = &self.reveals[seat as usize] {
+                let opponent_view = &views[1 - seat as usize];
+                match audit_board(&game, commitments[seat as usize], opponent_view, &sunk[1 - seat as usize], reveal) {
+                    Ok(()) => {}
+                    Err(GameError::AuditFailure(_)) => return Ok(Outcome::Cheated(seat)),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), GameError> {
+        writer.write_all(TRANSCRIPT_MAGIC)?;
+        TRANSCRIPT_VERSION.serialize(&mut writer)?;
+        P::Field::CURVE.to_u8().serialize(&mut writer)?;
+        P::SYSTEM.to_u8().serialize(&mut writer)?;
+        self.geometry.width.serialize(&mut writer)?;
+        self.geometry.height.serialize(&mut writer)?;
+        self.fleet.serialize(&mut writer)?;
+        self.backend.to_u8().serialize(&mut writer)?;
+        self.rule.to_u8().serialize(&mut writer)?;
+        self.salvo.to_u8().serialize(&mut writer)?;
+        self.keys.serialize(&mut writer)?;
+        for seat in 0..2 {
+            self.commitments[seat].serialize(&mut writer)?;
+            self.board_proofs[seat].serialize(&mut writer)?;
+        }
+        (self.turns.len() as u32).serialize(&mut writer)?;
+        for turn in &self.turns {
+            turn.shooter.serialize(&mut writer)?;
+            (turn.shots.len() as u8).serialize(&mut writer)?;
+            for shot in &turn.shots {
+                shot.target.serialize(&mut writer)?;
+                shot.hit.serialize(&mut writer)?;
+                shot.sunk.serialize(&mut writer)?;
+            }
+            turn.proof.serialize(&mut writer)?;
+        }
+        self.victory.serialize(&mut writer)?;
+        for reveal in &self.reveals {
+            reveal.serialize(&mut writer)?;
+        }
+        Ok(writer.flush()?)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Transcript<P>, GameError> {
+        let (curve, system) = read_system(&mut reader)?;
+        if curve != P::Field::CURVE {
+            return Err(GameError::BadInput(format!("the transcript's proofs are on {}, not {}", curve.name(), P::Field::CURVE.name())));
+        }
+        if system != P::SYSTEM {
+            return Err(GameError::BadInput(format!("the transcript's proofs are {}, not {}", system.name(), P::SYSTEM.name())));
+        }
+
+        let geometry = BoardGeometry::new(u8::deserialize(&mut reader)?, u8::deserialize(&mut reader)?);
+        let fleet = Vec::<u8>::deserialize(&mut reader)?;
+        let backend = CommitmentBackend::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        let rule = AdjacencyRule::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        let salvo = Salvo::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        let keys = Vec::<u8>::deserialize(&mut reader)?;
+        let mut commitments = [None, None];
+        let mut board_proofs = [None, None];
+        for seat in 0..2 {
+            commitments[seat] = Option::<Vec<u8>>::deserialize(&mut reader)?;
+            board_proofs[seat] = Option::<P::Proof>::deserialize(&mut reader)?;
+        }
+
+        // turns are read one at a time, so a bad count can't allocate more than the file holds
+        let count = u32::deserialize(&mut reader)?;
+        let mut turns = Vec::new();
+        for _ in 0..count {
+            let shooter = u8::deserialize(&mut reader)?;
+            if shooter > 1 {
+                return Err(SerializationError::InvalidData.into());
+            }
+            let mut shots = vec![];
+            for _ in 0..u8::deserialize(&mut reader)? {
+                shots.push(Shot {
+                    target: u16::deserialize(&mut reader)?,
+                    hit: bool::deserialize(&mut reader)?,
+                    sunk: Option::<Sunk<P>>::deserialize(&mut reader)?,
+                });
+            }
+            turns.push(Turn { shooter, shots, proof: P::Proof::deserialize(&mut reader)? });
+        }
+
+        let victory = Option::<P::Proof>::deserialize(&mut reader)?;
+        let reveals = [Option::<Reveal>::deserialize(&mut reader)?, Option::<Reveal>::deserialize(&mut reader)?];
+
+        Ok(Transcript { geometry, fleet, backend, rule, salvo, keys, commitments, board_proofs, turns, victory, reveals })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GameError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: &Path) -> Result<Transcript<P>, GameError> {
+        Transcript::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/**
+*   reads a transcript's header up to the curve and proof system its proofs are made with,
+*   so the caller can pick the keys to read and verify the rest with
+*/
+pub fn read_system<R: Read>(mut reader: R) -> Result<(Curve, System), GameError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != TRANSCRIPT_MAGIC {
+        return Err(GameError::BadInput("not a zk-battleships transcript".to_string()));
+    }
+    let version = u8::deserialize(&mut reader)?;
+    if version != TRANSCRIPT_VERSION {
+        return Err(GameError::BadInput(format!("unsupported transcript version {}", version)));
+    }
+    let curve = Curve::from_u8(u8::deserialize(&mut reader)?).ok_or(SerializationError::InvalidData)?;
+    let system = System::from_u8(u8::deserialize(&mut reader)?).ok_or(SerializationError::InvalidData)?;
+    Ok((curve, system))
+}
+
+/**
+*   plays the test game to a win for seat 0, recording it as it goes, then has seat 1 prove
+*   its fleet is gone and reveals both boards
+*/
+#[cfg(test)]
+fn recorded_game() -> (Transcript, GameKeys) {
+    let (game, keys) = crate::game::test_game();
+    let mut transcript = Transcript::new(&game, keys.fingerprint().unwrap());
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+    a.place_fleet(&[
+        Ship { origin: 0, orientation: Orientation::Horizontal },
+        Ship { origin: 8, orientation: Orientation::Horizontal },
+    ]).unwrap();
+    b.place_fleet(&[
+        Ship { origin: 1, orientation: Orientation::Vertical },
+        Ship { origin: 6, orientation: Orientation::Horizontal },
+    ]).unwrap();
+
+    let commitment_a = a.commit(&mut OsRng).unwrap();
+    let commitment_b = b.commit(&mut OsRng).unwrap();
+    transcript.record_commitment(0, &commitment_a);
+    transcript.record_commitment(1, &commitment_b);
+    a.receive_commitment(commitment_b).unwrap();
+    b.receive_commitment(commitment_a).unwrap();
+
+    let proof_a = a.prove_board(&keys, &mut OsRng).unwrap();
+    let proof_b = b.prove_board(&keys, &mut OsRng).unwrap();
+    transcript.record_board_proof(0, &proof_a);
+    transcript.record_board_proof(1, &proof_b);
+    a.verify_board(&keys, proof_b).unwrap();
+    b.verify_board(&keys, proof_a).unwrap();
+
+    // a misses once and then sinks b's fleet, b only ever misses
+    for (a_target, b_target) in [(0, 2), (1, 3), (4, 4), (6, 5)] {
+        let (hit, proof) = b.answer(&keys, a_target, &mut OsRng).unwrap();
+        transcript.record_turn(0, a_target, hit, &proof);
+        a.verify_answer(&keys, a_target, hit, proof).unwrap();
+        if let Some(sunk) = b.announce_sunk(&keys, a_target, &mut OsRng).unwrap() {
+            transcript.record_sunk(a_target, &sunk);
+            a.verify_sunk(&keys, a_target, sunk).unwrap();
+        }
+        if a.has_won().unwrap() {
+            break;
+        }
+
+        let (hit, proof) = a.answer(&keys, b_target, &mut OsRng).unwrap();
+        transcript.record_turn(1, b_target, hit, &proof);
+        b.verify_answer(&keys, b_target, hit, proof).unwrap();
+    }
+    let (remaining, proof) = b.prove_remaining(&keys, &mut OsRng).unwrap();
+    assert_eq!(remaining, 0);
+    transcript.record_victory(&proof);
+    transcript.record_reveal(0, &a.reveal().unwrap());
+    transcript.record_reveal(1, &b.reveal().unwrap());
+    (transcript, keys)
+}
+
+#[test]
+fn test_transcript_replay() {
+    let (transcript, keys) = recorded_game();
+    assert_eq!(transcript.turns.len(), 7);
+    assert_eq!(transcript.turns.iter().filter(|turn| turn.shots[0].sunk.is_some()).count(), 2);
+    assert_eq!(transcript.verify(&keys).unwrap(), Outcome::Won(0));
+
+    // survives a round trip through its file format
+    let mut bytes = vec![];
+    transcript.write(&mut bytes).unwrap();
+    let read = Transcript::read(bytes.as_slice()).unwrap();
+    assert_eq!(read, transcript);
+    assert!(Transcript::<Groth16<Bls12_381>>::read(&bytes[1..]).is_err());
+
+    // a game cut short hasn't been won by anyone
+    let mut cut = transcript.clone();
+    cut.turns.truncate(3);
+    cut.victory = None;
+    assert_eq!(cut.verify(&keys).unwrap(), Outcome::Unfinished);
+    cut.board_proofs[1] = None;
+    assert_eq!(cut.verify(&keys).unwrap(), Outcome::Unfinished);
+}
+
+#[test]
+fn test_transcript_catches_cheating() {
+    let (transcript, keys) = recorded_game();
+
+    // b claiming a hit was a miss is caught on b
+    let mut lie = transcript.clone();
+    lie.turns[2].shots[0].hit = !lie.turns[2].shots[0].hit;
+    assert_eq!(lie.verify(&keys).unwrap(), Outcome::Cheated(1));
+
+    // and so is a board proof for the wrong commitment
+    let mut swapped = transcript.clone();
+    swapped.board_proofs.swap(0, 1);
+    assert_eq!(swapped.verify(&keys).unwrap(), Outcome::Cheated(0));
+
+    // as is a sunk ship announcement for the wrong ship
+    let mut sunk = transcript.clone();
+    sunk.turns[4].shots[0].sunk.as_mut().unwrap().ship = 1;
+    assert_eq!(sunk.verify(&keys).unwrap(), Outcome::Cheated(1));
+
+    // as is a victory proof that isn't for the loser's board
+    let mut victory = transcript.clone();
+    victory.victory = Some(transcript.turns[0].proof.clone());
+    assert_eq!(victory.verify(&keys).unwrap(), Outcome::Cheated(1));
+
+    // as is a revealed board that isn't the committed one
+    let mut reveal = transcript.clone();
+    reveal.reveals[0].as_mut().unwrap().ships[1].origin = 7;
+    assert_eq!(reveal.verify(&keys).unwrap(), Outcome::Cheated(0));
+
+    // turns out of order or past the end of the game aren't a game at all
+    let mut reordered = transcript.clone();
+    reordered.turns.swap(0, 1);
+    assert!(matches!(reordered.verify(&keys), Err(GameError::BadInput(_))));
+    let mut extra = transcript.clone();
+    extra.turns.push(extra.turns[1].clone());
+    assert!(matches!(extra.verify(&keys), Err(GameError::BadInput(_))));
+
+    // and it only replays against the keys it was played with
+    let mut other = transcript;
+    other.keys = vec![0; 32];
+    assert!(matches!(other.verify(&keys), Err(GameError::KeyMismatch)));
+}
+
+#[test]
+fn test_salvo_transcript() {
+    let game = Game::new(BoardGeometry::square(3), vec![2, 1], CommitmentBackend::Poseidon).unwrap().with_salvo(Salvo::Ships).unwrap();
+    let keys: GameKeys = GameKeys::generate(game.geometry(), game.fleet(), game.backend(), game.salvo(), &mut OsRng).unwrap();
+    let mut transcript = Transcript::new(&game, keys.fingerprint().unwrap());
+    let mut a = game.new_player();
+    let mut b = game.new_player();
+    a.place_fleet(&[Ship { origin: 0, orientation: Orientation::Horizontal }, Ship { origin: 8, orientation: Orientation::Horizontal }]).unwrap();
+    b.place_fleet(&[Ship { origin: 1, orientation: Orientation::Vertical }, Ship { origin: 6, orientation: Orientation::Horizontal }]).unwrap();
+    let commitment_a = a.commit(&mut OsRng).unwrap();
+    let commitment_b = b.commit(&mut OsRng).unwrap();
+    transcript.record_commitment(0, &commitment_a);
+    transcript.record_commitment(1, &commitment_b);
+    a.receive_commitment(commitment_b).unwrap();
+    b.receive_commitment(commitment_a).unwrap();
+    let proof_a = a.prove_board(&keys, &mut OsRng).unwrap();
+    let proof_b = b.prove_board(&keys, &mut OsRng).unwrap();
+    transcript.record_board_proof(0, &proof_a);
+    transcript.record_board_proof(1, &proof_b);
+    a.verify_board(&keys, proof_b).unwrap();
+    b.verify_board(&keys, proof_a).unwrap();
+
+    // a sinks b's longer ship with its first salvo, so b has one shot and a has two again
+    let salvos: [(u8, &[u16]); 3] = [(0, &[1, 4]), (1, &[2]), (0, &[6, 7])];
+    for (shooter, targets) in salvos {
+        let (attacker, defender) = if shooter == 0 { (&mut a, &mut b) } else { (&mut b, &mut a) };
+        let (hits, proof) = defender.answer_salvo(&keys, targets, &mut OsRng).unwrap();
+        transcript.record_salvo(shooter, targets, &hits, &proof);
+        attacker.verify_salvo(&keys, targets, &hits, proof).unwrap();
+        for (target, sunk) in defender.announce_salvo_sunk(&keys, targets, &mut OsRng).unwrap() {
+            transcript.record_sunk(target, &sunk);
+            attacker.verify_sunk(&keys, target, sunk).unwrap();
+        }
+    }
+    assert!(a.has_won().unwrap());
+    transcript.record_victory(&b.prove_remaining(&keys, &mut OsRng).unwrap().1);
+    assert_eq!(transcript.verify(&keys).unwrap(), Outcome::Won(0));
+
+    let mut bytes = vec![];
+    transcript.write(&mut bytes).unwrap();
+    assert_eq!(Transcript::read(bytes.as_slice()).unwrap(), transcript);
+
+    // a lie about one shot of a salvo is caught on the defender
+    let mut lie = transcript.clone();
+    lie.turns[0].shots[1].hit = false;
+    assert_eq!(lie.verify(&keys).unwrap(), Outcome::Cheated(1));
+
+    // a salvo with more shots than the shooter has ships afloat isn't a game at all
+    let mut extra = transcript.clone();
+    extra.turns[1].shots.push(Shot { target: 3, hit: false, sunk: None });
+    assert!(matches!(extra.verify(&keys), Err(GameError::BadInput(_))));
+
+    // and b keeping quiet about its sunk ship would have given it a second shot
+    let mut quiet = transcript;
+    quiet.turns[0].shots[0].sunk = None;
+    assert!(matches!(quiet.verify(&keys), Err(GameError::BadInput(_))));
+}